@@ -0,0 +1,49 @@
+-- Timestamps are stored as RFC 3339 text, as in SQLite, so that both
+-- backends compare and sort them the same way
+CREATE TABLE IF NOT EXISTS tokens (
+    token TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    webhook_url TEXT NOT NULL,
+    expires_at TEXT,
+    disabled BOOLEAN NOT NULL DEFAULT FALSE,
+    forward_url TEXT,
+    webhook_secret TEXT
+);
+
+CREATE TABLE IF NOT EXISTS webhook_requests (
+    id TEXT PRIMARY KEY,
+    date TEXT NOT NULL,
+    token_id TEXT NOT NULL REFERENCES tokens (token),
+    method TEXT NOT NULL,
+    value TEXT NOT NULL,
+    headers TEXT NOT NULL,
+    query_parameters TEXT NOT NULL,
+    body TEXT,
+    body_object TEXT,
+    message TEXT,
+    replayed_from TEXT,
+    forward_status BIGINT,
+    signature_valid BOOLEAN
+);
+
+CREATE TABLE IF NOT EXISTS responses (
+    token_id TEXT PRIMARY KEY REFERENCES tokens (token) ON DELETE CASCADE,
+    status_code BIGINT NOT NULL,
+    response_headers TEXT NOT NULL,
+    response_body BYTEA NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS token_ip_rules (
+    token_id TEXT NOT NULL REFERENCES tokens (token) ON DELETE CASCADE,
+    cidr TEXT NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('allow', 'deny'))
+);
+
+CREATE TABLE IF NOT EXISTS forward_attempts (
+    id BIGSERIAL PRIMARY KEY,
+    request_id TEXT NOT NULL REFERENCES webhook_requests (id) ON DELETE CASCADE,
+    attempt BIGINT NOT NULL,
+    status_code BIGINT,
+    error TEXT,
+    attempted_at TEXT NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE INDEX IF NOT EXISTS idx_webhook_requests_token_id ON webhook_requests (token_id);
+CREATE INDEX IF NOT EXISTS idx_webhook_requests_date ON webhook_requests (date);
+CREATE INDEX IF NOT EXISTS idx_tokens_label ON tokens (label);
+CREATE INDEX IF NOT EXISTS idx_token_ip_rules_token_id ON token_ip_rules (token_id);
+CREATE INDEX IF NOT EXISTS idx_forward_attempts_request_id ON forward_attempts (request_id);
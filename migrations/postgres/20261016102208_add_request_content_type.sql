@@ -0,0 +1,3 @@
+-- add_request_content_type
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS content_type TEXT;
+UPDATE webhook_requests SET content_type = headers::jsonb -> 'content-type' ->> 0;
@@ -0,0 +1,7 @@
+-- query_parameters_as_objects
+UPDATE webhook_requests
+SET query_parameters = (
+    SELECT json_agg(json_build_object(split_part(p, '=', 1), substr(p, strpos(p, '=') + 1)) ORDER BY ord)::text
+    FROM json_array_elements_text(query_parameters::json) WITH ORDINALITY AS q (p, ord)
+)
+WHERE query_parameters <> '[]';
@@ -0,0 +1,2 @@
+-- add_request_compressed_size
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS compressed_size BIGINT;
@@ -0,0 +1,2 @@
+-- add_request_body_encoding
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS body_encoding TEXT;
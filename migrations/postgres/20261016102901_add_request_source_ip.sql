@@ -0,0 +1,3 @@
+-- add_request_source_ip
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS source_ip TEXT;
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS peer_ip TEXT;
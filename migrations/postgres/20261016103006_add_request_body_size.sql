@@ -0,0 +1,3 @@
+-- add_request_body_size
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS body_size_bytes BIGINT;
+UPDATE webhook_requests SET body_size_bytes = COALESCE(octet_length(body), 0);
@@ -0,0 +1,2 @@
+-- add_token_max_requests
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS max_requests BIGINT;
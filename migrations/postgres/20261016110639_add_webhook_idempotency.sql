@@ -0,0 +1,10 @@
+-- add_webhook_idempotency
+CREATE TABLE IF NOT EXISTS webhook_idempotency (
+    token_id TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request_id TEXT NOT NULL REFERENCES webhook_requests (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (token_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_idempotency_created_at ON webhook_idempotency (created_at);
@@ -0,0 +1,5 @@
+-- add_request_body_sha256
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS body_sha256 TEXT;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS deduplicate_window_secs BIGINT;
+
+CREATE INDEX IF NOT EXISTS idx_body_sha256 ON webhook_requests (token_id, body_sha256);
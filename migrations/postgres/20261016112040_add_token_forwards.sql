@@ -0,0 +1,22 @@
+-- add_token_forwards
+CREATE TABLE IF NOT EXISTS token_forwards (
+    id TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL REFERENCES tokens (token) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    headers_json TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS forward_results (
+    id BIGSERIAL PRIMARY KEY,
+    forward_id TEXT NOT NULL REFERENCES token_forwards (id) ON DELETE CASCADE,
+    request_id TEXT NOT NULL REFERENCES webhook_requests (id) ON DELETE CASCADE,
+    status_code BIGINT,
+    error TEXT,
+    delivered_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_forwards_token_id ON token_forwards (token_id);
+CREATE INDEX IF NOT EXISTS idx_forward_results_forward_id ON forward_results (forward_id);
+CREATE INDEX IF NOT EXISTS idx_forward_results_request_id ON forward_results (request_id);
@@ -0,0 +1,7 @@
+-- add_request_comment
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS comment TEXT;
+
+-- Full-text index of comments; queries must use the same expression to hit it
+CREATE INDEX IF NOT EXISTS idx_webhook_requests_comment
+    ON webhook_requests USING GIN (to_tsvector('simple', comment))
+    WHERE comment IS NOT NULL;
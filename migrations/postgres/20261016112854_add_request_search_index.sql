@@ -0,0 +1,4 @@
+-- add_request_search_index
+-- Full-text index of request bodies and URLs for multi-word searches; queries must use the same expression
+CREATE INDEX IF NOT EXISTS idx_webhook_requests_search
+    ON webhook_requests USING GIN (to_tsvector('simple', COALESCE(body, '') || ' ' || value));
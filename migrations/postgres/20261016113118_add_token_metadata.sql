@@ -0,0 +1,9 @@
+-- add_token_metadata
+CREATE TABLE IF NOT EXISTS token_metadata (
+    token_id TEXT NOT NULL REFERENCES tokens (token) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (token_id, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_metadata_key_value ON token_metadata (key, value);
@@ -0,0 +1,5 @@
+-- add_header_redaction
+-- JSON array of header names; NULL uses REDACT_HEADERS
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS redact_headers TEXT;
+-- JSON array of the header names whose values were withheld
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS redacted_headers TEXT;
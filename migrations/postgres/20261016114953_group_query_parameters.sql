@@ -0,0 +1,14 @@
+-- group_query_parameters
+-- Query parameters were stored as a list of single-entry objects ([{"tag": "a"}, {"tag": "b"}]);
+-- group them into one object of value lists ({"tag": ["a", "b"]})
+UPDATE webhook_requests
+SET query_parameters = COALESCE((
+    SELECT json_object_agg(name, vals)::text
+    FROM (
+        SELECT param.key AS name, json_agg(param.value ORDER BY entry.position) AS vals
+        FROM json_array_elements(webhook_requests.query_parameters::json) WITH ORDINALITY AS entry (elem, position),
+            json_each_text(entry.elem) AS param
+        GROUP BY param.key
+    ) AS grouped
+), '{}')
+WHERE query_parameters LIKE '[%';
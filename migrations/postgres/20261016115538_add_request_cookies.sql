@@ -0,0 +1,3 @@
+-- add_request_cookies
+-- JSON object of cookie name to value, parsed from the Cookie header
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS cookies TEXT;
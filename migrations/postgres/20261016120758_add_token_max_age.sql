@@ -0,0 +1,3 @@
+-- add_token_max_age
+-- Webhooks whose Date/X-Timestamp is older than this many seconds are rejected; NULL disables the check
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS max_age_seconds BIGINT;
@@ -0,0 +1,12 @@
+-- add_webhook_nonces
+-- Webhooks must present an unused nonce from webhook_nonces
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS nonce_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS webhook_nonces (
+    nonce TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL REFERENCES tokens (token) ON DELETE CASCADE,
+    used_at TEXT,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_nonces_expires_at ON webhook_nonces (expires_at);
@@ -0,0 +1,5 @@
+-- add_token_notifications
+-- Lightweight POST sent to notification_url on the token's events
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS notification_url TEXT;
+-- JSON array of event names, e.g. ["webhook.received"]; NULL sends webhook.received only
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS notification_events TEXT;
@@ -0,0 +1,5 @@
+-- add_token_readonly
+-- Read-only tokens reject incoming webhooks but keep serving their stored requests
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS readonly BOOLEAN NOT NULL DEFAULT FALSE;
+-- SHA-256 hex digest of the key that may read (but not change) the token's requests
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS readonly_key TEXT;
@@ -0,0 +1,3 @@
+-- add_request_flags
+-- Requests marked for follow-up; retention pruning leaves them alone
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS flagged BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- add_token_max_payload
+-- Largest accepted webhook body in bytes; NULL uses the server's MAX_PAYLOAD_BYTES
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS max_payload_bytes BIGINT;
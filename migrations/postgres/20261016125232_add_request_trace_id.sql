@@ -0,0 +1,3 @@
+-- add_request_trace_id
+-- W3C trace ID of the request, from its traceparent header or generated on receipt
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS trace_id TEXT;
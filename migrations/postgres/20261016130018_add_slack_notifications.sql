@@ -0,0 +1,8 @@
+-- add_slack_notifications
+-- Slack incoming webhook posted to when a token receives a webhook
+CREATE TABLE IF NOT EXISTS slack_notifications (
+    token_id TEXT PRIMARY KEY REFERENCES tokens (token) ON DELETE CASCADE,
+    webhook_url TEXT NOT NULL,
+    template TEXT,
+    created_at TEXT NOT NULL
+);
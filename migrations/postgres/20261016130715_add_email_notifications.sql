@@ -0,0 +1,8 @@
+-- add_email_notifications
+-- Address emailed when a token's subscribed events occur; events is a JSON array
+CREATE TABLE IF NOT EXISTS email_notifications (
+    token_id TEXT PRIMARY KEY REFERENCES tokens (token) ON DELETE CASCADE,
+    to_address TEXT NOT NULL,
+    events TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
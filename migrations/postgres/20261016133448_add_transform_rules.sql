@@ -0,0 +1,3 @@
+-- add_transform_rules
+-- JSON array of rules applied to parsed webhook bodies before storage; NULL when none
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS transform_rules TEXT;
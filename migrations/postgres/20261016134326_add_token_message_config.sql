@@ -0,0 +1,3 @@
+-- add_token_message_config
+-- JSON object saying where a request's Message is taken from; NULL uses the default body fields
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS token_message_config TEXT;
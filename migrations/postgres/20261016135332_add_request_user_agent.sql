@@ -0,0 +1,6 @@
+-- add_request_user_agent
+-- Parsed User-Agent header; ua_client is 'library' for HTTP libraries and 'browser' for browsers
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS ua_browser TEXT;
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS ua_os TEXT;
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS ua_device_type TEXT;
+ALTER TABLE webhook_requests ADD COLUMN IF NOT EXISTS ua_client TEXT;
@@ -0,0 +1,31 @@
+-- add_requests_version
+-- Bumped whenever one of the token's requests is stored, changed or deleted, so the
+-- request log's ETag changes with it. Statement triggers bump each token once, however
+-- many of its requests a statement touches
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS requests_version BIGINT NOT NULL DEFAULT 0;
+
+CREATE OR REPLACE FUNCTION bump_requests_version() RETURNS trigger AS $$
+BEGIN
+    UPDATE tokens SET requests_version = requests_version + 1
+    WHERE token IN (SELECT DISTINCT token_id FROM changed_requests);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS webhook_requests_version_insert ON webhook_requests;
+CREATE TRIGGER webhook_requests_version_insert
+AFTER INSERT ON webhook_requests
+REFERENCING NEW TABLE AS changed_requests
+FOR EACH STATEMENT EXECUTE FUNCTION bump_requests_version();
+
+DROP TRIGGER IF EXISTS webhook_requests_version_update ON webhook_requests;
+CREATE TRIGGER webhook_requests_version_update
+AFTER UPDATE ON webhook_requests
+REFERENCING NEW TABLE AS changed_requests
+FOR EACH STATEMENT EXECUTE FUNCTION bump_requests_version();
+
+DROP TRIGGER IF EXISTS webhook_requests_version_delete ON webhook_requests;
+CREATE TRIGGER webhook_requests_version_delete
+AFTER DELETE ON webhook_requests
+REFERENCING OLD TABLE AS changed_requests
+FOR EACH STATEMENT EXECUTE FUNCTION bump_requests_version();
@@ -0,0 +1,51 @@
+CREATE TABLE IF NOT EXISTS tokens (
+    token TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    webhook_url TEXT NOT NULL,
+    expires_at TEXT,
+    disabled INTEGER NOT NULL DEFAULT 0,
+    forward_url TEXT,
+    webhook_secret TEXT
+);
+
+CREATE TABLE IF NOT EXISTS webhook_requests (
+    id TEXT PRIMARY KEY,
+    date TEXT NOT NULL,
+    token_id TEXT NOT NULL,
+    method TEXT NOT NULL,
+    value TEXT NOT NULL,
+    headers TEXT NOT NULL,
+    query_parameters TEXT NOT NULL,
+    body TEXT,
+    body_object TEXT,
+    message TEXT,
+    replayed_from TEXT,
+    forward_status INTEGER,
+    signature_valid INTEGER,
+    FOREIGN KEY (token_id) REFERENCES tokens (token)
+);
+
+CREATE TABLE IF NOT EXISTS responses (
+    token_id TEXT PRIMARY KEY,
+    status_code INTEGER NOT NULL,
+    response_headers TEXT NOT NULL,
+    response_body TEXT NOT NULL,
+    FOREIGN KEY (token_id) REFERENCES tokens (token) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS token_ip_rules (
+    token_id TEXT NOT NULL,
+    cidr TEXT NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('allow', 'deny')),
+    FOREIGN KEY (token_id) REFERENCES tokens (token) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS forward_attempts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    attempted_at TEXT NOT NULL,
+    FOREIGN KEY (request_id) REFERENCES webhook_requests (id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+-- add_request_content_type
+ALTER TABLE webhook_requests ADD COLUMN content_type TEXT;
+UPDATE webhook_requests SET content_type = json_extract(headers, '$."content-type"[0]');
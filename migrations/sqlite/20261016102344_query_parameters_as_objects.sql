@@ -0,0 +1,9 @@
+-- query_parameters_as_objects
+UPDATE webhook_requests
+SET query_parameters = (
+    SELECT json_group_array(
+        json_object(substr(p.value, 1, instr(p.value, '=') - 1), substr(p.value, instr(p.value, '=') + 1))
+    )
+    FROM json_each(webhook_requests.query_parameters) AS p
+)
+WHERE query_parameters <> '[]';
@@ -0,0 +1,2 @@
+-- add_request_body_encoding
+ALTER TABLE webhook_requests ADD COLUMN body_encoding TEXT;
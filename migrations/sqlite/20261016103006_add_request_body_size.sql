@@ -0,0 +1,3 @@
+-- add_request_body_size
+ALTER TABLE webhook_requests ADD COLUMN body_size_bytes INTEGER;
+UPDATE webhook_requests SET body_size_bytes = COALESCE(length(CAST(body AS BLOB)), 0);
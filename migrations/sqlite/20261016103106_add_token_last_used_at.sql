@@ -0,0 +1,3 @@
+-- add_token_last_used_at
+ALTER TABLE tokens ADD COLUMN last_used_at TEXT;
+UPDATE tokens SET last_used_at = (SELECT MAX(date) FROM webhook_requests WHERE token_id = tokens.token);
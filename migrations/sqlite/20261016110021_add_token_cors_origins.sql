@@ -0,0 +1,2 @@
+-- add_token_cors_origins
+ALTER TABLE tokens ADD COLUMN cors_origins TEXT;
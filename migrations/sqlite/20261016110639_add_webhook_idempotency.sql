@@ -0,0 +1,11 @@
+-- add_webhook_idempotency
+CREATE TABLE IF NOT EXISTS webhook_idempotency (
+    token_id TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (token_id, idempotency_key),
+    FOREIGN KEY (request_id) REFERENCES webhook_requests (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_idempotency_created_at ON webhook_idempotency (created_at);
@@ -0,0 +1,5 @@
+-- add_request_body_sha256
+ALTER TABLE webhook_requests ADD COLUMN body_sha256 TEXT;
+ALTER TABLE tokens ADD COLUMN deduplicate_window_secs INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_body_sha256 ON webhook_requests (token_id, body_sha256);
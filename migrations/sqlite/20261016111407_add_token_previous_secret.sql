@@ -0,0 +1,3 @@
+-- add_token_previous_secret
+ALTER TABLE tokens ADD COLUMN webhook_secret_prev TEXT;
+ALTER TABLE tokens ADD COLUMN webhook_secret_prev_expires_at TEXT;
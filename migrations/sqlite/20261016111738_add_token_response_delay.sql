@@ -0,0 +1,2 @@
+-- add_token_response_delay
+ALTER TABLE tokens ADD COLUMN response_delay_ms INTEGER;
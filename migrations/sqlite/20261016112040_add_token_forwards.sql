@@ -0,0 +1,25 @@
+-- add_token_forwards
+CREATE TABLE IF NOT EXISTS token_forwards (
+    id TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    headers_json TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (token_id) REFERENCES tokens (token) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS forward_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    forward_id TEXT NOT NULL,
+    request_id TEXT NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    delivered_at TEXT NOT NULL,
+    FOREIGN KEY (forward_id) REFERENCES token_forwards (id) ON DELETE CASCADE,
+    FOREIGN KEY (request_id) REFERENCES webhook_requests (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_forwards_token_id ON token_forwards (token_id);
+CREATE INDEX IF NOT EXISTS idx_forward_results_forward_id ON forward_results (forward_id);
+CREATE INDEX IF NOT EXISTS idx_forward_results_request_id ON forward_results (request_id);
@@ -0,0 +1,25 @@
+-- add_request_comment
+ALTER TABLE webhook_requests ADD COLUMN comment TEXT;
+
+-- Full-text index of comments, kept in sync with webhook_requests by the triggers below
+CREATE VIRTUAL TABLE IF NOT EXISTS webhook_request_comments USING fts5 (request_id UNINDEXED, comment);
+
+CREATE TRIGGER IF NOT EXISTS webhook_request_comments_insert
+AFTER INSERT ON webhook_requests WHEN new.comment IS NOT NULL
+BEGIN
+    INSERT INTO webhook_request_comments (request_id, comment) VALUES (new.id, new.comment);
+END;
+
+CREATE TRIGGER IF NOT EXISTS webhook_request_comments_update
+AFTER UPDATE OF comment ON webhook_requests
+BEGIN
+    DELETE FROM webhook_request_comments WHERE request_id = old.id;
+    INSERT INTO webhook_request_comments (request_id, comment)
+    SELECT new.id, new.comment WHERE new.comment IS NOT NULL;
+END;
+
+CREATE TRIGGER IF NOT EXISTS webhook_request_comments_delete
+AFTER DELETE ON webhook_requests WHEN old.comment IS NOT NULL
+BEGIN
+    DELETE FROM webhook_request_comments WHERE request_id = old.id;
+END;
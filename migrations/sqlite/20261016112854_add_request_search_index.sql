@@ -0,0 +1,29 @@
+-- add_request_search_index
+-- Full-text index of request bodies and URLs for multi-word searches. The index refers to
+-- rows by rowid instead of keeping its own copy of the bodies, so it has to be rebuilt
+-- (INSERT INTO webhook_requests_fts (webhook_requests_fts) VALUES ('rebuild')) after a VACUUM
+CREATE VIRTUAL TABLE IF NOT EXISTS webhook_requests_fts
+USING fts5 (body, value, content = 'webhook_requests', content_rowid = 'rowid');
+
+INSERT INTO webhook_requests_fts (webhook_requests_fts) VALUES ('rebuild');
+
+CREATE TRIGGER IF NOT EXISTS webhook_requests_fts_insert
+AFTER INSERT ON webhook_requests
+BEGIN
+    INSERT INTO webhook_requests_fts (rowid, body, value) VALUES (new.rowid, new.body, new.value);
+END;
+
+CREATE TRIGGER IF NOT EXISTS webhook_requests_fts_delete
+AFTER DELETE ON webhook_requests
+BEGIN
+    INSERT INTO webhook_requests_fts (webhook_requests_fts, rowid, body, value)
+    VALUES ('delete', old.rowid, old.body, old.value);
+END;
+
+CREATE TRIGGER IF NOT EXISTS webhook_requests_fts_update
+AFTER UPDATE OF body, value ON webhook_requests
+BEGIN
+    INSERT INTO webhook_requests_fts (webhook_requests_fts, rowid, body, value)
+    VALUES ('delete', old.rowid, old.body, old.value);
+    INSERT INTO webhook_requests_fts (rowid, body, value) VALUES (new.rowid, new.body, new.value);
+END;
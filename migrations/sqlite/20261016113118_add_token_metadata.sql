@@ -0,0 +1,10 @@
+-- add_token_metadata
+CREATE TABLE IF NOT EXISTS token_metadata (
+    token_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (token_id, key),
+    FOREIGN KEY (token_id) REFERENCES tokens (token) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_metadata_key_value ON token_metadata (key, value);
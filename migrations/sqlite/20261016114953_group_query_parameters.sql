@@ -0,0 +1,13 @@
+-- group_query_parameters
+-- Query parameters were stored as a list of single-entry objects ([{"tag": "a"}, {"tag": "b"}]);
+-- group them into one object of value lists ({"tag": ["a", "b"]})
+UPDATE webhook_requests
+SET query_parameters = COALESCE((
+    SELECT json_group_object(name, json(vals))
+    FROM (
+        SELECT param.key AS name, json_group_array(param.value) AS vals
+        FROM json_each(webhook_requests.query_parameters) AS entry, json_each(entry.value) AS param
+        GROUP BY param.key
+    )
+), '{}')
+WHERE json_type(query_parameters) = 'array';
@@ -0,0 +1,3 @@
+-- add_allowed_methods
+-- JSON array of the HTTP methods webhooks may use; NULL allows all
+ALTER TABLE tokens ADD COLUMN allowed_methods TEXT;
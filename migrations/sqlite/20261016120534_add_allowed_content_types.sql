@@ -0,0 +1,3 @@
+-- add_allowed_content_types
+-- JSON array of the media types webhooks may be sent as; NULL allows all
+ALTER TABLE tokens ADD COLUMN allowed_content_types TEXT;
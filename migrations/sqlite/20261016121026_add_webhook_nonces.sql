@@ -0,0 +1,13 @@
+-- add_webhook_nonces
+-- Webhooks must present an unused nonce from webhook_nonces
+ALTER TABLE tokens ADD COLUMN nonce_required INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS webhook_nonces (
+    nonce TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    used_at TEXT,
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (token_id) REFERENCES tokens (token) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_nonces_expires_at ON webhook_nonces (expires_at);
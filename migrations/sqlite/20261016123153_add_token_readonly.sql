@@ -0,0 +1,5 @@
+-- add_token_readonly
+-- Read-only tokens reject incoming webhooks but keep serving their stored requests
+ALTER TABLE tokens ADD COLUMN readonly INTEGER NOT NULL DEFAULT 0;
+-- SHA-256 hex digest of the key that may read (but not change) the token's requests
+ALTER TABLE tokens ADD COLUMN readonly_key TEXT;
@@ -0,0 +1,3 @@
+-- add_request_flags
+-- Requests marked for follow-up; retention pruning leaves them alone
+ALTER TABLE webhook_requests ADD COLUMN flagged INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- add_forward_region
+-- Region a mirror target runs in; instances prefer targets in their own SERVICE_REGION
+ALTER TABLE token_forwards ADD COLUMN forward_region TEXT;
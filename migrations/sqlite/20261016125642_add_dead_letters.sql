@@ -0,0 +1,15 @@
+-- add_dead_letters
+-- Forward deliveries that still failed after all retries, kept until requeued or discarded
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    request_id TEXT NOT NULL,
+    forward_url TEXT NOT NULL,
+    last_error TEXT,
+    failed_at TEXT NOT NULL,
+    FOREIGN KEY (token_id) REFERENCES tokens (token) ON DELETE CASCADE,
+    FOREIGN KEY (request_id) REFERENCES webhook_requests (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_dead_letters_token_id ON dead_letters (token_id);
+CREATE INDEX IF NOT EXISTS idx_dead_letters_request_id ON dead_letters (request_id);
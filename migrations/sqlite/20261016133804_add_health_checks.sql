@@ -0,0 +1,10 @@
+-- add_health_checks
+-- Results of the periodic and on-demand database checks; result is "ok" or one problem per line
+CREATE TABLE IF NOT EXISTS health_checks (
+    id TEXT PRIMARY KEY,
+    checked_at TEXT NOT NULL,
+    check_type TEXT NOT NULL,
+    result TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_health_checks_type_checked_at ON health_checks (check_type, checked_at);
@@ -0,0 +1,5 @@
+-- add_request_geolocation
+-- Location of source_ip from the GEO_DB_PATH database; NULL when it is not configured or has no entry
+ALTER TABLE webhook_requests ADD COLUMN source_country TEXT;
+ALTER TABLE webhook_requests ADD COLUMN source_city TEXT;
+ALTER TABLE webhook_requests ADD COLUMN source_asn INTEGER;
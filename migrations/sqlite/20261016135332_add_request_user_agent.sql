@@ -0,0 +1,6 @@
+-- add_request_user_agent
+-- Parsed User-Agent header; ua_client is 'library' for HTTP libraries and 'browser' for browsers
+ALTER TABLE webhook_requests ADD COLUMN ua_browser TEXT;
+ALTER TABLE webhook_requests ADD COLUMN ua_os TEXT;
+ALTER TABLE webhook_requests ADD COLUMN ua_device_type TEXT;
+ALTER TABLE webhook_requests ADD COLUMN ua_client TEXT;
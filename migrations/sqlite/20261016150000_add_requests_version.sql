@@ -0,0 +1,22 @@
+-- add_requests_version
+-- Bumped whenever one of the token's requests is stored, changed or deleted, so the
+-- request log's ETag changes with it
+ALTER TABLE tokens ADD COLUMN requests_version INTEGER NOT NULL DEFAULT 0;
+
+CREATE TRIGGER IF NOT EXISTS webhook_requests_version_insert
+AFTER INSERT ON webhook_requests
+BEGIN
+    UPDATE tokens SET requests_version = requests_version + 1 WHERE token = NEW.token_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS webhook_requests_version_update
+AFTER UPDATE ON webhook_requests
+BEGIN
+    UPDATE tokens SET requests_version = requests_version + 1 WHERE token = NEW.token_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS webhook_requests_version_delete
+AFTER DELETE ON webhook_requests
+BEGIN
+    UPDATE tokens SET requests_version = requests_version + 1 WHERE token = OLD.token_id;
+END;
@@ -0,0 +1,19 @@
+#!/usr/bin/env bash
+# Create an empty, timestamped migration for both database backends.
+# Usage: scripts/create_migration.sh add_something
+set -euo pipefail
+
+if [ $# -ne 1 ]; then
+    echo "Usage: $0 <description>" >&2
+    exit 1
+fi
+
+description=$(echo "$1" | tr '[:upper:] -' '[:lower:]__')
+version=$(date -u +%Y%m%d%H%M%S)
+root=$(cd "$(dirname "$0")/.." && pwd)
+
+for backend in sqlite postgres; do
+    file="$root/migrations/$backend/${version}_${description}.sql"
+    echo "-- $1" > "$file"
+    echo "Created $file"
+done
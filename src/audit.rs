@@ -0,0 +1,60 @@
+use std::future::Future;
+use std::net::IpAddr;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::database::DatabaseBackend;
+use crate::models::AuditEntry;
+
+tokio::task_local! {
+    /// Address of the client whose API request is being handled
+    static ACTOR_IP: Option<IpAddr>;
+}
+
+/// Run `future` with `ip` recorded as the actor of any audit entries it appends
+pub async fn with_actor<F: Future>(ip: Option<IpAddr>, future: F) -> F::Output {
+    ACTOR_IP.scope(ip, future).await
+}
+
+fn actor_ip() -> Option<String> {
+    ACTOR_IP
+        .try_with(|ip| ip.map(|ip| ip.to_string()))
+        .ok()
+        .flatten()
+}
+
+/// Append an entry to the audit log. A failure is logged rather than returned, as the
+/// change it describes has already been made
+pub async fn record(
+    db: &dyn DatabaseBackend,
+    action: &str,
+    token_id: Option<&str>,
+    details: Option<serde_json::Value>,
+) {
+    record_many(db, action, [(token_id, details)]).await;
+}
+
+/// Append an entry per `(token_id, details)` pair in one insert, e.g. for a batch of tokens
+pub async fn record_many<'a>(
+    db: &dyn DatabaseBackend,
+    action: &str,
+    entries: impl IntoIterator<Item = (Option<&'a str>, Option<serde_json::Value>)>,
+) {
+    let occurred_at = chrono::Utc::now().to_rfc3339();
+    let actor_ip = actor_ip();
+    let entries: Vec<AuditEntry> = entries
+        .into_iter()
+        .map(|(token_id, details)| AuditEntry {
+            id: Uuid::new_v4().to_string(),
+            occurred_at: occurred_at.clone(),
+            action: action.to_string(),
+            actor_ip: actor_ip.clone(),
+            token_id: token_id.map(str::to_string),
+            details,
+        })
+        .collect();
+
+    if let Err(e) = db.append_audit(&entries).await {
+        warn!("Failed to append audit entry {}: {}", action, e);
+    }
+}
@@ -0,0 +1,67 @@
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::models::AuthToken;
+
+/// Lifetime of JWTs issued by `POST /api/auth/token`
+const ADMIN_TOKEN_TTL_SECS: u64 = 60 * 60;
+
+/// Scope of JWTs that grant access to the whole admin API
+pub const SCOPE_ADMIN: &str = "admin";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// Expiry as a Unix timestamp
+    pub exp: u64,
+    /// Issue time as a Unix timestamp
+    pub issued_at: u64,
+    pub token_scope: String,
+    /// The webhook token a token-scoped JWT is limited to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+}
+
+/// Sign an admin-scoped JWT with `secret` (HS256)
+pub fn issue_admin_token(secret: &str) -> Result<AuthToken, AppError> {
+    let now = jsonwebtoken::get_current_timestamp();
+    let claims = Claims {
+        exp: now + ADMIN_TOKEN_TTL_SECS,
+        issued_at: now,
+        token_scope: SCOPE_ADMIN.to_string(),
+        token_id: None,
+    };
+
+    let token = jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .map_err(|e| {
+        warn!("Failed to sign JWT: {}", e);
+        AppError::internal()
+    })?;
+
+    Ok(AuthToken {
+        token,
+        token_type: "Bearer",
+        expires_in: ADMIN_TOKEN_TTL_SECS,
+    })
+}
+
+/// Check the signature and expiry of a JWT and that it grants admin access
+pub fn verify_admin_token(token: &str, key: &DecodingKey) -> Result<Claims, AppError> {
+    let claims = jsonwebtoken::decode::<Claims>(token, key, &Validation::new(Algorithm::HS256))
+        .map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => AppError::AuthTokenExpired,
+            _ => AppError::Unauthorized,
+        })?
+        .claims;
+
+    if claims.token_scope != SCOPE_ADMIN || claims.token_id.is_some() {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(claims)
+}
@@ -0,0 +1,159 @@
+use base64::Engine;
+use serde_json::{Map, Value, json};
+use std::convert::Infallible;
+use std::io::Read;
+use tracing::warn;
+
+use crate::error::ParseError;
+
+/// File parts up to this size are kept in `body_object` as base64; larger ones only by size
+const MAX_INLINE_FILE_SIZE: usize = 64 * 1024;
+
+/// Decoded bodies this many times larger than the compressed input are treated as zip bombs
+const MAX_COMPRESSION_RATIO: usize = 1000;
+
+/// Decode a body sent with `Content-Encoding`, reading at most `limit + 1` decoded bytes.
+/// Returns `None` for `identity` and encodings that are not supported.
+pub fn decompress(
+    encoding: &str,
+    body: &[u8],
+    limit: usize,
+) -> Result<Option<Vec<u8>>, ParseError> {
+    let encodings: Vec<String> = encoding
+        .split(',')
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty() && encoding != "identity")
+        .collect();
+    let supported =
+        |encoding: &String| matches!(encoding.as_str(), "gzip" | "x-gzip" | "deflate" | "br");
+    if encodings.is_empty() || !encodings.iter().all(supported) {
+        return Ok(None);
+    }
+
+    // Encodings are listed in the order they were applied
+    let mut decoded = body.to_vec();
+    for encoding in encodings.iter().rev() {
+        decoded = decode(encoding, &decoded, limit).map_err(|e| ParseError::Encoding {
+            encoding: encoding.clone(),
+            reason: e.to_string(),
+        })?;
+        if decoded.len() > limit {
+            return Err(ParseError::TooLarge { limit });
+        }
+    }
+
+    if decoded.len() > body.len().max(1) * MAX_COMPRESSION_RATIO {
+        warn!(
+            "Rejected {} body expanding from {} to {} bytes",
+            encoding,
+            body.len(),
+            decoded.len()
+        );
+        return Err(ParseError::TooLarge { limit });
+    }
+
+    Ok(Some(decoded))
+}
+
+fn decode(encoding: &str, body: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+    let limit = limit as u64 + 1;
+    let mut decoded = Vec::new();
+    match encoding {
+        "gzip" | "x-gzip" => {
+            flate2::read::MultiGzDecoder::new(body)
+                .take(limit)
+                .read_to_end(&mut decoded)?;
+        }
+        "deflate" => {
+            // `deflate` is zlib-wrapped per RFC 9110, but some senders use raw deflate
+            if flate2::read::ZlibDecoder::new(body)
+                .take(limit)
+                .read_to_end(&mut decoded)
+                .is_err()
+            {
+                decoded.clear();
+                flate2::read::DeflateDecoder::new(body)
+                    .take(limit)
+                    .read_to_end(&mut decoded)?;
+            }
+        }
+        _ => {
+            brotli::Decompressor::new(body, 4096)
+                .take(limit)
+                .read_to_end(&mut decoded)?;
+        }
+    }
+    Ok(decoded)
+}
+
+/// Structured view of a request body, stored as `body_object`
+pub async fn parse_body(content_type: Option<&str>, body: &[u8]) -> Option<Value> {
+    if body.is_empty() {
+        return None;
+    }
+
+    let mime = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+
+    match mime.as_deref() {
+        Some("multipart/form-data") => parse_multipart(content_type?, body).await,
+        Some("application/x-www-form-urlencoded") => Some(parse_form(body)),
+        _ => serde_json::from_slice(body).ok(),
+    }
+}
+
+/// Form fields by name; file parts are described by name, type and size
+async fn parse_multipart(content_type: &str, body: &[u8]) -> Option<Value> {
+    let boundary = multer::parse_boundary(content_type).ok()?;
+    let stream = tokio_stream::once(Ok::<_, Infallible>(body.to_vec()));
+    let mut multipart = multer::Multipart::new(stream, boundary);
+    let mut fields = Map::new();
+
+    while let Some(field) = multipart.next_field().await.ok()? {
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field.file_name().map(str::to_string);
+        let part_type = field.content_type().map(|mime| mime.to_string());
+        let data = field.bytes().await.ok()?;
+
+        let value = match file_name {
+            Some(file_name) => {
+                let mut file = json!({
+                    "filename": file_name,
+                    "content_type": part_type,
+                    "size": data.len(),
+                });
+                if data.len() <= MAX_INLINE_FILE_SIZE {
+                    file["data_base64"] = base64::engine::general_purpose::STANDARD
+                        .encode(&data)
+                        .into();
+                }
+                file
+            }
+            None => String::from_utf8_lossy(&data).into_owned().into(),
+        };
+        insert_field(&mut fields, name, value);
+    }
+
+    Some(Value::Object(fields))
+}
+
+/// Form fields by name, with values as strings
+fn parse_form(body: &[u8]) -> Value {
+    let mut fields = Map::new();
+    for (name, value) in url::form_urlencoded::parse(body) {
+        insert_field(&mut fields, name.into_owned(), value.into_owned().into());
+    }
+    Value::Object(fields)
+}
+
+/// Add a form field, turning repeated names into arrays
+fn insert_field(fields: &mut Map<String, Value>, name: String, value: Value) {
+    match fields.get_mut(&name) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+        None => {
+            fields.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent normally
+    Closed,
+    /// Requests are refused until the given time
+    Open(Instant),
+    /// A single probe request is in flight to find out whether the target recovered
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Name shown in the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open(_) => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+}
+
+/// Stops sending to forward targets that keep failing, so a target that is down does not
+/// cost every incoming webhook a slow timeout
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    /// Targets with recent failures by URL; healthy targets have no entry
+    circuits: Arc<DashMap<String, Circuit>>,
+    threshold: u32,
+    timeout: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            circuits: Arc::new(DashMap::new()),
+            threshold: config.circuit_breaker_threshold,
+            timeout: Duration::from_secs(config.circuit_breaker_timeout_secs),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Whether a request to `url` may be sent now. Once an open circuit has timed out,
+    /// the first caller is let through as the probe and moves it to half-open.
+    pub fn allow(&self, url: &str) -> bool {
+        let Some(mut circuit) = self.circuits.get_mut(url) else {
+            return true;
+        };
+
+        match circuit.state {
+            CircuitState::Closed => true,
+            CircuitState::Open(until) if Instant::now() >= until => {
+                circuit.state = CircuitState::HalfOpen;
+                info!("Circuit for {} is half-open, sending a probe request", url);
+                true
+            }
+            CircuitState::Open(_) | CircuitState::HalfOpen => false,
+        }
+    }
+
+    pub fn record_success(&self, url: &str) {
+        if let Some((_, circuit)) = self.circuits.remove(url)
+            && circuit.state != CircuitState::Closed
+        {
+            info!("Circuit for {} closed, target recovered", url);
+        }
+    }
+
+    pub fn record_failure(&self, url: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut circuit = self.circuits.entry(url.to_string()).or_insert(Circuit {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+        });
+        circuit.consecutive_failures += 1;
+
+        let reopen = match circuit.state {
+            CircuitState::Closed => circuit.consecutive_failures >= self.threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open(_) => false,
+        };
+        if reopen {
+            circuit.state = CircuitState::Open(Instant::now() + self.timeout);
+            warn!(
+                "Circuit for {} opened after {} consecutive failures, pausing deliveries for {}s",
+                url,
+                circuit.consecutive_failures,
+                self.timeout.as_secs()
+            );
+        }
+    }
+
+    pub fn state(&self, url: &str) -> CircuitState {
+        self.circuits
+            .get(url)
+            .map_or(CircuitState::Closed, |circuit| circuit.state)
+    }
+}
@@ -1,16 +1,80 @@
-use anyhow::Result;
-use tracing::info;
+use anyhow::{Result, bail};
+use ipnetwork::IpNetwork;
+use std::fmt::{self, Display};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use crate::models::DEFAULT_MAX_PAYLOAD_BYTES;
+
+/// Bounds of `MAX_PAYLOAD_BYTES`, 1 KiB to 512 MiB
+const MIN_MAX_PAYLOAD_BYTES: usize = 1024;
+const MAX_MAX_PAYLOAD_BYTES: usize = 512 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    pub database_url: Option<String>,
+    pub db_path: String,
+    pub db_max_connections: u32,
+    pub db_min_connections: u32,
+    pub db_busy_timeout_ms: u64,
     pub base_url: Option<String>,
     pub bind_addr: String,
+    pub bind_unix: Option<PathBuf>,
     pub cors_permissive: bool,
     pub cors_allowed_origins: Vec<String>,
+    pub metrics_auth_token: Option<String>,
+    pub admin_api_key: Option<String>,
+    pub jwt_secret: Option<String>,
+    pub trust_proxy: bool,
+    pub redact_headers: Vec<String>,
+    pub retention_days: u32,
+    pub retention_max_per_token: u32,
+    pub default_max_requests_per_token: u32,
+    pub max_tokens: u32,
+    pub cache_max_tokens: usize,
+    pub idempotency_window_secs: u64,
+    pub secret_rotation_grace_secs: u64,
+    pub max_response_delay_ms: u32,
+    pub max_payload_bytes: usize,
+    pub clock_skew_tolerance_secs: u64,
+    pub rate_limit_requests: u32,
+    pub rate_limit_window_secs: u64,
+    pub ip_rate_limit_requests: u32,
+    pub ip_rate_limit_window_secs: u64,
+    pub ip_rate_limit_allowlist: Vec<IpNetwork>,
+    pub ip_token_create_limit: u32,
+    pub ip_token_create_window_secs: u64,
+    pub forward_max_retries: u32,
+    pub forward_retry_base_ms: u64,
+    pub forward_connect_timeout_ms: u64,
+    pub forward_read_timeout_ms: u64,
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_timeout_secs: u64,
+    pub service_region: Option<String>,
+    pub geo_db_path: Option<String>,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub socks5_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_tls: SmtpTls,
+    pub smtp_user: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_from: Option<String>,
+    pub correlation_header_name: String,
+    pub shutdown_timeout_secs: u64,
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
+        // Problems are collected rather than returned so that all of them are reported at once
+        let mut errors = Vec::new();
+
         let base_url = std::env::var("BASE_URL").ok();
         if let Some(ref url) = base_url {
             info!("Using configured BASE_URL: {}", url);
@@ -18,10 +82,39 @@ impl Config {
             info!("No BASE_URL configured, will derive from request headers");
         }
 
+        // PostgreSQL when set to a postgres:// URL, SQLite otherwise
+        let database_url = std::env::var("DATABASE_URL").ok();
+
+        let db_path =
+            std::env::var("DB_PATH").unwrap_or_else(|_| "./webhook_service.db".to_string());
+        let db_max_connections = env_or("DB_MAX_CONNECTIONS", 5, &mut errors);
+        let db_min_connections = env_or("DB_MIN_CONNECTIONS", 1, &mut errors);
+        let db_busy_timeout_ms = env_or("DB_BUSY_TIMEOUT_MS", 5000, &mut errors);
+
         let bind_addr = std::env::var("BIND_ADDR")
             .or_else(|_| std::env::var("PORT").map(|p| format!("0.0.0.0:{p}")))
             .unwrap_or_else(|_| "0.0.0.0:3000".to_string());
 
+        // Unix domain socket for a local reverse proxy, replacing the TCP listener
+        let bind_unix = match std::env::var("BIND_UNIX") {
+            Ok(path) if std::env::var("BIND_ADDR").is_ok() => {
+                errors.push(ConfigError::new(
+                    "BIND_UNIX",
+                    path,
+                    "BIND_UNIX and BIND_ADDR are mutually exclusive; set only one of them",
+                ));
+                None
+            }
+            Ok(path) => match std::path::absolute(&path) {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    errors.push(ConfigError::new("BIND_UNIX", path, e));
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
         let cors_permissive = std::env::var("CORS_PERMISSIVE").is_ok();
 
         let cors_allowed_origins = if cors_permissive {
@@ -34,21 +127,484 @@ impl Config {
                 .collect()
         };
 
-        Ok(Self {
+        let metrics_auth_token = std::env::var("METRICS_AUTH_TOKEN").ok();
+
+        let admin_api_key = std::env::var("ADMIN_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty());
+
+        // Enables exchanging the admin key for short-lived JWTs
+        let jwt_secret = std::env::var("JWT_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+
+        // Only enable behind a reverse proxy that overwrites the forwarding headers;
+        // TRUST_X_FORWARDED_FOR is the older name of this setting
+        let trust_proxy =
+            std::env::var("TRUST_PROXY").is_ok() || std::env::var("TRUST_X_FORWARDED_FOR").is_ok();
+
+        // Values of these headers are replaced before storage; tokens can override the list
+        let redact_headers = std::env::var("REDACT_HEADERS")
+            .unwrap_or_else(|_| "authorization,cookie,x-api-key".into())
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        // 0 disables the corresponding retention limit
+        let retention_days = env_or("RETENTION_DAYS", 30, &mut errors);
+        let retention_max_per_token = env_or("RETENTION_MAX_PER_TOKEN", 10_000, &mut errors);
+
+        // Hard cap for tokens without their own `max_requests`; 0 means unlimited
+        let default_max_requests_per_token =
+            env_or("DEFAULT_MAX_REQUESTS_PER_TOKEN", 0, &mut errors);
+
+        // Tokens this instance may hold in total; 0 means unlimited
+        let max_tokens = env_or("MAX_TOKENS", 0, &mut errors);
+
+        // Tokens whose existence is cached in memory; 0 disables the cache
+        let cache_max_tokens = env_or("CACHE_MAX_TOKENS", 1000, &mut errors);
+
+        // How long a repeated Idempotency-Key returns the original request; 0 disables
+        let idempotency_window_secs = env_or("IDEMPOTENCY_WINDOW_SECS", 3600, &mut errors);
+
+        // How long the previous webhook secret keeps validating after a rotation
+        let secret_rotation_grace_secs = env_or("SECRET_ROTATION_GRACE_SECS", 3600, &mut errors);
+
+        // Server-wide cap on per-token response delays
+        let max_response_delay_ms = env_or("MAX_RESPONSE_DELAY_MS", 10_000, &mut errors);
+
+        // Largest webhook body accepted by tokens without their own max_payload_bytes
+        let max_payload_bytes = env_or("MAX_PAYLOAD_BYTES", DEFAULT_MAX_PAYLOAD_BYTES, &mut errors);
+
+        // Leeway for sender clocks running behind when checking a token's max_age_seconds
+        let clock_skew_tolerance_secs = env_or("CLOCK_SKEW_TOLERANCE_SECS", 5, &mut errors);
+
+        // 0 disables rate limiting
+        let rate_limit_requests = env_or("RATE_LIMIT_REQUESTS", 0, &mut errors);
+        let rate_limit_window_secs = env_or("RATE_LIMIT_WINDOW_SECS", 60, &mut errors);
+
+        // Limit across all routes per client IP; 0 disables it
+        let ip_rate_limit_requests = env_or("IP_RATE_LIMIT_REQUESTS", 100, &mut errors);
+        let ip_rate_limit_window_secs = env_or("IP_RATE_LIMIT_WINDOW_SECS", 60, &mut errors);
+        // Comma-separated CIDRs (e.g. trusted proxies) exempt from the per-IP limit
+        let ip_rate_limit_allowlist = std::env::var("IP_RATE_LIMIT_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|cidr| !cidr.is_empty())
+            .filter_map(|cidr| {
+                cidr.parse::<IpNetwork>()
+                    .map_err(|e| {
+                        errors.push(ConfigError::new("IP_RATE_LIMIT_ALLOWLIST", cidr, e));
+                    })
+                    .ok()
+            })
+            .collect();
+
+        // Tokens one client IP may create via POST /api/tokens per window; 0 disables the limit
+        let ip_token_create_limit = env_or("IP_TOKEN_CREATE_LIMIT", 10, &mut errors);
+        let ip_token_create_window_secs = env_or("IP_TOKEN_CREATE_WINDOW_SECS", 3600, &mut errors);
+
+        let forward_max_retries = env_or("FORWARD_MAX_RETRIES", 3, &mut errors);
+        let forward_retry_base_ms = env_or("FORWARD_RETRY_BASE_MS", 1000, &mut errors);
+        // Limits per forward attempt; the read timeout covers the whole exchange
+        let forward_connect_timeout_ms = env_or("FORWARD_CONNECT_TIMEOUT_MS", 5000, &mut errors);
+        let forward_read_timeout_ms = env_or("FORWARD_READ_TIMEOUT_MS", 30_000, &mut errors);
+
+        // Consecutive failures after which a forward target is paused; 0 disables the breaker
+        let circuit_breaker_threshold = env_or("CIRCUIT_BREAKER_THRESHOLD", 5, &mut errors);
+        let circuit_breaker_timeout_secs = env_or("CIRCUIT_BREAKER_TIMEOUT_SECS", 60, &mut errors);
+
+        // Region this instance runs in; mirror targets in the same forward_region are preferred
+        let service_region = std::env::var("SERVICE_REGION")
+            .ok()
+            .map(|region| region.trim().to_string())
+            .filter(|region| !region.is_empty());
+
+        // MaxMind City database (MMDB) to geolocate webhook source IPs with
+        let geo_db_path = std::env::var("GEO_DB_PATH")
+            .ok()
+            .filter(|path| !path.is_empty());
+
+        // Proxies for forwards and notifications, by scheme of the destination URL;
+        // SOCKS5_PROXY carries whatever the other two do not
+        let http_proxy = proxy_url("HTTP_PROXY", &["http", "https"], &mut errors);
+        let https_proxy = proxy_url("HTTPS_PROXY", &["http", "https"], &mut errors);
+        let socks5_proxy = proxy_url("SOCKS5_PROXY", &["socks5", "socks5h"], &mut errors);
+        // Comma-separated hosts reached directly; `*.example.com` matches the domain and its subdomains
+        let no_proxy = env_var_any_case("NO_PROXY").map(|hosts| {
+            hosts
+                .split(',')
+                .map(|host| {
+                    let host = host.trim();
+                    host.strip_prefix("*.").unwrap_or(host)
+                })
+                .filter(|host| !host.is_empty())
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+
+        // Mail server for email notifications; they are disabled without SMTP_HOST
+        let smtp_host = std::env::var("SMTP_HOST")
+            .ok()
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty());
+        let smtp_port = env_or("SMTP_PORT", 587, &mut errors);
+        // `starttls` upgrades the connection, `tls` connects with TLS (usually port 465)
+        let smtp_tls = env_or("SMTP_TLS", SmtpTls::StartTls, &mut errors);
+        let smtp_user = std::env::var("SMTP_USER").ok();
+        let smtp_password = std::env::var("SMTP_PASSWORD").ok();
+        let smtp_from = std::env::var("SMTP_FROM")
+            .ok()
+            .map(|from| from.trim().to_string());
+
+        // Serve HTTPS directly when both are set
+        let tls_cert_path = std::env::var("TLS_CERT_PATH").ok();
+        let tls_key_path = std::env::var("TLS_KEY_PATH").ok();
+
+        // Forwarded requests carry `<name>-Request-ID` and `<name>-Token-ID` headers
+        let correlation_header_name = std::env::var("CORRELATION_HEADER_NAME")
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| "X-Webhook-Service".to_string());
+
+        // How long in-flight requests may take to finish after SIGTERM/Ctrl+C
+        let shutdown_timeout_secs = env_or("SHUTDOWN_TIMEOUT_SECS", 30, &mut errors);
+
+        let config = Self {
+            database_url,
+            db_path,
+            db_max_connections,
+            db_min_connections,
+            db_busy_timeout_ms,
             base_url,
             bind_addr,
+            bind_unix,
             cors_permissive,
             cors_allowed_origins,
-        })
+            metrics_auth_token,
+            admin_api_key,
+            jwt_secret,
+            trust_proxy,
+            redact_headers,
+            retention_days,
+            retention_max_per_token,
+            default_max_requests_per_token,
+            max_tokens,
+            cache_max_tokens,
+            idempotency_window_secs,
+            secret_rotation_grace_secs,
+            max_response_delay_ms,
+            max_payload_bytes,
+            clock_skew_tolerance_secs,
+            rate_limit_requests,
+            rate_limit_window_secs,
+            ip_rate_limit_requests,
+            ip_rate_limit_window_secs,
+            ip_rate_limit_allowlist,
+            ip_token_create_limit,
+            ip_token_create_window_secs,
+            forward_max_retries,
+            forward_retry_base_ms,
+            forward_connect_timeout_ms,
+            forward_read_timeout_ms,
+            circuit_breaker_threshold,
+            circuit_breaker_timeout_secs,
+            service_region,
+            geo_db_path,
+            http_proxy,
+            https_proxy,
+            socks5_proxy,
+            no_proxy,
+            smtp_host,
+            smtp_port,
+            smtp_tls,
+            smtp_user,
+            smtp_password,
+            smtp_from,
+            correlation_header_name,
+            shutdown_timeout_secs,
+            tls_cert_path,
+            tls_key_path,
+        };
+        if let Err(invalid) = config.validate() {
+            errors.extend(invalid);
+        }
+        if !errors.is_empty() {
+            let problems: Vec<String> = errors.iter().map(|e| format!("  - {}", e)).collect();
+            bail!("Invalid configuration:\n{}", problems.join("\n"));
+        }
+        Ok(config)
+    }
+
+    /// Check the settings that parse but can't work, reporting every problem at once
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if self.bind_unix.is_none() && self.bind_addr.parse::<SocketAddr>().is_err() {
+            errors.push(ConfigError::new(
+                "BIND_ADDR",
+                &self.bind_addr,
+                "expected an IP address and port, e.g. 0.0.0.0:3000 (PORT must be a number)",
+            ));
+        }
+
+        for origin in &self.cors_allowed_origins {
+            if let Err(e) = url::Url::parse(origin) {
+                errors.push(ConfigError::new("CORS_ALLOWED_ORIGINS", origin, e));
+            }
+        }
+
+        if let Some(ref base_url) = self.base_url {
+            match url::Url::parse(base_url) {
+                Ok(url) if !matches!(url.scheme(), "http" | "https") => errors.push(
+                    ConfigError::new("BASE_URL", base_url, "must start with http:// or https://"),
+                ),
+                Ok(url)
+                    if url.path() != "/" || url.query().is_some() || url.fragment().is_some() =>
+                {
+                    errors.push(ConfigError::new(
+                        "BASE_URL",
+                        base_url,
+                        "must not have a path, e.g. https://hooks.example.com",
+                    ))
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(ConfigError::new("BASE_URL", base_url, e)),
+            }
+        }
+
+        if !(MIN_MAX_PAYLOAD_BYTES..=MAX_MAX_PAYLOAD_BYTES).contains(&self.max_payload_bytes) {
+            errors.push(ConfigError::new(
+                "MAX_PAYLOAD_BYTES",
+                self.max_payload_bytes,
+                format!(
+                    "must be between {} (1 KiB) and {} (512 MiB)",
+                    MIN_MAX_PAYLOAD_BYTES, MAX_MAX_PAYLOAD_BYTES
+                ),
+            ));
+        }
+
+        if self.db_max_connections == 0 || self.db_min_connections > self.db_max_connections {
+            errors.push(ConfigError::new(
+                "DB_MAX_CONNECTIONS",
+                self.db_max_connections,
+                format!(
+                    "must be at least 1 and no less than DB_MIN_CONNECTIONS ({})",
+                    self.db_min_connections
+                ),
+            ));
+        }
+
+        if self.forward_connect_timeout_ms == 0 {
+            errors.push(ConfigError::new(
+                "FORWARD_CONNECT_TIMEOUT_MS",
+                self.forward_connect_timeout_ms,
+                "must be at least 1",
+            ));
+        }
+        if self.forward_read_timeout_ms == 0 {
+            errors.push(ConfigError::new(
+                "FORWARD_READ_TIMEOUT_MS",
+                self.forward_read_timeout_ms,
+                "must be at least 1",
+            ));
+        }
+
+        // Only names are shown, never the credentials themselves
+        match (&self.smtp_user, &self.smtp_password) {
+            (Some(user), None) => errors.push(ConfigError::new(
+                "SMTP_USER",
+                user,
+                "SMTP_PASSWORD must be set as well",
+            )),
+            (None, Some(_)) => errors.push(ConfigError::new(
+                "SMTP_PASSWORD",
+                "***",
+                "SMTP_USER must be set as well",
+            )),
+            _ => {}
+        }
+        match &self.smtp_from {
+            Some(from) => {
+                if let Err(e) = from.parse::<lettre::message::Mailbox>() {
+                    errors.push(ConfigError::new("SMTP_FROM", from, e));
+                }
+            }
+            None => {
+                if let Some(ref host) = self.smtp_host {
+                    errors.push(ConfigError::new(
+                        "SMTP_HOST",
+                        host,
+                        "SMTP_FROM must be set as well",
+                    ));
+                }
+            }
+        }
+
+        match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(path), None) => errors.push(ConfigError::new(
+                "TLS_CERT_PATH",
+                path,
+                "TLS_KEY_PATH must be set as well",
+            )),
+            (None, Some(path)) => errors.push(ConfigError::new(
+                "TLS_KEY_PATH",
+                path,
+                "TLS_CERT_PATH must be set as well",
+            )),
+            _ => {}
+        }
+        if let Some(ref path) = self.bind_unix {
+            if self.tls_cert_path.is_some() {
+                errors.push(ConfigError::new(
+                    "BIND_UNIX",
+                    path.display(),
+                    "TLS is not supported on unix sockets; terminate TLS in the proxy instead",
+                ));
+            }
+            // Socket peers have no address, so without the proxy's headers every client would
+            // share one IP for rate limits, IP rules and geolocation
+            if !self.trust_proxy {
+                errors.push(ConfigError::new(
+                    "BIND_UNIX",
+                    path.display(),
+                    "requires TRUST_PROXY; the client IP must come from the proxy's headers",
+                ));
+            }
+        }
+
+        if axum::http::HeaderName::try_from(format!("{}-Request-ID", self.correlation_header_name))
+            .is_err()
+        {
+            errors.push(ConfigError::new(
+                "CORRELATION_HEADER_NAME",
+                &self.correlation_header_name,
+                "must be a valid header name",
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     pub fn log_startup_info(&self) {
-        info!("Listening on {}", self.bind_addr);
+        if let Some(ref path) = self.bind_unix {
+            info!("Listening on unix:{}", path.display());
+        } else {
+            let scheme = if self.tls_cert_path.is_some() {
+                "https"
+            } else {
+                "http"
+            };
+            info!("Listening on {}://{}", scheme, self.bind_addr);
+        }
 
         if let Some(ref url) = self.base_url {
             info!("Web interface available at: {}", url);
         } else {
             info!("No BASE_URL set; Web interface available at http://localhost:3000");
         }
+
+        if self.admin_api_key.is_none() {
+            warn!("ADMIN_API_KEY is not set; the /api/tokens endpoints are unauthenticated");
+        }
+    }
+}
+
+/// A setting rejected by [`Config::from_env`] or [`Config::validate`]
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// Environment variable the value came from
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+impl ConfigError {
+    fn new(field: &str, value: impl Display, reason: impl Display) -> Self {
+        Self {
+            field: field.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}='{}': {}", self.field, self.value, self.reason)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    StartTls,
+    Tls,
+    /// Plain text, for a relay on the local host or network
+    None,
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "starttls" => Ok(Self::StartTls),
+            "tls" => Ok(Self::Tls),
+            "none" => Ok(Self::None),
+            _ => Err("expected starttls, tls or none".to_string()),
+        }
+    }
+}
+
+/// A non-empty environment variable, also accepted in lower case (e.g. `https_proxy`)
+fn env_var_any_case(name: &str) -> Option<String> {
+    std::env::var(name)
+        .or_else(|_| std::env::var(name.to_ascii_lowercase()))
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// A proxy URL from the environment, which must use one of `schemes`
+fn proxy_url(name: &str, schemes: &[&str], errors: &mut Vec<ConfigError>) -> Option<String> {
+    let value = env_var_any_case(name)?;
+    match url::Url::parse(&value) {
+        Ok(url) if schemes.contains(&url.scheme()) => Some(value),
+        Ok(_) => {
+            let reason = format!("must be a {} URL", schemes.join(" or "));
+            errors.push(ConfigError::new(name, value, reason));
+            None
+        }
+        Err(e) => {
+            errors.push(ConfigError::new(name, value, e));
+            None
+        }
+    }
+}
+
+/// Parse an environment variable, falling back to `default` when it is not set or (after
+/// recording the problem in `errors`) does not parse
+fn env_or<T>(name: &str, default: T, errors: &mut Vec<ConfigError>) -> T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match std::env::var(name) {
+        Ok(value) => match value.trim().parse() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                errors.push(ConfigError::new(name, value, e));
+                default
+            }
+        },
+        Err(_) => default,
     }
 }
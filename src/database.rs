@@ -1,222 +1,407 @@
 use anyhow::Result;
-use sqlx::{
-    Row,
-    sqlite::{SqliteConnectOptions, SqlitePool},
-};
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta, Utc};
 use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio_stream::Stream;
 
-use crate::models::{MessageObject, TokenInfo, WebhookRequest};
+use crate::config::Config;
+use crate::models::{
+    AuditEntry, AuditQuery, CountryCount, CustomResponse, DeadLetter, DuplicateGroup,
+    EmailNotification, ForwardAttempt, ForwardResult, ForwardTarget, GlobalStats, HeaderCount,
+    HealthCheck, HeatmapCell, HourlyBucket, ImportSummary, IpRule, NonceStatus, PageStats,
+    SlackNotification, TokenInfo, TokenListQuery, TokenStats, UpdateTokenRequest, UserAgentCount,
+    WebhookRequest, WebhookRequestFilter,
+};
 
-pub struct Database {
-    pool: SqlitePool,
-}
+mod postgres;
+mod sqlite;
 
-impl Database {
-    pub async fn new() -> Result<Self> {
-        // Get current directory and create database path
-        let current_dir = std::env::current_dir()?;
-        let db_path = current_dir.join("webhook_service.db");
+pub use postgres::PostgresBackend;
+pub use sqlite::SqliteBackend;
 
-        // Ensure the directory exists and is writable
-        if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+const REQUEST_COLUMNS: &str = "id, date, token_id, method, value, headers, query_parameters, body, body_object, message, replayed_from, forward_status, signature_valid, content_type, compressed_size, body_encoding, source_ip, peer_ip, source_country, source_city, source_asn, ua_browser, ua_os, ua_device_type, ua_client, body_size_bytes, body_sha256, comment, flagged, redacted_headers, cookies, trace_id";
 
-        // Create connection options with concurrency-friendly settings
-        let options = SqliteConnectOptions::new()
-            .filename(&db_path)
-            .create_if_missing(true)
-            .foreign_keys(true)
-            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .busy_timeout(Duration::from_secs(5));
-
-        // Create the database connection
-        let pool = SqlitePool::connect_with(options).await?;
-
-        // Create tables
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS tokens (
-                token TEXT PRIMARY KEY,
-                created_at TEXT NOT NULL,
-                webhook_url TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS webhook_requests (
-                id TEXT PRIMARY KEY,
-                date TEXT NOT NULL,
-                token_id TEXT NOT NULL,
-                method TEXT NOT NULL,
-                value TEXT NOT NULL,
-                headers TEXT NOT NULL,
-                query_parameters TEXT NOT NULL,
-                body TEXT,
-                body_object TEXT,
-                message TEXT,
-                FOREIGN KEY (token_id) REFERENCES tokens (token)
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
-
-        // Create index for faster queries
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_webhook_requests_token_id ON webhook_requests (token_id)")
-            .execute(&pool)
-            .await?;
-
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_webhook_requests_date ON webhook_requests (date)",
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(Self { pool })
-    }
+const TOKEN_COLUMNS: &str = "token, created_at, webhook_url, expires_at, label, disabled, forward_url, webhook_secret IS NOT NULL AS has_secret, last_used_at, max_requests, cors_origins, deduplicate_window_secs, response_delay_ms, redact_headers, allowed_methods, allowed_content_types, max_age_seconds, nonce_required, notification_url, notification_events, readonly, readonly_key IS NOT NULL AS has_readonly_key, EXISTS (SELECT 1 FROM responses WHERE token_id = tokens.token) AS has_custom_response, max_payload_bytes, transform_rules, token_message_config, (SELECT COUNT(*) FROM webhook_requests WHERE token_id = tokens.token) AS request_count";
 
-    pub async fn create_token(&self, token_info: &TokenInfo) -> Result<()> {
-        sqlx::query("INSERT INTO tokens (token, created_at, webhook_url) VALUES (?, ?, ?)")
-            .bind(&token_info.token)
-            .bind(&token_info.created_at)
-            .bind(&token_info.webhook_url)
-            .execute(&self.pool)
-            .await?;
+/// Token settings copied by `clone_token`; the secrets and read-only key are left out
+const CLONED_TOKEN_COLUMNS: &str = "expires_at, label, disabled, forward_url, max_requests, cors_origins, deduplicate_window_secs, response_delay_ms, redact_headers, allowed_methods, allowed_content_types, max_age_seconds, nonce_required, notification_url, notification_events, readonly, max_payload_bytes, transform_rules, token_message_config";
 
-        Ok(())
-    }
+/// Per-token configuration tables copied by `clone_token`, with their columns besides `token_id`
+const CLONED_TOKEN_TABLES: &[(&str, &str)] = &[
+    ("token_metadata", "key, value"),
+    ("responses", "status_code, response_headers, response_body"),
+    ("token_ip_rules", "cidr, action"),
+    ("slack_notifications", "webhook_url, template, created_at"),
+    ("email_notifications", "to_address, events, created_at"),
+];
 
-    pub async fn list_tokens(&self) -> Result<Vec<TokenInfo>> {
-        let rows = sqlx::query(
-            "SELECT token, created_at, webhook_url FROM tokens ORDER BY created_at DESC",
-        )
-        .fetch_all(&self.pool)
-        .await?;
-
-        let tokens = rows
-            .into_iter()
-            .map(|row| TokenInfo {
-                token: row.get("token"),
-                created_at: row.get("created_at"),
-                webhook_url: row.get("webhook_url"),
-            })
-            .collect();
-
-        Ok(tokens)
-    }
+/// Returned by [`DatabaseBackend::ping`] when the database does not answer in time
+#[derive(Debug, thiserror::Error)]
+#[error("database ping timed out")]
+pub struct PingTimeout;
 
-    pub async fn token_exists(&self, token: &str) -> Result<bool> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tokens WHERE token = ?")
-            .bind(token)
-            .fetch_one(&self.pool)
-            .await?;
+/// Rows of a streamed query, fetched from the database as they are consumed
+pub type RequestStream<'a> = Pin<Box<dyn Stream<Item = Result<WebhookRequest>> + Send + 'a>>;
 
-        Ok(count > 0)
+/// Connect to PostgreSQL when `DATABASE_URL` is a `postgres://` URL, otherwise to SQLite
+pub async fn connect(config: &Config) -> Result<Arc<dyn DatabaseBackend>> {
+    match config.database_url.as_deref() {
+        Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+            Ok(Arc::new(PostgresBackend::new(url, config).await?))
+        }
+        _ => Ok(Arc::new(SqliteBackend::new(config).await?)),
     }
+}
 
-    pub async fn delete_token(&self, token: &str) -> Result<()> {
-        let mut tx = self.pool.begin().await?;
+/// Storage operations shared by the SQLite and PostgreSQL backends
+#[async_trait]
+pub trait DatabaseBackend: Send + Sync {
+    /// Verify the database answers a trivial query within a short timeout
+    async fn ping(&self) -> Result<()>;
 
-        sqlx::query("DELETE FROM webhook_requests WHERE token_id = ?")
-            .bind(token)
-            .execute(&mut *tx)
-            .await?;
+    /// Whether the schema has been created (the `webhook_requests` table exists)
+    async fn schema_ready(&self) -> Result<bool>;
 
-        sqlx::query("DELETE FROM tokens WHERE token = ?")
-            .bind(token)
-            .execute(&mut *tx)
-            .await?;
+    /// Total size of the database in bytes
+    async fn size_bytes(&self) -> Result<i64>;
 
-        tx.commit().await?;
+    async fn create_token(&self, token_info: &TokenInfo) -> Result<()>;
 
-        Ok(())
-    }
+    /// Insert all tokens in a single transaction
+    async fn create_tokens(&self, tokens: &[TokenInfo]) -> Result<()>;
 
-    pub async fn store_webhook_request(&self, request: &WebhookRequest) -> Result<()> {
-        let headers_json = serde_json::to_string(&request.message_object.headers)?;
-        let query_params_json = serde_json::to_string(&request.message_object.query_parameters)?;
-        let body_object_json = request
-            .message_object
-            .body_object
-            .as_ref()
-            .map(serde_json::to_string)
-            .transpose()?;
-
-        sqlx::query(
-            r#"
-            INSERT INTO webhook_requests 
-            (id, date, token_id, method, value, headers, query_parameters, body, body_object, message)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(&request.id)
-        .bind(&request.date)
-        .bind(&request.token_id)
-        .bind(&request.message_object.method)
-        .bind(&request.message_object.value)
-        .bind(headers_json)
-        .bind(query_params_json)
-        .bind(&request.message_object.body)
-        .bind(body_object_json)
-        .bind(&request.message)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
+    async fn list_tokens(&self, query: &TokenListQuery) -> Result<Vec<TokenInfo>>;
+
+    async fn get_token(&self, token: &str) -> Result<Option<TokenInfo>>;
+
+    /// Tokens in creation order, for exporting them page by page
+    async fn list_tokens_page(&self, limit: u32, offset: u32) -> Result<Vec<TokenInfo>>;
+
+    /// Insert the tokens and requests that are not stored yet in a single transaction,
+    /// which is rolled back instead of committed when `dry_run` is set
+    async fn import_data(
+        &self,
+        tokens: &[TokenInfo],
+        requests: &[WebhookRequest],
+        dry_run: bool,
+    ) -> Result<ImportSummary>;
+
+    /// Store unused nonces for the token
+    async fn create_nonces(&self, token: &str, nonces: &[String], expires_at: &str) -> Result<()>;
+
+    /// Mark the token's nonce as used if it is unused and has not expired at `now`
+    async fn consume_nonce(&self, token: &str, nonce: &str, now: &str) -> Result<NonceStatus>;
+
+    /// Delete nonces that expired before `now`, used or not
+    async fn prune_expired_nonces(&self, now: &str) -> Result<u64>;
+
+    /// Store every setting the update contains in one statement; returns `false` if the token
+    /// does not exist
+    async fn update_token_settings(&self, token: &str, update: &UpdateTokenRequest)
+    -> Result<bool>;
+
+    async fn token_exists(&self, token: &str) -> Result<bool>;
+
+    async fn count_tokens(&self) -> Result<i64>;
+
+    /// Returns `false` if the token does not exist
+    async fn set_token_disabled(&self, token: &str, disabled: bool) -> Result<bool>;
+
+    /// SHA-256 hex digest of the token's read-only key
+    async fn get_readonly_key_hash(&self, token: &str) -> Result<Option<String>>;
+
+    /// Returns `false` if the token does not exist; `None` revokes the key
+    async fn set_readonly_key_hash(&self, token: &str, hash: Option<&str>) -> Result<bool>;
+
+    /// Returns `false` if the token does not exist
+    async fn set_forward_url(&self, token: &str, url: Option<&str>) -> Result<bool>;
+
+    /// Mirror targets of a token with the outcome of their latest delivery, oldest first
+    async fn list_forward_targets(&self, token: &str) -> Result<Vec<ForwardTarget>>;
+
+    async fn create_forward_target(&self, token: &str, target: &ForwardTarget) -> Result<()>;
+
+    /// Returns `false` if no target with that ID belongs to the token
+    async fn delete_forward_target(&self, token: &str, forward_id: &str) -> Result<bool>;
+
+    async fn record_forward_result(&self, result: &ForwardResult) -> Result<()>;
+
+    /// Returns `false` if the token does not exist; an empty list clears the origins
+    async fn set_cors_origins(&self, token: &str, origins: &[String]) -> Result<bool>;
+
+    async fn get_webhook_secret(&self, token: &str) -> Result<Option<String>>;
 
-    pub async fn get_webhook_requests(
+    /// Returns `false` if the token does not exist; also drops any previous secret
+    async fn set_webhook_secret(&self, token: &str, secret: &str) -> Result<bool>;
+
+    /// The secret replaced by the last rotation, while it is still accepted
+    async fn get_previous_webhook_secret(&self, token: &str) -> Result<Option<String>>;
+
+    /// Make `secret` current and keep accepting the old one until `previous_expires_at`.
+    /// Returns `false` if the token does not exist
+    async fn rotate_webhook_secret(
         &self,
         token: &str,
-        count: u32,
-    ) -> Result<Vec<WebhookRequest>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, date, token_id, method, value, headers, query_parameters, body, body_object, message
-            FROM webhook_requests 
-            WHERE token_id = ? 
-            ORDER BY date DESC 
-            LIMIT ?
-            "#
-        )
-        .bind(token)
-        .bind(count as i64)
-        .fetch_all(&self.pool)
-        .await?;
-
-        let mut requests = Vec::new();
-        for row in rows {
-            let headers: HashMap<String, Vec<String>> = serde_json::from_str(row.get("headers"))?;
-            let query_parameters: Vec<String> = serde_json::from_str(row.get("query_parameters"))?;
-            let body_object: Option<serde_json::Value> = row
-                .get::<Option<String>, _>("body_object")
-                .map(|s| serde_json::from_str(&s))
-                .transpose()?;
-
-            let request = WebhookRequest {
-                id: row.get("id"),
-                date: row.get("date"),
-                token_id: row.get("token_id"),
-                message_object: MessageObject {
-                    method: row.get("method"),
-                    value: row.get("value"),
-                    headers,
-                    query_parameters,
-                    body: row.get("body"),
-                    body_object,
-                },
-                message: row.get("message"),
-            };
-
-            requests.push(request);
-        }
+        secret: &str,
+        previous_expires_at: &str,
+    ) -> Result<bool>;
+
+    async fn get_token_metadata(&self, token: &str) -> Result<HashMap<String, String>>;
+
+    /// Replace all metadata of a token
+    async fn set_token_metadata(
+        &self,
+        token: &str,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()>;
+
+    /// Returns `false` if the token has no metadata under that key
+    async fn delete_token_metadata(&self, token: &str, key: &str) -> Result<bool>;
+
+    /// Entries are never updated or deleted once appended
+    async fn append_audit(&self, entries: &[AuditEntry]) -> Result<()>;
+
+    /// Matching audit entries, most recent first
+    async fn list_audit(&self, query: &AuditQuery, limit: u32) -> Result<Vec<AuditEntry>>;
+
+    async fn get_ip_rules(&self, token: &str) -> Result<Vec<IpRule>>;
+
+    /// Replace all IP rules of a token
+    async fn set_ip_rules(&self, token: &str, rules: &[IpRule]) -> Result<()>;
+
+    /// Delete all expired tokens together with their webhook requests
+    async fn prune_expired_tokens(&self) -> Result<u64>;
+
+    /// Delete unflagged requests older than `retention_days`, returning how many were removed
+    async fn prune_old_requests(&self, retention_days: u32) -> Result<u64>;
+
+    /// Keep only the `max_per_token` most recent unflagged requests of each token
+    async fn prune_excess_requests_per_token(&self, max_per_token: u32) -> Result<u64>;
+
+    /// Page usage of the database
+    async fn page_stats(&self) -> Result<PageStats>;
+
+    /// Rewrite the database to give the space of deleted rows back to the file system
+    async fn vacuum(&self) -> Result<()>;
+
+    /// Refresh the statistics the query planner chooses indexes by
+    async fn analyze(&self) -> Result<()>;
+
+    /// Problems found by the database's own consistency checks; empty when there are none
+    async fn integrity_check(&self) -> Result<Vec<String>>;
+
+    async fn record_health_check(&self, check: &HealthCheck) -> Result<()>;
+
+    /// The most recent stored result of the given kind of check
+    async fn get_last_health_check(&self, check_type: &str) -> Result<Option<HealthCheck>>;
+
+    async fn delete_token(&self, token: &str) -> Result<()>;
+
+    /// Create `token_info`'s token with the configuration of `source` in one transaction:
+    /// its settings, metadata, custom response, mirror targets, IP rules and notifications.
+    /// Returns false when `source` does not exist.
+    async fn clone_token(&self, source: &str, token_info: &TokenInfo) -> Result<bool>;
+
+    async fn set_custom_response(&self, token: &str, response: &CustomResponse) -> Result<()>;
+
+    async fn get_custom_response(&self, token: &str) -> Result<Option<CustomResponse>>;
+
+    /// Returns `false` if no custom response was configured
+    async fn delete_custom_response(&self, token: &str) -> Result<bool>;
+
+    async fn set_slack_notification(
+        &self,
+        token: &str,
+        notification: &SlackNotification,
+    ) -> Result<()>;
+
+    async fn get_slack_notification(&self, token: &str) -> Result<Option<SlackNotification>>;
+
+    /// Returns false when the token had no Slack notification
+    async fn delete_slack_notification(&self, token: &str) -> Result<bool>;
 
-        Ok(requests)
+    async fn set_email_notification(
+        &self,
+        token: &str,
+        notification: &EmailNotification,
+    ) -> Result<()>;
+
+    async fn get_email_notification(&self, token: &str) -> Result<Option<EmailNotification>>;
+
+    /// Returns false when the token had no email notification
+    async fn delete_email_notification(&self, token: &str) -> Result<bool>;
+
+    /// Store the request and record it as the token's most recent use
+    async fn store_webhook_request(&self, request: &WebhookRequest) -> Result<()>;
+
+    /// The request stored for an idempotency key since `since`, if any
+    async fn find_idempotent_request(
+        &self,
+        token: &str,
+        idempotency_key: &str,
+        since: &str,
+    ) -> Result<Option<String>>;
+
+    /// Remember which request an idempotency key produced, replacing an older entry
+    async fn record_idempotency_key(
+        &self,
+        token: &str,
+        idempotency_key: &str,
+        request_id: &str,
+    ) -> Result<()>;
+
+    /// The most recent request stored since `since` with the given body hash
+    async fn find_duplicate_request(
+        &self,
+        token: &str,
+        body_sha256: &str,
+        since: &str,
+    ) -> Result<Option<String>>;
+
+    /// Forget idempotency keys recorded before `before`
+    async fn prune_idempotency_keys(&self, before: &str) -> Result<u64>;
+
+    async fn set_request_forward_status(&self, request_id: &str, status: u16) -> Result<()>;
+
+    async fn record_forward_attempt(&self, attempt: &ForwardAttempt) -> Result<()>;
+
+    async fn get_forward_attempts(&self, request_id: &str) -> Result<Vec<ForwardAttempt>>;
+
+    async fn create_dead_letter(&self, dead_letter: &DeadLetter) -> Result<()>;
+
+    /// The token's dead letters, most recent first
+    async fn list_dead_letters(&self, token: &str) -> Result<Vec<DeadLetter>>;
+
+    async fn get_dead_letter(&self, token: &str, id: &str) -> Result<Option<DeadLetter>>;
+
+    async fn delete_dead_letter(&self, token: &str, id: &str) -> Result<bool>;
+
+    async fn count_requests_by_token(&self, token: &str) -> Result<i64>;
+
+    async fn get_request_by_id(
+        &self,
+        token: &str,
+        request_id: &str,
+    ) -> Result<Option<WebhookRequest>>;
+
+    /// Token and request counts across the whole database
+    async fn get_global_stats(&self) -> Result<GlobalStats>;
+
+    /// Request count, body sizes and date range of the token's stored requests
+    async fn get_token_size_stats(&self, token: &str) -> Result<TokenStats>;
+
+    /// Request counts per UTC hour and method for requests received since `since`, oldest first
+    async fn get_hourly_stats(&self, token: &str, since: &str) -> Result<Vec<HourlyBucket>>;
+
+    /// Requests per day of the week and hour over the last `weeks` weeks, divided by
+    /// `weeks`; hours without requests are left out
+    async fn get_heatmap_data(&self, token: &str, weeks: u32) -> Result<Vec<HeatmapCell>>;
+
+    /// Bodies received more than once within the last `window`, by `body_sha256`, most
+    /// repeated first
+    async fn find_duplicate_requests(
+        &self,
+        token: &str,
+        window: Duration,
+    ) -> Result<Vec<DuplicateGroup>>;
+
+    /// Number of the token's requests per source country, most frequent first; requests
+    /// without a country are left out
+    async fn get_country_counts(&self, token: &str) -> Result<Vec<CountryCount>>;
+
+    /// Number of the token's requests per parsed user agent, most frequent first; requests
+    /// whose `User-Agent` was missing or not recognized are left out
+    async fn get_user_agent_counts(&self, token: &str) -> Result<Vec<UserAgentCount>>;
+
+    /// The `limit` header names carried by most of the token's requests
+    async fn get_top_headers(&self, token: &str, limit: i64) -> Result<Vec<HeaderCount>>;
+
+    /// Parsed bodies of the token's `limit` most recent requests that have one, newest first
+    async fn get_recent_body_objects(
+        &self,
+        token: &str,
+        limit: u32,
+    ) -> Result<Vec<serde_json::Value>>;
+
+    /// Set or clear a request's comment, returning the updated request if it belongs to the token
+    async fn update_request_comment(
+        &self,
+        token: &str,
+        request_id: &str,
+        comment: Option<&str>,
+    ) -> Result<Option<WebhookRequest>>;
+
+    /// Flag or unflag a request, returning the updated request if it belongs to the token
+    async fn set_request_flag(
+        &self,
+        token: &str,
+        request_id: &str,
+        flagged: bool,
+    ) -> Result<Option<WebhookRequest>>;
+
+    /// Unflag every flagged request of the token, returning how many there were
+    async fn clear_request_flags(&self, token: &str) -> Result<u64>;
+
+    /// Returns `false` if no request with that ID belongs to the token
+    async fn delete_webhook_request(&self, token: &str, request_id: &str) -> Result<bool>;
+
+    /// Counter bumped whenever one of the token's requests is stored, changed or deleted;
+    /// `None` if the token does not exist
+    async fn get_requests_version(&self, token: &str) -> Result<Option<i64>>;
+
+    async fn get_webhook_requests(&self, token: &str, count: u32) -> Result<Vec<WebhookRequest>> {
+        self.get_requests_filtered(token, &WebhookRequestFilter::default(), count, None)
+            .await
     }
+
+    /// Most recent requests first; `before_id` restricts results to requests older than that one
+    async fn get_requests_filtered(
+        &self,
+        token: &str,
+        filter: &WebhookRequestFilter,
+        limit: u32,
+        before_id: Option<&str>,
+    ) -> Result<Vec<WebhookRequest>>;
+
+    /// Requests whose body, URL or message contains `query`, case-insensitively. A single
+    /// word is matched as a substring, most recent first; several words use the full-text
+    /// index and are ranked by relevance
+    async fn search_requests(
+        &self,
+        token: &str,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<WebhookRequest>>;
+
+    /// All matching requests, most recent first, without loading them into memory at once
+    fn stream_webhook_requests<'a>(
+        &'a self,
+        token: &'a str,
+        filter: &'a WebhookRequestFilter,
+    ) -> RequestStream<'a>;
+}
+
+/// Start of the "active", "today" and "this week" windows of [`GlobalStats`], as stored dates
+fn global_stats_cutoffs() -> (String, String, String) {
+    let now = Utc::now();
+    let today = now.date_naive();
+    let week_start = today - TimeDelta::days(i64::from(today.weekday().num_days_from_monday()));
+    let midnight = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc().to_rfc3339();
+
+    (
+        (now - TimeDelta::hours(24)).to_rfc3339(),
+        midnight(today),
+        midnight(week_start),
+    )
+}
+
+/// Escape `LIKE` wildcards so user input is matched literally (with `ESCAPE '\'`)
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
@@ -0,0 +1,2178 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sqlx::{
+    Postgres, QueryBuilder, Row,
+    migrate::Migrator,
+    postgres::{PgConnection, PgPool, PgPoolOptions, PgRow},
+};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tracing::info;
+
+use super::{
+    CLONED_TOKEN_COLUMNS, CLONED_TOKEN_TABLES, DatabaseBackend, PingTimeout, REQUEST_COLUMNS,
+    RequestStream, TOKEN_COLUMNS, escape_like, global_stats_cutoffs,
+};
+use crate::circuit_breaker::CircuitState;
+use crate::config::Config;
+use crate::models::{
+    AuditEntry, AuditQuery, ClientKind, CountryCount, CustomResponse, DeadLetter, DuplicateGroup,
+    EmailNotification, ForwardAttempt, ForwardResult, ForwardTarget, GlobalStats, HeaderCount,
+    HealthCheck, HeatmapCell, HourlyBucket, ImportSummary, IpRule, IpRuleAction, MessageObject,
+    NonceStatus, PageStats, SlackNotification, TokenInfo, TokenListQuery, TokenRequestCount,
+    TokenSort, TokenStats, UpdateTokenRequest, UserAgentCount, WebhookRequest,
+    WebhookRequestFilter,
+};
+
+/// Filters are optional parameters so that the statement can be a `'static` string for streaming
+static STREAM_REQUESTS_SQL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+            SELECT {} FROM webhook_requests
+            WHERE token_id = $1
+              AND ($2::text IS NULL OR method = $2)
+              AND ($3::text IS NULL OR date >= $3)
+              AND ($4::text IS NULL OR date <= $4)
+              AND ($5::text IS NULL OR content_type LIKE $5 ESCAPE '\')
+              AND ($6::text IS NULL OR (id <> $6 AND body_sha256 =
+                  (SELECT body_sha256 FROM webhook_requests WHERE id = $6 AND token_id = $1)))
+              AND ($7::text IS NULL OR (comment IS NOT NULL
+                  AND to_tsvector('simple', comment) @@ plainto_tsquery('simple', $7)))
+              AND ($8::text IS NULL OR (query_parameters::jsonb -> $8) IS NOT NULL)
+              AND ($9::text IS NULL OR (query_parameters::jsonb -> $8) @> jsonb_build_array($9::text))
+              AND ($10::boolean IS NULL OR flagged = $10)
+            ORDER BY date DESC
+            "#,
+        REQUEST_COLUMNS
+    )
+});
+
+/// The token's metadata as a JSON object, selected next to [`TOKEN_COLUMNS`]
+const TOKEN_METADATA_COLUMN: &str = "(SELECT json_object_agg(key, value)::text FROM token_metadata WHERE token_id = tokens.token) AS metadata";
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+pub struct PostgresBackend {
+    pool: PgPool,
+}
+
+impl PostgresBackend {
+    pub async fn new(url: &str, config: &Config) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(config.db_max_connections)
+            .min_connections(config.db_min_connections)
+            .connect(url)
+            .await?;
+
+        let backend = Self { pool };
+        backend.run_migrations().await?;
+        Ok(backend)
+    }
+
+    /// Bring the schema up to date, refusing to start on a failed migration
+    async fn run_migrations(&self) -> Result<()> {
+        MIGRATOR
+            .run(&self.pool)
+            .await
+            .context("Failed to run PostgreSQL database migrations")?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DatabaseBackend for PostgresBackend {
+    async fn ping(&self) -> Result<()> {
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            sqlx::query("SELECT 1").execute(&self.pool),
+        )
+        .await
+        .map_err(|_| PingTimeout)??;
+
+        Ok(())
+    }
+
+    async fn schema_ready(&self) -> Result<bool> {
+        let ready: bool = sqlx::query_scalar("SELECT to_regclass('webhook_requests') IS NOT NULL")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(ready)
+    }
+
+    async fn size_bytes(&self) -> Result<i64> {
+        let size: i64 = sqlx::query_scalar("SELECT pg_database_size(current_database())")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(size)
+    }
+
+    async fn create_token(&self, token_info: &TokenInfo) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO tokens (token, created_at, webhook_url, expires_at, label) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(&token_info.token)
+        .bind(&token_info.created_at)
+        .bind(&token_info.webhook_url)
+        .bind(&token_info.expires_at)
+        .bind(&token_info.label)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn create_tokens(&self, tokens: &[TokenInfo]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        QueryBuilder::<Postgres>::new(
+            "INSERT INTO tokens (token, created_at, webhook_url, expires_at, label) ",
+        )
+        .push_values(tokens, |mut row, token_info| {
+            row.push_bind(&token_info.token)
+                .push_bind(&token_info.created_at)
+                .push_bind(&token_info.webhook_url)
+                .push_bind(&token_info.expires_at)
+                .push_bind(&token_info.label);
+        })
+        .build()
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_tokens(&self, query: &TokenListQuery) -> Result<Vec<TokenInfo>> {
+        let mut builder = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {}, {} FROM tokens",
+            TOKEN_COLUMNS, TOKEN_METADATA_COLUMN
+        ));
+
+        let mut clause = " WHERE ";
+        if let Some(days) = query.inactive_since {
+            let cutoff = chrono::Utc::now() - chrono::TimeDelta::days(i64::from(days));
+            builder
+                .push(clause)
+                .push("COALESCE(last_used_at, created_at) < ")
+                .push_bind(cutoff.to_rfc3339());
+            clause = " AND ";
+        }
+        for (key, value) in &query.metadata {
+            builder
+                .push(clause)
+                .push(
+                    "EXISTS (SELECT 1 FROM token_metadata WHERE token_id = tokens.token AND key = ",
+                )
+                .push_bind(key)
+                .push(" AND value = ")
+                .push_bind(value)
+                .push(")");
+            clause = " AND ";
+        }
+        builder.push(match query.sort {
+            TokenSort::Created => " ORDER BY created_at DESC",
+            TokenSort::LastUsed => " ORDER BY last_used_at DESC NULLS LAST, created_at DESC",
+        });
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+
+        Ok(rows.iter().map(token_from_row).collect())
+    }
+
+    async fn get_token(&self, token: &str) -> Result<Option<TokenInfo>> {
+        let row = sqlx::query(&format!(
+            "SELECT {}, {} FROM tokens WHERE token = $1",
+            TOKEN_COLUMNS, TOKEN_METADATA_COLUMN
+        ))
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(token_from_row))
+    }
+
+    async fn list_tokens_page(&self, limit: u32, offset: u32) -> Result<Vec<TokenInfo>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, {} FROM tokens ORDER BY created_at, token LIMIT $1 OFFSET $2",
+            TOKEN_COLUMNS, TOKEN_METADATA_COLUMN
+        ))
+        .bind(i64::from(limit))
+        .bind(i64::from(offset))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(token_from_row).collect())
+    }
+
+    async fn import_data(
+        &self,
+        tokens: &[TokenInfo],
+        requests: &[WebhookRequest],
+        dry_run: bool,
+    ) -> Result<ImportSummary> {
+        let mut summary = ImportSummary {
+            dry_run,
+            ..ImportSummary::default()
+        };
+        let mut tx = self.pool.begin().await?;
+
+        for token_info in tokens {
+            let cors_origins = if token_info.cors_origins.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&token_info.cors_origins)?)
+            };
+            let allowed_methods = if token_info.allowed_methods.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&token_info.allowed_methods)?)
+            };
+            let transform_rules = if token_info.transform_rules.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&token_info.transform_rules)?)
+            };
+            let result = sqlx::query(
+                "INSERT INTO tokens (token, created_at, webhook_url, expires_at, label, disabled, forward_url, last_used_at, max_requests, cors_origins, deduplicate_window_secs, response_delay_ms, redact_headers, allowed_methods, allowed_content_types, max_age_seconds, nonce_required, notification_url, notification_events, readonly, max_payload_bytes, transform_rules, token_message_config) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23) ON CONFLICT (token) DO NOTHING",
+            )
+            .bind(&token_info.token)
+            .bind(&token_info.created_at)
+            .bind(&token_info.webhook_url)
+            .bind(&token_info.expires_at)
+            .bind(&token_info.label)
+            .bind(token_info.disabled)
+            .bind(&token_info.forward_url)
+            .bind(&token_info.last_used_at)
+            .bind(token_info.max_requests.map(i64::from))
+            .bind(cors_origins)
+            .bind(token_info.deduplicate_window_secs.map(i64::from))
+            .bind(token_info.response_delay_ms.map(i64::from))
+            .bind(
+                token_info
+                    .redact_headers
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+            )
+            .bind(allowed_methods)
+            .bind(
+                token_info
+                    .allowed_content_types
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+            )
+            .bind(token_info.max_age_seconds.map(i64::from))
+            .bind(token_info.nonce_required)
+            .bind(&token_info.notification_url)
+            .bind(
+                token_info
+                    .notification_events
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+            )
+            .bind(token_info.readonly)
+            .bind(token_info.max_payload_bytes.map(i64::from))
+            .bind(transform_rules)
+            .bind(
+                token_info
+                    .message_config
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if result.rows_affected() == 0 {
+                summary.tokens_skipped += 1;
+                continue;
+            }
+            summary.tokens_imported += 1;
+
+            for (key, value) in &token_info.metadata {
+                sqlx::query(
+                    "INSERT INTO token_metadata (token_id, key, value) VALUES ($1, $2, $3)",
+                )
+                .bind(&token_info.token)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for request in requests {
+            if insert_request(&mut tx, request, true).await? {
+                summary.requests_imported += 1;
+            } else {
+                summary.requests_skipped += 1;
+            }
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+        Ok(summary)
+    }
+
+    async fn create_nonces(&self, token: &str, nonces: &[String], expires_at: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        QueryBuilder::<Postgres>::new("INSERT INTO webhook_nonces (nonce, token_id, expires_at) ")
+            .push_values(nonces, |mut row, nonce| {
+                row.push_bind(nonce).push_bind(token).push_bind(expires_at);
+            })
+            .build()
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn consume_nonce(&self, token: &str, nonce: &str, now: &str) -> Result<NonceStatus> {
+        let result = sqlx::query(
+            "UPDATE webhook_nonces SET used_at = $1 WHERE nonce = $2 AND token_id = $3 AND used_at IS NULL AND expires_at > $4",
+        )
+        .bind(now)
+        .bind(nonce)
+        .bind(token)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() > 0 {
+            return Ok(NonceStatus::Accepted);
+        }
+
+        let used_at: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT used_at FROM webhook_nonces WHERE nonce = $1 AND token_id = $2 AND expires_at > $3",
+        )
+        .bind(nonce)
+        .bind(token)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(match used_at {
+            Some(Some(_)) => NonceStatus::AlreadyUsed,
+            _ => NonceStatus::Unknown,
+        })
+    }
+
+    async fn prune_expired_nonces(&self, now: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM webhook_nonces WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn update_token_settings(
+        &self,
+        token: &str,
+        update: &UpdateTokenRequest,
+    ) -> Result<bool> {
+        // Lists that mean "no restriction" when empty are stored as NULL
+        let non_empty = |list: &[String]| -> Result<Option<String>> {
+            Ok((!list.is_empty())
+                .then(|| serde_json::to_string(list))
+                .transpose()?)
+        };
+
+        let mut builder = QueryBuilder::<Postgres>::new("UPDATE tokens SET ");
+        let mut columns = builder.separated(", ");
+        if let Some(ref label) = update.label {
+            columns
+                .push("label = ")
+                .push_bind_unseparated(label.clone());
+        }
+        if let Some(max_requests) = update.max_requests {
+            columns
+                .push("max_requests = ")
+                .push_bind_unseparated(max_requests.map(i64::from));
+        }
+        if let Some(window_secs) = update.deduplicate_window_secs {
+            columns
+                .push("deduplicate_window_secs = ")
+                .push_bind_unseparated(window_secs.map(i64::from));
+        }
+        if let Some(delay_ms) = update.response_delay_ms {
+            columns
+                .push("response_delay_ms = ")
+                .push_bind_unseparated(delay_ms.map(i64::from));
+        }
+        if let Some(ref headers) = update.redact_headers {
+            columns
+                .push("redact_headers = ")
+                .push_bind_unseparated(headers.as_ref().map(serde_json::to_string).transpose()?);
+        }
+        if let Some(ref methods) = update.allowed_methods {
+            columns
+                .push("allowed_methods = ")
+                .push_bind_unseparated(non_empty(methods)?);
+        }
+        if let Some(ref content_types) = update.allowed_content_types {
+            columns
+                .push("allowed_content_types = ")
+                .push_bind_unseparated(
+                    content_types
+                        .as_ref()
+                        .map(serde_json::to_string)
+                        .transpose()?,
+                );
+        }
+        if let Some(max_age_secs) = update.max_age_seconds {
+            columns
+                .push("max_age_seconds = ")
+                .push_bind_unseparated(max_age_secs.map(i64::from));
+        }
+        if let Some(required) = update.nonce_required {
+            columns
+                .push("nonce_required = ")
+                .push_bind_unseparated(required);
+        }
+        if let Some(ref url) = update.notification_url {
+            columns
+                .push("notification_url = ")
+                .push_bind_unseparated(url.clone());
+        }
+        if let Some(ref events) = update.notification_events {
+            columns
+                .push("notification_events = ")
+                .push_bind_unseparated(events.as_ref().map(serde_json::to_string).transpose()?);
+        }
+        if let Some(readonly) = update.readonly {
+            columns.push("readonly = ").push_bind_unseparated(readonly);
+        }
+        if let Some(max_bytes) = update.max_payload_bytes {
+            columns
+                .push("max_payload_bytes = ")
+                .push_bind_unseparated(max_bytes.map(i64::from));
+        }
+        if let Some(ref rules) = update.transform_rules {
+            let rules = (!rules.is_empty())
+                .then(|| serde_json::to_string(rules))
+                .transpose()?;
+            columns
+                .push("transform_rules = ")
+                .push_bind_unseparated(rules);
+        }
+        if let Some(ref extractor) = update.message_config {
+            columns
+                .push("token_message_config = ")
+                .push_bind_unseparated(extractor.as_ref().map(serde_json::to_string).transpose()?);
+        }
+        builder.push(" WHERE token = ").push_bind(token);
+
+        let result = builder.build().execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn token_exists(&self, token: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tokens WHERE token = $1")
+            .bind(token)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count > 0)
+    }
+
+    async fn count_tokens(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tokens")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn set_token_disabled(&self, token: &str, disabled: bool) -> Result<bool> {
+        let result = sqlx::query("UPDATE tokens SET disabled = $1 WHERE token = $2")
+            .bind(disabled)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_readonly_key_hash(&self, token: &str) -> Result<Option<String>> {
+        let hash: Option<Option<String>> =
+            sqlx::query_scalar("SELECT readonly_key FROM tokens WHERE token = $1")
+                .bind(token)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(hash.flatten())
+    }
+
+    async fn set_readonly_key_hash(&self, token: &str, hash: Option<&str>) -> Result<bool> {
+        let result = sqlx::query("UPDATE tokens SET readonly_key = $1 WHERE token = $2")
+            .bind(hash)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_forward_url(&self, token: &str, url: Option<&str>) -> Result<bool> {
+        let result = sqlx::query("UPDATE tokens SET forward_url = $1 WHERE token = $2")
+            .bind(url)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_forward_targets(&self, token: &str) -> Result<Vec<ForwardTarget>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT f.id, f.url, f.enabled, f.headers_json, f.forward_region, f.created_at,
+                   r.request_id, r.status_code, r.error, r.delivered_at
+            FROM token_forwards f
+            LEFT JOIN forward_results r ON r.id = (
+                SELECT id FROM forward_results WHERE forward_id = f.id ORDER BY id DESC LIMIT 1
+            )
+            WHERE f.token_id = $1
+            ORDER BY f.created_at
+            "#,
+        )
+        .bind(token)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(forward_target_from_row).collect())
+    }
+
+    async fn create_forward_target(&self, token: &str, target: &ForwardTarget) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO token_forwards (id, token_id, url, enabled, headers_json, forward_region, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&target.id)
+        .bind(token)
+        .bind(&target.url)
+        .bind(target.enabled)
+        .bind(serde_json::to_string(&target.headers)?)
+        .bind(&target.forward_region)
+        .bind(&target.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_forward_target(&self, token: &str, forward_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM token_forwards WHERE id = $1 AND token_id = $2")
+            .bind(forward_id)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record_forward_result(&self, result: &ForwardResult) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO forward_results (forward_id, request_id, status_code, error, delivered_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&result.forward_id)
+        .bind(&result.request_id)
+        .bind(result.status_code.map(i64::from))
+        .bind(&result.error)
+        .bind(&result.delivered_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_cors_origins(&self, token: &str, origins: &[String]) -> Result<bool> {
+        let origins = if origins.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(origins)?)
+        };
+        let result = sqlx::query("UPDATE tokens SET cors_origins = $1 WHERE token = $2")
+            .bind(origins)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_webhook_secret(&self, token: &str) -> Result<Option<String>> {
+        let secret: Option<Option<String>> =
+            sqlx::query_scalar("SELECT webhook_secret FROM tokens WHERE token = $1")
+                .bind(token)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(secret.flatten())
+    }
+
+    async fn set_webhook_secret(&self, token: &str, secret: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE tokens
+            SET webhook_secret = $1, webhook_secret_prev = NULL, webhook_secret_prev_expires_at = NULL
+            WHERE token = $2
+            "#,
+        )
+        .bind(secret)
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_previous_webhook_secret(&self, token: &str) -> Result<Option<String>> {
+        let secret: Option<Option<String>> = sqlx::query_scalar(
+            r#"
+            SELECT webhook_secret_prev FROM tokens
+            WHERE token = $1 AND webhook_secret_prev_expires_at > $2
+            "#,
+        )
+        .bind(token)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(secret.flatten())
+    }
+
+    async fn rotate_webhook_secret(
+        &self,
+        token: &str,
+        secret: &str,
+        previous_expires_at: &str,
+    ) -> Result<bool> {
+        // Without a current secret there is nothing to keep accepting
+        let result = sqlx::query(
+            r#"
+            UPDATE tokens
+            SET webhook_secret_prev = webhook_secret,
+                webhook_secret_prev_expires_at =
+                    CASE WHEN webhook_secret IS NULL THEN NULL ELSE $1 END,
+                webhook_secret = $2
+            WHERE token = $3
+            "#,
+        )
+        .bind(previous_expires_at)
+        .bind(secret)
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_token_metadata(&self, token: &str) -> Result<HashMap<String, String>> {
+        let rows = sqlx::query("SELECT key, value FROM token_metadata WHERE token_id = $1")
+            .bind(token)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("key"), row.get("value")))
+            .collect())
+    }
+
+    async fn set_token_metadata(
+        &self,
+        token: &str,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM token_metadata WHERE token_id = $1")
+            .bind(token)
+            .execute(&mut *tx)
+            .await?;
+
+        for (key, value) in metadata {
+            sqlx::query("INSERT INTO token_metadata (token_id, key, value) VALUES ($1, $2, $3)")
+                .bind(token)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_token_metadata(&self, token: &str, key: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM token_metadata WHERE token_id = $1 AND key = $2")
+            .bind(token)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn append_audit(&self, entries: &[AuditEntry]) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        QueryBuilder::<Postgres>::new(
+            "INSERT INTO audit_log (id, occurred_at, action, actor_ip, token_id, details) ",
+        )
+        .push_values(entries, |mut row, entry| {
+            row.push_bind(&entry.id)
+                .push_bind(&entry.occurred_at)
+                .push_bind(&entry.action)
+                .push_bind(&entry.actor_ip)
+                .push_bind(&entry.token_id)
+                .push_bind(entry.details.as_ref().map(serde_json::Value::to_string));
+        })
+        .build()
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_audit(&self, query: &AuditQuery, limit: u32) -> Result<Vec<AuditEntry>> {
+        let mut builder = QueryBuilder::<Postgres>::new(
+            "SELECT id, occurred_at, action, actor_ip, token_id, details FROM audit_log WHERE 1 = 1",
+        );
+        if let Some(ref from) = query.from {
+            builder.push(" AND occurred_at >= ").push_bind(from);
+        }
+        if let Some(ref to) = query.to {
+            builder.push(" AND occurred_at < ").push_bind(to);
+        }
+        if let Some(ref action) = query.action {
+            builder.push(" AND action = ").push_bind(action);
+        }
+        if let Some(ref token_id) = query.token_id {
+            builder.push(" AND token_id = ").push_bind(token_id);
+        }
+        builder
+            .push(" ORDER BY occurred_at DESC LIMIT ")
+            .push_bind(i64::from(limit));
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AuditEntry {
+                id: row.get("id"),
+                occurred_at: row.get("occurred_at"),
+                action: row.get("action"),
+                actor_ip: row.get("actor_ip"),
+                token_id: row.get("token_id"),
+                details: row
+                    .get::<Option<String>, _>("details")
+                    .and_then(|details| serde_json::from_str(&details).ok()),
+            })
+            .collect())
+    }
+
+    async fn get_ip_rules(&self, token: &str) -> Result<Vec<IpRule>> {
+        let rows = sqlx::query("SELECT cidr, action FROM token_ip_rules WHERE token_id = $1")
+            .bind(token)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let rules = rows
+            .into_iter()
+            .map(|row| IpRule {
+                cidr: row.get("cidr"),
+                action: match row.get::<&str, _>("action") {
+                    "allow" => IpRuleAction::Allow,
+                    _ => IpRuleAction::Deny,
+                },
+            })
+            .collect();
+
+        Ok(rules)
+    }
+
+    async fn set_ip_rules(&self, token: &str, rules: &[IpRule]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM token_ip_rules WHERE token_id = $1")
+            .bind(token)
+            .execute(&mut *tx)
+            .await?;
+
+        for rule in rules {
+            sqlx::query("INSERT INTO token_ip_rules (token_id, cidr, action) VALUES ($1, $2, $3)")
+                .bind(token)
+                .bind(&rule.cidr)
+                .bind(rule.action.as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn prune_expired_tokens(&self) -> Result<u64> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        let requests = sqlx::query(
+            r#"
+            DELETE FROM webhook_requests WHERE token_id IN
+                (SELECT token FROM tokens WHERE expires_at IS NOT NULL AND expires_at <= $1)
+            "#,
+        )
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+
+        let tokens =
+            sqlx::query("DELETE FROM tokens WHERE expires_at IS NOT NULL AND expires_at <= $1")
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+
+        tx.commit().await?;
+
+        if tokens.rows_affected() > 0 {
+            info!(
+                "Pruned {} expired tokens and {} webhook requests",
+                tokens.rows_affected(),
+                requests.rows_affected()
+            );
+        }
+
+        Ok(tokens.rows_affected())
+    }
+
+    async fn prune_old_requests(&self, retention_days: u32) -> Result<u64> {
+        let cutoff =
+            (chrono::Utc::now() - chrono::Duration::days(retention_days.into())).to_rfc3339();
+
+        let result = sqlx::query("DELETE FROM webhook_requests WHERE date < $1 AND NOT flagged")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn prune_excess_requests_per_token(&self, max_per_token: u32) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            WITH ranked AS (
+                SELECT id, ROW_NUMBER() OVER (PARTITION BY token_id ORDER BY date DESC, id DESC) AS position
+                FROM webhook_requests
+                WHERE NOT flagged
+            )
+            DELETE FROM webhook_requests
+            WHERE id IN (SELECT id FROM ranked WHERE position > $1)
+            "#,
+        )
+        .bind(max_per_token as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn page_stats(&self) -> Result<PageStats> {
+        let page_count: i64 = sqlx::query_scalar(
+            "SELECT pg_database_size(current_database()) / current_setting('block_size')::BIGINT",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        // PostgreSQL keeps free space per table instead of in a database-wide free list
+        Ok(PageStats {
+            page_count,
+            freelist_count: None,
+        })
+    }
+
+    async fn vacuum(&self) -> Result<()> {
+        // VACUUM cannot run inside the implicit transaction of a prepared statement
+        sqlx::raw_sql("VACUUM").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn analyze(&self) -> Result<()> {
+        sqlx::raw_sql("ANALYZE").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn integrity_check(&self) -> Result<Vec<String>> {
+        // PostgreSQL enforces foreign keys on every write and verifies page checksums as it
+        // reads; there is no equivalent of SQLite's integrity pragmas to run
+        Ok(Vec::new())
+    }
+
+    async fn record_health_check(&self, check: &HealthCheck) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO health_checks (id, checked_at, check_type, result) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&check.id)
+        .bind(&check.checked_at)
+        .bind(&check.check_type)
+        .bind(&check.result)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_last_health_check(&self, check_type: &str) -> Result<Option<HealthCheck>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, checked_at, check_type, result FROM health_checks
+            WHERE check_type = $1
+            ORDER BY checked_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(check_type)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| HealthCheck {
+            id: row.get("id"),
+            checked_at: row.get("checked_at"),
+            check_type: row.get("check_type"),
+            result: row.get("result"),
+        }))
+    }
+
+    async fn delete_token(&self, token: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM webhook_requests WHERE token_id = $1")
+            .bind(token)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM tokens WHERE token = $1")
+            .bind(token)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn clone_token(&self, source: &str, token_info: &TokenInfo) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(&format!(
+            "INSERT INTO tokens (token, created_at, webhook_url, {columns}) SELECT $1, $2, $3, {columns} FROM tokens WHERE token = $4",
+            columns = CLONED_TOKEN_COLUMNS
+        ))
+        .bind(&token_info.token)
+        .bind(&token_info.created_at)
+        .bind(&token_info.webhook_url)
+        .bind(source)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        for (table, columns) in CLONED_TOKEN_TABLES {
+            sqlx::query(&format!(
+                "INSERT INTO {table} (token_id, {columns}) SELECT $1, {columns} FROM {table} WHERE token_id = $2"
+            ))
+            .bind(&token_info.token)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Mirror targets are keyed by their own ID, so each copy gets a new one
+        let forward_ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM token_forwards WHERE token_id = $1")
+                .bind(source)
+                .fetch_all(&mut *tx)
+                .await?;
+        for forward_id in forward_ids {
+            sqlx::query(
+                r#"
+                INSERT INTO token_forwards (id, token_id, url, enabled, headers_json, forward_region, created_at)
+                SELECT $1, $2, url, enabled, headers_json, forward_region, created_at
+                FROM token_forwards WHERE id = $3
+                "#,
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&token_info.token)
+            .bind(&forward_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn set_custom_response(&self, token: &str, response: &CustomResponse) -> Result<()> {
+        let headers_json = serde_json::to_string(&response.headers)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO responses (token_id, status_code, response_headers, response_body)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (token_id) DO UPDATE SET
+                status_code = excluded.status_code,
+                response_headers = excluded.response_headers,
+                response_body = excluded.response_body
+            "#,
+        )
+        .bind(token)
+        .bind(response.status_code as i64)
+        .bind(headers_json)
+        .bind(&response.body)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_custom_response(&self, token: &str) -> Result<Option<CustomResponse>> {
+        let row = sqlx::query(
+            "SELECT status_code, response_headers, response_body FROM responses WHERE token_id = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(CustomResponse {
+            status_code: row.get::<i64, _>("status_code") as u16,
+            headers: serde_json::from_str(row.get("response_headers"))?,
+            body: row.get("response_body"),
+        }))
+    }
+
+    async fn delete_custom_response(&self, token: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM responses WHERE token_id = $1")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_slack_notification(
+        &self,
+        token: &str,
+        notification: &SlackNotification,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO slack_notifications (token_id, webhook_url, template, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (token_id) DO UPDATE SET
+                webhook_url = excluded.webhook_url,
+                template = excluded.template,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(token)
+        .bind(&notification.webhook_url)
+        .bind(&notification.template)
+        .bind(&notification.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_slack_notification(&self, token: &str) -> Result<Option<SlackNotification>> {
+        let row = sqlx::query(
+            "SELECT webhook_url, template, created_at FROM slack_notifications WHERE token_id = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| SlackNotification {
+            webhook_url: row.get("webhook_url"),
+            template: row.get("template"),
+            created_at: row.get("created_at"),
+        }))
+    }
+
+    async fn delete_slack_notification(&self, token: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM slack_notifications WHERE token_id = $1")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_email_notification(
+        &self,
+        token: &str,
+        notification: &EmailNotification,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_notifications (token_id, to_address, events, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (token_id) DO UPDATE SET
+                to_address = excluded.to_address,
+                events = excluded.events,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(token)
+        .bind(&notification.to)
+        .bind(serde_json::to_string(&notification.events)?)
+        .bind(&notification.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_email_notification(&self, token: &str) -> Result<Option<EmailNotification>> {
+        let row = sqlx::query(
+            "SELECT to_address, events, created_at FROM email_notifications WHERE token_id = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| {
+            Ok(EmailNotification {
+                to: row.get("to_address"),
+                events: serde_json::from_str(row.get("events"))?,
+                created_at: row.get("created_at"),
+            })
+        })
+        .transpose()
+    }
+
+    async fn delete_email_notification(&self, token: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM email_notifications WHERE token_id = $1")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn store_webhook_request(&self, request: &WebhookRequest) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        insert_request(&mut tx, request, false).await?;
+
+        sqlx::query("UPDATE tokens SET last_used_at = $1 WHERE token = $2")
+            .bind(&request.date)
+            .bind(&request.token_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn find_idempotent_request(
+        &self,
+        token: &str,
+        idempotency_key: &str,
+        since: &str,
+    ) -> Result<Option<String>> {
+        let request_id = sqlx::query_scalar(
+            r#"
+            SELECT request_id FROM webhook_idempotency
+            WHERE token_id = $1 AND idempotency_key = $2 AND created_at >= $3
+            "#,
+        )
+        .bind(token)
+        .bind(idempotency_key)
+        .bind(since)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(request_id)
+    }
+
+    async fn record_idempotency_key(
+        &self,
+        token: &str,
+        idempotency_key: &str,
+        request_id: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_idempotency (token_id, idempotency_key, request_id, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (token_id, idempotency_key) DO UPDATE SET
+                request_id = excluded.request_id,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(token)
+        .bind(idempotency_key)
+        .bind(request_id)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_duplicate_request(
+        &self,
+        token: &str,
+        body_sha256: &str,
+        since: &str,
+    ) -> Result<Option<String>> {
+        let request_id = sqlx::query_scalar(
+            r#"
+            SELECT id FROM webhook_requests
+            WHERE token_id = $1 AND body_sha256 = $2 AND date >= $3
+            ORDER BY date DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(token)
+        .bind(body_sha256)
+        .bind(since)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(request_id)
+    }
+
+    async fn prune_idempotency_keys(&self, before: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM webhook_idempotency WHERE created_at < $1")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn set_request_forward_status(&self, request_id: &str, status: u16) -> Result<()> {
+        sqlx::query("UPDATE webhook_requests SET forward_status = $1 WHERE id = $2")
+            .bind(status as i64)
+            .bind(request_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_forward_attempt(&self, attempt: &ForwardAttempt) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO forward_attempts (request_id, attempt, status_code, error, attempted_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&attempt.request_id)
+        .bind(attempt.attempt as i64)
+        .bind(attempt.status_code.map(i64::from))
+        .bind(&attempt.error)
+        .bind(&attempt.attempted_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_forward_attempts(&self, request_id: &str) -> Result<Vec<ForwardAttempt>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT request_id, attempt, status_code, error, attempted_at
+            FROM forward_attempts
+            WHERE request_id = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(request_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let attempts = rows
+            .into_iter()
+            .map(|row| ForwardAttempt {
+                request_id: row.get("request_id"),
+                attempt: row.get::<i64, _>("attempt") as u32,
+                status_code: row
+                    .get::<Option<i64>, _>("status_code")
+                    .map(|status| status as u16),
+                error: row.get("error"),
+                attempted_at: row.get("attempted_at"),
+            })
+            .collect();
+
+        Ok(attempts)
+    }
+
+    async fn create_dead_letter(&self, dead_letter: &DeadLetter) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO dead_letters (id, token_id, request_id, forward_url, last_error, failed_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&dead_letter.id)
+        .bind(&dead_letter.token_id)
+        .bind(&dead_letter.request_id)
+        .bind(&dead_letter.forward_url)
+        .bind(&dead_letter.last_error)
+        .bind(&dead_letter.failed_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_dead_letters(&self, token: &str) -> Result<Vec<DeadLetter>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, token_id, request_id, forward_url, last_error, failed_at
+            FROM dead_letters
+            WHERE token_id = $1
+            ORDER BY failed_at DESC
+            "#,
+        )
+        .bind(token)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(dead_letter_from_row).collect())
+    }
+
+    async fn get_dead_letter(&self, token: &str, id: &str) -> Result<Option<DeadLetter>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, token_id, request_id, forward_url, last_error, failed_at
+            FROM dead_letters
+            WHERE id = $1 AND token_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(dead_letter_from_row))
+    }
+
+    async fn delete_dead_letter(&self, token: &str, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM dead_letters WHERE id = $1 AND token_id = $2")
+            .bind(id)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_requests_by_token(&self, token: &str) -> Result<i64> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM webhook_requests WHERE token_id = $1")
+                .bind(token)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count)
+    }
+
+    async fn get_request_by_id(
+        &self,
+        token: &str,
+        request_id: &str,
+    ) -> Result<Option<WebhookRequest>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM webhook_requests WHERE id = $1 AND token_id = $2",
+            REQUEST_COLUMNS
+        ))
+        .bind(request_id)
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(webhook_request_from_row).transpose()
+    }
+
+    async fn get_global_stats(&self) -> Result<GlobalStats> {
+        let (active_since, today, week_start) = global_stats_cutoffs();
+        let row = sqlx::query(
+            r#"
+            WITH token_counts AS (
+                SELECT COUNT(*) AS total_tokens,
+                       COUNT(CASE WHEN last_used_at >= $1 THEN 1 END) AS active_tokens
+                FROM tokens
+            ),
+            request_counts AS (
+                SELECT COUNT(*) AS total_requests,
+                       COUNT(CASE WHEN date >= $2 THEN 1 END) AS requests_today,
+                       COUNT(CASE WHEN date >= $3 THEN 1 END) AS requests_this_week
+                FROM webhook_requests
+            ),
+            largest_token AS (
+                SELECT token_id, COUNT(*) AS request_count
+                FROM webhook_requests
+                GROUP BY token_id
+                ORDER BY request_count DESC
+                LIMIT 1
+            )
+            SELECT t.total_tokens, t.active_tokens,
+                   r.total_requests, r.requests_today, r.requests_this_week,
+                   pg_database_size(current_database()) AS db_size_bytes,
+                   l.token_id AS largest_token, l.request_count AS largest_token_requests
+            FROM token_counts t
+            CROSS JOIN request_counts r
+            LEFT JOIN largest_token l ON 1 = 1
+            "#,
+        )
+        .bind(active_since)
+        .bind(today)
+        .bind(week_start)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let largest_token: Option<String> = row.get("largest_token");
+        Ok(GlobalStats {
+            total_tokens: row.get("total_tokens"),
+            active_tokens: row.get("active_tokens"),
+            total_requests: row.get("total_requests"),
+            requests_today: row.get("requests_today"),
+            requests_this_week: row.get("requests_this_week"),
+            db_size_bytes: row.get("db_size_bytes"),
+            largest_token_by_count: largest_token.map(|token| TokenRequestCount {
+                token,
+                request_count: row.get("largest_token_requests"),
+            }),
+            max_tokens: None,
+        })
+    }
+
+    async fn get_token_size_stats(&self, token: &str) -> Result<TokenStats> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS request_count,
+                   COALESCE(SUM(body_size_bytes), 0)::BIGINT AS total_bytes,
+                   COALESCE(AVG(body_size_bytes), 0)::DOUBLE PRECISION AS avg_bytes,
+                   COALESCE(MAX(body_size_bytes), 0) AS max_bytes,
+                   MIN(date) AS oldest_request,
+                   MAX(date) AS newest_request
+            FROM webhook_requests
+            WHERE token_id = $1
+            "#,
+        )
+        .bind(token)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(TokenStats {
+            request_count: row.get("request_count"),
+            total_bytes: row.get("total_bytes"),
+            avg_bytes: row.get("avg_bytes"),
+            max_bytes: row.get("max_bytes"),
+            oldest_request: row.get("oldest_request"),
+            newest_request: row.get("newest_request"),
+        })
+    }
+
+    async fn get_hourly_stats(&self, token: &str, since: &str) -> Result<Vec<HourlyBucket>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT to_char(date::timestamptz AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24') AS hour, method, COUNT(*) AS count
+            FROM webhook_requests
+            WHERE token_id = $1 AND date >= $2
+            GROUP BY 1, method
+            ORDER BY 1, method
+            "#,
+        )
+        .bind(token)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| HourlyBucket {
+                hour: row.get("hour"),
+                method: row.get("method"),
+                count: row.get("count"),
+            })
+            .collect())
+    }
+
+    async fn get_heatmap_data(&self, token: &str, weeks: u32) -> Result<Vec<HeatmapCell>> {
+        let since = (chrono::Utc::now() - chrono::TimeDelta::weeks(i64::from(weeks))).to_rfc3339();
+        let rows = sqlx::query(
+            r#"
+            SELECT EXTRACT(DOW FROM date::timestamptz AT TIME ZONE 'UTC')::bigint AS dow,
+                   EXTRACT(HOUR FROM date::timestamptz AT TIME ZONE 'UTC')::bigint AS hour,
+                   COUNT(*)::float8 / $1 AS avg_count
+            FROM webhook_requests
+            WHERE token_id = $2 AND date >= $3
+            GROUP BY 1, 2
+            ORDER BY 1, 2
+            "#,
+        )
+        .bind(f64::from(weeks))
+        .bind(token)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| HeatmapCell {
+                dow: row.get::<i64, _>("dow") as u8,
+                hour: row.get::<i64, _>("hour") as u8,
+                avg_count: row.get("avg_count"),
+            })
+            .collect())
+    }
+
+    async fn find_duplicate_requests(
+        &self,
+        token: &str,
+        window: Duration,
+    ) -> Result<Vec<DuplicateGroup>> {
+        let since = (chrono::Utc::now() - chrono::TimeDelta::from_std(window)?).to_rfc3339();
+        let rows = sqlx::query(
+            r#"
+            WITH windowed AS (
+                SELECT id, date, method, body_sha256,
+                       COUNT(*) OVER (PARTITION BY body_sha256) AS count,
+                       ROW_NUMBER() OVER (PARTITION BY body_sha256 ORDER BY date, id) AS from_first,
+                       ROW_NUMBER() OVER (PARTITION BY body_sha256 ORDER BY date DESC, id DESC) AS from_last
+                FROM webhook_requests
+                WHERE token_id = $1 AND date >= $2 AND body_sha256 IS NOT NULL
+            )
+            SELECT first.body_sha256 AS hash, first.count, first.id AS first_id,
+                   last.id AS last_id, first.method
+            FROM windowed first
+            JOIN windowed last ON last.body_sha256 = first.body_sha256 AND last.from_last = 1
+            WHERE first.from_first = 1 AND first.count > 1
+            ORDER BY first.count DESC, last.date DESC
+            "#,
+        )
+        .bind(token)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DuplicateGroup {
+                hash: row.get("hash"),
+                count: row.get("count"),
+                first_id: row.get("first_id"),
+                last_id: row.get("last_id"),
+                method: row.get("method"),
+            })
+            .collect())
+    }
+
+    async fn get_country_counts(&self, token: &str) -> Result<Vec<CountryCount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT source_country AS country, COUNT(*) AS count
+            FROM webhook_requests
+            WHERE token_id = $1 AND source_country IS NOT NULL
+            GROUP BY source_country
+            ORDER BY count DESC, country
+            "#,
+        )
+        .bind(token)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| CountryCount {
+                country: row.get("country"),
+                count: row.get("count"),
+            })
+            .collect())
+    }
+
+    async fn get_user_agent_counts(&self, token: &str) -> Result<Vec<UserAgentCount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT ua_browser, ua_os, ua_device_type, ua_client, COUNT(*) AS count
+            FROM webhook_requests
+            WHERE token_id = $1 AND (ua_browser IS NOT NULL OR ua_os IS NOT NULL)
+            GROUP BY ua_browser, ua_os, ua_device_type, ua_client
+            ORDER BY count DESC, ua_browser, ua_os
+            "#,
+        )
+        .bind(token)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| UserAgentCount {
+                browser: row.get("ua_browser"),
+                os: row.get("ua_os"),
+                device_type: row.get("ua_device_type"),
+                client: row
+                    .get::<Option<&str>, _>("ua_client")
+                    .and_then(ClientKind::from_name),
+                count: row.get("count"),
+            })
+            .collect())
+    }
+
+    async fn get_top_headers(&self, token: &str, limit: i64) -> Result<Vec<HeaderCount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT header.key AS name, COUNT(*) AS count
+            FROM webhook_requests, json_each(webhook_requests.headers::json) AS header
+            WHERE token_id = $1
+            GROUP BY header.key
+            ORDER BY count DESC, name
+            LIMIT $2
+            "#,
+        )
+        .bind(token)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| HeaderCount {
+                name: row.get("name"),
+                count: row.get("count"),
+            })
+            .collect())
+    }
+
+    async fn get_requests_version(&self, token: &str) -> Result<Option<i64>> {
+        let version = sqlx::query_scalar("SELECT requests_version FROM tokens WHERE token = $1")
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(version)
+    }
+
+    async fn get_recent_body_objects(
+        &self,
+        token: &str,
+        limit: u32,
+    ) -> Result<Vec<serde_json::Value>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT body_object FROM webhook_requests
+            WHERE token_id = $1 AND body_object IS NOT NULL
+            ORDER BY date DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(token)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(row.get("body_object"))?))
+            .collect()
+    }
+
+    async fn update_request_comment(
+        &self,
+        token: &str,
+        request_id: &str,
+        comment: Option<&str>,
+    ) -> Result<Option<WebhookRequest>> {
+        let row = sqlx::query(&format!(
+            "UPDATE webhook_requests SET comment = $1 WHERE id = $2 AND token_id = $3 RETURNING {}",
+            REQUEST_COLUMNS
+        ))
+        .bind(comment)
+        .bind(request_id)
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(webhook_request_from_row).transpose()
+    }
+
+    async fn set_request_flag(
+        &self,
+        token: &str,
+        request_id: &str,
+        flagged: bool,
+    ) -> Result<Option<WebhookRequest>> {
+        let row = sqlx::query(&format!(
+            "UPDATE webhook_requests SET flagged = $1 WHERE id = $2 AND token_id = $3 RETURNING {}",
+            REQUEST_COLUMNS
+        ))
+        .bind(flagged)
+        .bind(request_id)
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(webhook_request_from_row).transpose()
+    }
+
+    async fn clear_request_flags(&self, token: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE webhook_requests SET flagged = FALSE WHERE token_id = $1 AND flagged",
+        )
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_webhook_request(&self, token: &str, request_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM webhook_requests WHERE id = $1 AND token_id = $2")
+            .bind(request_id)
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_requests_filtered(
+        &self,
+        token: &str,
+        filter: &WebhookRequestFilter,
+        limit: u32,
+        before_id: Option<&str>,
+    ) -> Result<Vec<WebhookRequest>> {
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM webhook_requests WHERE token_id = ",
+            REQUEST_COLUMNS
+        ));
+        query.push_bind(token);
+
+        if let Some(before_id) = before_id {
+            query
+                .push(" AND date < (SELECT date FROM webhook_requests WHERE id = ")
+                .push_bind(before_id)
+                .push(")");
+        }
+        if let Some(ref method) = filter.method {
+            query
+                .push(" AND method = ")
+                .push_bind(method.to_uppercase());
+        }
+        if let Some(from) = filter.from {
+            query.push(" AND date >= ").push_bind(from.to_rfc3339());
+        }
+        if let Some(to) = filter.to {
+            query.push(" AND date <= ").push_bind(to.to_rfc3339());
+        }
+        if let Some(ref content_type) = filter.content_type {
+            // Prefix match so that `application/json` also matches `application/json; charset=utf-8`
+            query
+                .push(" AND content_type LIKE ")
+                .push_bind(format!("{}%", escape_like(content_type)))
+                .push(r" ESCAPE '\'");
+        }
+        if let Some(ref duplicate_of) = filter.duplicate_of {
+            query
+                .push(" AND id <> ")
+                .push_bind(duplicate_of)
+                .push(" AND body_sha256 = (SELECT body_sha256 FROM webhook_requests WHERE id = ")
+                .push_bind(duplicate_of)
+                .push(" AND token_id = ")
+                .push_bind(token)
+                .push(")");
+        }
+        if let Some(comment) = filter
+            .comment
+            .as_deref()
+            .filter(|comment| !comment.trim().is_empty())
+        {
+            query
+                .push(" AND comment IS NOT NULL AND to_tsvector('simple', comment) @@ plainto_tsquery('simple', ")
+                .push_bind(comment)
+                .push(")");
+        }
+        if let Some(ref key) = filter.query_key {
+            match filter.query_value {
+                Some(ref value) => query
+                    .push(" AND (query_parameters::jsonb -> ")
+                    .push_bind(key)
+                    .push(") @> jsonb_build_array(")
+                    .push_bind(value)
+                    .push("::text)"),
+                None => query
+                    .push(" AND (query_parameters::jsonb -> ")
+                    .push_bind(key)
+                    .push(") IS NOT NULL"),
+            };
+        }
+        if let Some(flagged) = filter.flagged {
+            query.push(" AND flagged = ").push_bind(flagged);
+        }
+
+        query
+            .push(" ORDER BY date DESC LIMIT ")
+            .push_bind(limit as i64);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+
+        rows.iter().map(webhook_request_from_row).collect()
+    }
+
+    async fn search_requests(
+        &self,
+        token: &str,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<WebhookRequest>> {
+        let (sql, search) = if query.split_whitespace().nth(1).is_some() {
+            let sql = format!(
+                r#"
+                SELECT {} FROM webhook_requests
+                WHERE token_id = $1
+                  AND to_tsvector('simple', COALESCE(body, '') || ' ' || value)
+                      @@ plainto_tsquery('simple', $2)
+                ORDER BY ts_rank(
+                    to_tsvector('simple', COALESCE(body, '') || ' ' || value),
+                    plainto_tsquery('simple', $2)
+                ) DESC
+                LIMIT $3
+                "#,
+                REQUEST_COLUMNS
+            );
+            (sql, query.to_string())
+        } else {
+            let sql = format!(
+                r#"
+                SELECT {} FROM webhook_requests
+                WHERE token_id = $1
+                  AND (body ILIKE $2 ESCAPE '\' OR value ILIKE $2 ESCAPE '\' OR message ILIKE $2 ESCAPE '\')
+                ORDER BY date DESC
+                LIMIT $3
+                "#,
+                REQUEST_COLUMNS
+            );
+            (sql, format!("%{}%", escape_like(query.trim())))
+        };
+
+        let rows = sqlx::query(&sql)
+            .bind(token)
+            .bind(search)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(webhook_request_from_row).collect()
+    }
+
+    fn stream_webhook_requests<'a>(
+        &'a self,
+        token: &'a str,
+        filter: &'a WebhookRequestFilter,
+    ) -> RequestStream<'a> {
+        let rows = sqlx::query(STREAM_REQUESTS_SQL.as_str())
+            .bind(token)
+            .bind(filter.method.as_ref().map(|method| method.to_uppercase()))
+            .bind(filter.from.map(|from| from.to_rfc3339()))
+            .bind(filter.to.map(|to| to.to_rfc3339()))
+            .bind(
+                filter
+                    .content_type
+                    .as_deref()
+                    .map(|content_type| format!("{}%", escape_like(content_type))),
+            )
+            .bind(filter.duplicate_of.as_deref())
+            .bind(
+                filter
+                    .comment
+                    .as_deref()
+                    .filter(|comment| !comment.trim().is_empty()),
+            )
+            .bind(filter.query_key.as_deref())
+            .bind(filter.query_value.as_deref())
+            .bind(filter.flagged)
+            .fetch(&self.pool);
+
+        Box::pin(rows.map(|row| webhook_request_from_row(&row?)))
+    }
+}
+
+fn webhook_request_from_row(row: &PgRow) -> Result<WebhookRequest> {
+    let headers: HashMap<String, Vec<String>> = serde_json::from_str(row.get("headers"))?;
+    let query_parameters: HashMap<String, Vec<String>> =
+        serde_json::from_str(row.get("query_parameters"))?;
+    let body_object: Option<serde_json::Value> = row
+        .get::<Option<String>, _>("body_object")
+        .map(|s| serde_json::from_str(&s))
+        .transpose()?;
+
+    Ok(WebhookRequest {
+        id: row.get("id"),
+        date: row.get("date"),
+        token_id: row.get("token_id"),
+        message_object: MessageObject {
+            method: row.get("method"),
+            value: row.get("value"),
+            headers,
+            query_parameters,
+            body: row.get("body"),
+            body_object,
+            content_type: row.get("content_type"),
+            compressed_size: row
+                .get::<Option<i64>, _>("compressed_size")
+                .map(|size| size as usize),
+            body_encoding: row.get("body_encoding"),
+            body_size_bytes: row
+                .get::<Option<i64>, _>("body_size_bytes")
+                .map(|size| size as usize),
+            body_sha256: row.get("body_sha256"),
+            cookies: row
+                .get::<Option<String>, _>("cookies")
+                .and_then(|cookies| serde_json::from_str(&cookies).ok())
+                .unwrap_or_default(),
+        },
+        message: row.get("message"),
+        replayed_from: row.get("replayed_from"),
+        forward_status: row
+            .get::<Option<i64>, _>("forward_status")
+            .map(|status| status as u16),
+        signature_valid: row.get("signature_valid"),
+        source_ip: row.get("source_ip"),
+        peer_ip: row.get("peer_ip"),
+        source_country: row.get("source_country"),
+        source_city: row.get("source_city"),
+        source_asn: row
+            .get::<Option<i64>, _>("source_asn")
+            .map(|asn| asn as u32),
+        ua_browser: row.get("ua_browser"),
+        ua_os: row.get("ua_os"),
+        ua_device_type: row.get("ua_device_type"),
+        ua_client: row
+            .get::<Option<&str>, _>("ua_client")
+            .and_then(ClientKind::from_name),
+        comment: row.get("comment"),
+        flagged: row.get("flagged"),
+        redacted_headers: row
+            .get::<Option<String>, _>("redacted_headers")
+            .and_then(|headers| serde_json::from_str(&headers).ok())
+            .unwrap_or_default(),
+        trace_id: row.get("trace_id"),
+    })
+}
+
+/// Insert a request row; with `skip_existing` a request whose ID is already stored is left
+/// alone and `false` is returned
+async fn insert_request(
+    conn: &mut PgConnection,
+    request: &WebhookRequest,
+    skip_existing: bool,
+) -> Result<bool> {
+    let headers_json = serde_json::to_string(&request.message_object.headers)?;
+    let query_params_json = serde_json::to_string(&request.message_object.query_parameters)?;
+    let body_object_json = request
+        .message_object
+        .body_object
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    let redacted_headers_json = if request.redacted_headers.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&request.redacted_headers)?)
+    };
+    let cookies_json = if request.message_object.cookies.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&request.message_object.cookies)?)
+    };
+
+    let result = sqlx::query(&format!(
+        r#"
+        INSERT INTO webhook_requests
+        (id, date, token_id, method, value, headers, query_parameters, body, body_object, message, replayed_from, forward_status, signature_valid, content_type, compressed_size, body_encoding, source_ip, peer_ip, source_country, source_city, source_asn, ua_browser, ua_os, ua_device_type, ua_client, body_size_bytes, body_sha256, comment, flagged, redacted_headers, cookies, trace_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32)
+        {}
+        "#,
+        if skip_existing {
+            "ON CONFLICT (id) DO NOTHING"
+        } else {
+            ""
+        }
+    ))
+    .bind(&request.id)
+    .bind(&request.date)
+    .bind(&request.token_id)
+    .bind(&request.message_object.method)
+    .bind(&request.message_object.value)
+    .bind(headers_json)
+    .bind(query_params_json)
+    .bind(&request.message_object.body)
+    .bind(body_object_json)
+    .bind(&request.message)
+    .bind(&request.replayed_from)
+    .bind(request.forward_status.map(i64::from))
+    .bind(request.signature_valid)
+    .bind(&request.message_object.content_type)
+    .bind(
+        request
+            .message_object
+            .compressed_size
+            .map(|size| size as i64),
+    )
+    .bind(&request.message_object.body_encoding)
+    .bind(&request.source_ip)
+    .bind(&request.peer_ip)
+    .bind(&request.source_country)
+    .bind(&request.source_city)
+    .bind(request.source_asn.map(i64::from))
+    .bind(&request.ua_browser)
+    .bind(&request.ua_os)
+    .bind(&request.ua_device_type)
+    .bind(request.ua_client.map(|client| client.as_str()))
+    .bind(
+        request
+            .message_object
+            .body_size_bytes
+            .map(|size| size as i64),
+    )
+    .bind(&request.message_object.body_sha256)
+    .bind(&request.comment)
+    .bind(request.flagged)
+    .bind(redacted_headers_json)
+    .bind(cookies_json)
+    .bind(&request.trace_id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+fn token_from_row(row: &PgRow) -> TokenInfo {
+    TokenInfo {
+        token: row.get("token"),
+        created_at: row.get("created_at"),
+        webhook_url: row.get("webhook_url"),
+        expires_at: row.get("expires_at"),
+        label: row.get("label"),
+        disabled: row.get("disabled"),
+        forward_url: row.get("forward_url"),
+        has_secret: row.get("has_secret"),
+        last_used_at: row.get("last_used_at"),
+        max_requests: row
+            .get::<Option<i64>, _>("max_requests")
+            .map(|max| max as u32),
+        cors_origins: row
+            .get::<Option<String>, _>("cors_origins")
+            .and_then(|origins| serde_json::from_str(&origins).ok())
+            .unwrap_or_default(),
+        deduplicate_window_secs: row
+            .get::<Option<i64>, _>("deduplicate_window_secs")
+            .map(|window| window as u32),
+        response_delay_ms: row
+            .get::<Option<i64>, _>("response_delay_ms")
+            .map(|delay| delay as u32),
+        redact_headers: row
+            .get::<Option<String>, _>("redact_headers")
+            .and_then(|headers| serde_json::from_str(&headers).ok()),
+        allowed_methods: row
+            .get::<Option<String>, _>("allowed_methods")
+            .and_then(|methods| serde_json::from_str(&methods).ok())
+            .unwrap_or_default(),
+        allowed_content_types: row
+            .get::<Option<String>, _>("allowed_content_types")
+            .and_then(|content_types| serde_json::from_str(&content_types).ok()),
+        max_age_seconds: row
+            .get::<Option<i64>, _>("max_age_seconds")
+            .map(|max_age| max_age as u32),
+        nonce_required: row.get("nonce_required"),
+        notification_url: row.get("notification_url"),
+        notification_events: row
+            .get::<Option<String>, _>("notification_events")
+            .and_then(|events| serde_json::from_str(&events).ok()),
+        readonly: row.get("readonly"),
+        has_readonly_key: row.get("has_readonly_key"),
+        has_custom_response: row.get("has_custom_response"),
+        max_payload_bytes: row
+            .get::<Option<i64>, _>("max_payload_bytes")
+            .map(|max| max as u32),
+        transform_rules: row
+            .get::<Option<String>, _>("transform_rules")
+            .and_then(|rules| serde_json::from_str(&rules).ok())
+            .unwrap_or_default(),
+        message_config: row
+            .get::<Option<String>, _>("token_message_config")
+            .and_then(|extractor| serde_json::from_str(&extractor).ok()),
+        metadata: row
+            .get::<Option<String>, _>("metadata")
+            .and_then(|metadata| serde_json::from_str(&metadata).ok())
+            .unwrap_or_default(),
+        request_count: row.get("request_count"),
+    }
+}
+
+fn dead_letter_from_row(row: &PgRow) -> DeadLetter {
+    DeadLetter {
+        id: row.get("id"),
+        token_id: row.get("token_id"),
+        request_id: row.get("request_id"),
+        forward_url: row.get("forward_url"),
+        last_error: row.get("last_error"),
+        failed_at: row.get("failed_at"),
+    }
+}
+
+fn forward_target_from_row(row: &PgRow) -> ForwardTarget {
+    let id: String = row.get("id");
+    let last_result = row
+        .get::<Option<String>, _>("delivered_at")
+        .map(|delivered_at| ForwardResult {
+            forward_id: id.clone(),
+            request_id: row.get("request_id"),
+            status_code: row
+                .get::<Option<i64>, _>("status_code")
+                .map(|status| status as u16),
+            error: row.get("error"),
+            delivered_at,
+        });
+
+    ForwardTarget {
+        id,
+        url: row.get("url"),
+        enabled: row.get("enabled"),
+        headers: serde_json::from_str(row.get("headers_json")).unwrap_or_default(),
+        forward_region: row.get("forward_region"),
+        created_at: row.get("created_at"),
+        last_result,
+        circuit_state: CircuitState::Closed.as_str(),
+    }
+}
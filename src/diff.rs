@@ -0,0 +1,81 @@
+use similar::TextDiff;
+use std::collections::BTreeMap;
+
+use crate::models::{Change, MessageObject, RequestDiff, WebhookRequest};
+
+/// Compare two stored requests, `a` being the earlier one of the diff
+pub fn diff_requests(a: &WebhookRequest, b: &WebhookRequest) -> RequestDiff {
+    let (a_message, b_message) = (&a.message_object, &b.message_object);
+
+    let mut headers_added = BTreeMap::new();
+    let mut headers_changed = BTreeMap::new();
+    for (name, b_values) in &b_message.headers {
+        match a_message.headers.get(name) {
+            None => {
+                headers_added.insert(name.clone(), b_values.clone());
+            }
+            Some(a_values) if a_values != b_values => {
+                headers_changed.insert(
+                    name.clone(),
+                    Change {
+                        from: a_values.clone(),
+                        to: b_values.clone(),
+                    },
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    let headers_removed = a_message
+        .headers
+        .iter()
+        .filter(|(name, _)| !b_message.headers.contains_key(*name))
+        .map(|(name, values)| (name.clone(), values.clone()))
+        .collect();
+
+    let url_diff = (a_message.value != b_message.value).then(|| Change {
+        from: a_message.value.clone(),
+        to: b_message.value.clone(),
+    });
+
+    RequestDiff {
+        method_changed: a_message.method != b_message.method,
+        url_diff,
+        headers_added,
+        headers_removed,
+        headers_changed,
+        body_diff: body_diff(a, b),
+    }
+}
+
+/// Unified diff of the two bodies; empty when they are equal
+fn body_diff(a: &WebhookRequest, b: &WebhookRequest) -> String {
+    let (a_message, b_message) = (&a.message_object, &b.message_object);
+    if a_message.is_binary() || b_message.is_binary() {
+        return if a_message.body == b_message.body {
+            String::new()
+        } else {
+            "Binary bodies differ\n".to_string()
+        };
+    }
+
+    let (a_text, b_text) = (diffable_body(a_message), diffable_body(b_message));
+    TextDiff::from_lines(&a_text, &b_text)
+        .unified_diff()
+        .header(&a.id, &b.id)
+        .to_string()
+}
+
+/// The body as text to diff line by line; JSON bodies are pretty-printed so that a change
+/// shows up on the line of the field instead of the whole (often single-line) document
+fn diffable_body(message: &MessageObject) -> String {
+    let pretty = message
+        .body_object
+        .as_ref()
+        .and_then(|body| serde_json::to_string_pretty(body).ok());
+    let mut text = pretty.or_else(|| message.body.clone()).unwrap_or_default();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
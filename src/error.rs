@@ -1,13 +1,18 @@
 use axum::Json;
-use axum::http::StatusCode;
+use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use serde_json::json;
 use std::borrow::Cow;
 
+use crate::middleware::current_request_id;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
-    #[error("Database error: {0}")]
-    Database(#[from] sqlx::Error),
+    #[error("Database error while {context}")]
+    Database {
+        context: String,
+        source: anyhow::Error,
+    },
 
     #[error("JSON parsing error: {0}")]
     JsonParsing(#[from] serde_json::Error),
@@ -24,53 +29,324 @@ pub enum AppError {
     #[error("Token not found")]
     TokenNotFound,
 
+    #[error("Token already exists")]
+    TokenAlreadyExists,
+
+    #[error("Token has expired")]
+    TokenExpired,
+
+    #[error("Token is disabled")]
+    TokenDisabled,
+
+    #[error("Token is read-only")]
+    TokenReadOnly,
+
     #[error("Invalid token format - tokens must be valid UUIDs")]
     InvalidToken,
 
-    #[error("Request body too large")]
-    PayloadTooLarge,
+    #[error("Request body exceeds the limit of {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+
+    #[error("Method not allowed for this token")]
+    MethodNotAllowed { allowed: Vec<String> },
+
+    #[error("Content type not accepted by this token")]
+    UnsupportedMediaType { allowed: Vec<String> },
+
+    #[error("Nonce has already been used")]
+    NonceAlreadyUsed,
+
+    #[error("Request is {age_secs}s old, older than the allowed {max_age_secs}s")]
+    RequestTooOld { age_secs: i64, max_age_secs: u32 },
 
     #[error("Internal server error")]
-    InternalServerError,
+    InternalServerError { request_id: Option<String> },
 
     #[error("Resource not found")]
     NotFound,
 
+    #[error("Webhook request not found")]
+    RequestNotFound,
+
     #[error("Common browser file not found: {0}")]
     CommonFileNotFound(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("No forward URL configured for this token")]
+    ForwardUrlNotConfigured,
+
+    #[error("Forward target not found")]
+    ForwardTargetNotFound,
+
+    #[error("Dead letter not found")]
+    DeadLetterNotFound,
+
+    #[error("Forwarding failed: {0}")]
+    ForwardFailed(String),
+
+    /// The forward URL did not answer in time; `connect_timeout` or `read_timeout`
+    #[error("{0}")]
+    ForwardTimeout(&'static str),
+
+    #[error("Email notifications are not configured on this instance")]
+    EmailNotConfigured,
+
+    #[error("Sending email failed: {0}")]
+    EmailFailed(String),
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Authentication token has expired")]
+    AuthTokenExpired,
+
+    #[error("Webhook signature verification failed")]
+    SignatureVerificationFailed,
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("Token has reached its limit of {limit} stored requests")]
+    StorageLimitExceeded { limit: u32 },
+
+    #[error("Instance has reached its limit of {limit} tokens")]
+    MaxTokensReached { count: i64, limit: u32 },
+
+    #[error("Rate limit exceeded, retry after {retry_after_secs}s")]
+    RateLimitExceeded { retry_after_secs: u64 },
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message): (StatusCode, Cow<str>) = match &self {
-            AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database error".into()),
+/// Why an HTTP request could not be read into a `MessageObject` or `WebhookRequest`
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error("Request body exceeds the limit of {limit} bytes")]
+    TooLarge { limit: usize },
+
+    #[error("Failed to decode {encoding} body: {reason}")]
+    Encoding { encoding: String, reason: String },
+
+    #[error("Invalid JSON body: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<ParseError> for AppError {
+    fn from(error: ParseError) -> Self {
+        match error {
+            ParseError::TooLarge { limit } => AppError::PayloadTooLarge { limit },
+            ParseError::Encoding { .. } => AppError::InvalidRequest(error.to_string()),
+            ParseError::Json(e) => AppError::JsonParsing(e),
+        }
+    }
+}
+
+impl AppError {
+    /// A failed database call, with what was being done, e.g. `"storing webhook request for token
+    /// abc"`
+    pub fn database(context: impl Into<String>, source: anyhow::Error) -> Self {
+        AppError::Database {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// An internal error tagged with the ID of the request being handled
+    pub fn internal() -> Self {
+        AppError::InternalServerError {
+            request_id: current_request_id(),
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        self.status_and_message().0
+    }
+
+    /// HTTP status and the message that is safe to show to clients
+    fn status_and_message(&self) -> (StatusCode, Cow<'_, str>) {
+        match self {
+            AppError::Database { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Database error".into()),
             AppError::JsonParsing(_) => (StatusCode::BAD_REQUEST, "Invalid JSON".into()),
             AppError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, "IO error".into()),
             AppError::InvalidUuid(_) => (StatusCode::BAD_REQUEST, "Invalid UUID format".into()),
             AppError::EnvVar(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Configuration error".into()),
             AppError::TokenNotFound => (StatusCode::NOT_FOUND, "Token not found".into()),
+            AppError::TokenAlreadyExists => {
+                (StatusCode::CONFLICT, "A token with this ID already exists".into())
+            }
+            AppError::TokenExpired => (StatusCode::GONE, "Token has expired".into()),
+            AppError::TokenDisabled => (
+                StatusCode::FORBIDDEN,
+                "Token is disabled and not accepting webhooks. Re-enable it to resume capturing requests".into(),
+            ),
+            AppError::TokenReadOnly => (
+                StatusCode::FORBIDDEN,
+                "Token is read-only and not accepting webhooks. Its stored requests can still be read".into(),
+            ),
             AppError::InvalidToken => (
                 StatusCode::BAD_REQUEST,
                 "Invalid token format. Tokens must be valid UUIDs (e.g., 550e8400-e29b-41d4-a716-446655440000)".into(),
             ),
-            AppError::PayloadTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large".into()),
-            AppError::InternalServerError => {
+            AppError::PayloadTooLarge { limit } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Request body exceeds the limit of {} bytes", limit).into(),
+            ),
+            AppError::MethodNotAllowed { allowed } => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("This token only accepts {}", allowed.join(", ")).into(),
+            ),
+            AppError::NonceAlreadyUsed => {
+                (StatusCode::CONFLICT, "X-Nonce has already been used".into())
+            }
+            AppError::RequestTooOld { age_secs, max_age_secs } => (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Request timestamp is {}s old; this token only accepts requests up to {}s old",
+                    age_secs, max_age_secs
+                )
+                .into(),
+            ),
+            AppError::UnsupportedMediaType { allowed } => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("This token only accepts Content-Type {}", allowed.join(", ")).into(),
+            ),
+            AppError::InternalServerError { .. } => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".into())
             }
             AppError::NotFound => (StatusCode::NOT_FOUND, "Resource not found".into()),
+            AppError::RequestNotFound => {
+                (StatusCode::NOT_FOUND, "Webhook request not found".into())
+            }
             AppError::CommonFileNotFound(path) => (
                 StatusCode::NOT_FOUND,
                 format!("Common browser file not found: {}", path).into()
             ),
-        };
+            AppError::InvalidRequest(reason) => (StatusCode::BAD_REQUEST, reason.as_str().into()),
+            AppError::ForwardUrlNotConfigured => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "No forward URL configured for this token".into(),
+            ),
+            AppError::ForwardTargetNotFound => {
+                (StatusCode::NOT_FOUND, "Forward target not found".into())
+            }
+            AppError::DeadLetterNotFound => (StatusCode::NOT_FOUND, "Dead letter not found".into()),
+            AppError::ForwardFailed(_) => {
+                (StatusCode::BAD_GATEWAY, "Failed to reach the forward URL".into())
+            }
+            AppError::ForwardTimeout(_) => {
+                (StatusCode::GATEWAY_TIMEOUT, "The forward URL did not respond in time".into())
+            }
+            AppError::EmailNotConfigured => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Email notifications are not configured on this instance".into(),
+            ),
+            AppError::EmailFailed(_) => {
+                (StatusCode::BAD_GATEWAY, "Failed to send the email".into())
+            }
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".into()),
+            // A fixed code so clients know to request a new JWT
+            AppError::AuthTokenExpired => (StatusCode::UNAUTHORIZED, "token_expired".into()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden".into()),
+            AppError::RateLimitExceeded { .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".into())
+            }
+            AppError::StorageLimitExceeded { limit } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Token has reached its limit of {} stored requests. Delete some requests or raise max_requests to accept more",
+                    limit
+                )
+                .into(),
+            ),
+            AppError::MaxTokensReached { limit, .. } => (
+                StatusCode::INSUFFICIENT_STORAGE,
+                format!(
+                    "This instance has reached its limit of {} tokens. Delete unused tokens to create new ones",
+                    limit
+                )
+                .into(),
+            ),
+            AppError::SignatureVerificationFailed => (
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid X-Hub-Signature-256 header".into(),
+            ),
+        }
+    }
+}
 
-        tracing::warn!("Error occurred: {}", self);
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = self.status_and_message();
+
+        let request_id = match &self {
+            AppError::InternalServerError {
+                request_id: Some(id),
+            } => Some(id.clone()),
+            _ => current_request_id(),
+        };
+        // Inner errors (SQL, IO, ...) can reveal internals, so the warning only has the
+        // message shown to the client and the details are left to debug logging
+        match &request_id {
+            Some(id) => tracing::warn!("Error occurred in request {}: {}", id, error_message),
+            None => tracing::warn!("Error occurred: {}", error_message),
+        }
+        tracing::debug!(
+            error = %self,
+            source = ?std::error::Error::source(&self),
+            "Error details"
+        );
+        // What the failed database call was doing is needed to debug it, so it is logged with
+        // the full error chain in a span of its own
+        if let AppError::Database { context, source } = &self {
+            tracing::error_span!(
+                "database_error",
+                request_id = request_id.as_deref(),
+                context = %context
+            )
+            .in_scope(|| {
+                tracing::error!(
+                    error = format!("{:#}", source),
+                    "Database error while {}",
+                    context
+                )
+            });
+        }
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_message,
-            "status": status.as_u16()
-        }));
+            "status": status.as_u16(),
+            "request_id": request_id,
+        });
+        if let AppError::MaxTokensReached { count, limit } = &self {
+            body["token_count"] = json!(count);
+            body["max_tokens"] = json!(limit);
+        }
 
-        (status, body).into_response()
+        let mut response = (status, Json(body)).into_response();
+        match self {
+            AppError::Unauthorized => {
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            AppError::AuthTokenExpired => {
+                response.headers_mut().insert(
+                    header::WWW_AUTHENTICATE,
+                    HeaderValue::from_static(r#"Bearer error="invalid_token""#),
+                );
+            }
+            AppError::MethodNotAllowed { allowed } => {
+                if let Ok(value) = HeaderValue::from_str(&allowed.join(", ")) {
+                    response.headers_mut().insert(header::ALLOW, value);
+                }
+            }
+            AppError::RateLimitExceeded { retry_after_secs } => {
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+            }
+            _ => {}
+        }
+        response
     }
 }
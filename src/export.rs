@@ -0,0 +1,323 @@
+use serde::Serialize;
+use url::form_urlencoded;
+
+use crate::models::WebhookRequest;
+
+/// Opening of a HAR 1.2 document, up to the start of the `entries` array
+pub const HAR_HEADER: &str = concat!(
+    r#"{"log":{"version":"1.2","creator":{"name":""#,
+    env!("CARGO_PKG_NAME"),
+    r#"","version":""#,
+    env!("CARGO_PKG_VERSION"),
+    r#""},"entries":["#
+);
+
+/// Closes the `entries` array and the document opened by [`HAR_HEADER`]
+pub const HAR_FOOTER: &str = "]}}";
+
+/// Headers describing the original connection, which the HTTP client sets itself
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length"];
+
+/// Columns of the CSV export, in order; `body` is appended on request
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "date",
+    "method",
+    "url",
+    "content_type",
+    "body_size",
+    "body_encoding",
+    "status",
+];
+
+/// The CSV header row, with the `body` column if `include_body` is set
+pub fn csv_header(include_body: bool) -> csv::Result<Vec<u8>> {
+    let mut header = CSV_COLUMNS.to_vec();
+    if include_body {
+        header.push("body");
+    }
+    write_csv_record(&header)
+}
+
+/// One request as a CSV row matching [`csv_header`]
+pub fn csv_record(request: &WebhookRequest, include_body: bool) -> csv::Result<Vec<u8>> {
+    let message = &request.message_object;
+    let content_type = message
+        .headers
+        .get("content-type")
+        .and_then(|values| values.first())
+        .map(String::as_str)
+        .unwrap_or("");
+    let body = message.body.as_deref().unwrap_or("");
+    let status = request
+        .forward_status
+        .map(|status| status.to_string())
+        .unwrap_or_default();
+
+    let mut record = vec![
+        request.id.as_str(),
+        request.date.as_str(),
+        message.method.as_str(),
+        message.value.as_str(),
+        content_type,
+    ];
+    let body_size = message
+        .body_bytes()
+        .map_or(0, |bytes| bytes.len())
+        .to_string();
+    record.push(&body_size);
+    record.push(message.body_encoding.as_deref().unwrap_or(""));
+    record.push(&status);
+    if include_body {
+        record.push(body);
+    }
+    write_csv_record(&record)
+}
+
+fn write_csv_record(record: &[&str]) -> csv::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(record)?;
+    writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))
+}
+
+/// One request/response pair of a HAR 1.2 log; the response is the acknowledgement sent back
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    pub started_date_time: String,
+    pub time: u32,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub cache: HarCache,
+    pub timings: HarTimings,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: &'static str,
+    pub cookies: Vec<HarNameValue>,
+    pub headers: Vec<HarNameValue>,
+    pub query_string: Vec<HarNameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    pub status_text: &'static str,
+    pub http_version: &'static str,
+    pub cookies: Vec<HarNameValue>,
+    pub headers: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: &'static str,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    pub mime_type: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HarCache {}
+
+#[derive(Debug, Serialize)]
+pub struct HarTimings {
+    pub send: i64,
+    pub wait: i64,
+    pub receive: i64,
+}
+
+impl HarEntry {
+    /// Describe a stored request that was sent to `url`; sizes and timings are not recorded
+    pub fn new(request: &WebhookRequest, url: &str) -> Self {
+        let message = &request.message_object;
+
+        let headers = message
+            .headers
+            .iter()
+            .flat_map(|(name, values)| {
+                values.iter().map(|value| HarNameValue {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+            })
+            .collect();
+        let query_string = message
+            .value
+            .split_once('?')
+            .map(|(_, query)| {
+                form_urlencoded::parse(query.as_bytes())
+                    .map(|(name, value)| HarNameValue {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let body = message.body.as_deref().filter(|body| !body.is_empty());
+        let post_data = body.map(|body| HarPostData {
+            mime_type: message
+                .headers
+                .get("content-type")
+                .and_then(|values| values.first())
+                .cloned()
+                .unwrap_or_default(),
+            text: body.to_string(),
+            comment: message.is_binary().then_some("Binary body, base64-encoded"),
+        });
+
+        HarEntry {
+            started_date_time: request.date.clone(),
+            time: 0,
+            request: HarRequest {
+                method: message.method.clone(),
+                url: url.to_string(),
+                http_version: "HTTP/1.1",
+                cookies: message
+                    .cookies
+                    .iter()
+                    .map(|(name, value)| HarNameValue {
+                        name: name.clone(),
+                        value: value.clone(),
+                    })
+                    .collect(),
+                headers,
+                query_string,
+                body_size: body.map_or(0, |body| body.len() as i64),
+                post_data,
+                headers_size: -1,
+            },
+            response: HarResponse {
+                status: 200,
+                status_text: "OK",
+                http_version: "HTTP/1.1",
+                cookies: Vec::new(),
+                headers: Vec::new(),
+                content: HarContent {
+                    size: 0,
+                    mime_type: "application/json",
+                },
+                redirect_url: "",
+                headers_size: -1,
+                body_size: -1,
+            },
+            cache: HarCache {},
+            timings: HarTimings {
+                send: 0,
+                wait: 0,
+                receive: 0,
+            },
+        }
+    }
+}
+
+/// A `curl` command line that reproduces the request against `url`; binary bodies are piped in
+pub fn curl_command(request: &WebhookRequest, url: &str) -> String {
+    let message = &request.message_object;
+    let mut args = vec![format!(
+        "{}curl -X {}",
+        binary_body_pipe(request),
+        message.method
+    )];
+
+    for (name, value) in replayable_headers(request) {
+        args.push(format!(
+            "-H {}",
+            shell_quote(&format!("{}: {}", name, value))
+        ));
+    }
+    if message.is_binary() {
+        args.push("--data-binary @-".to_string());
+    } else if let Some(body) = message.body.as_deref().filter(|body| !body.is_empty()) {
+        args.push(format!("--data-raw {}", shell_quote(body)));
+    }
+    args.push(shell_quote(url));
+
+    args.join(" \\\n  ")
+}
+
+/// An HTTPie command line that reproduces the request against `url`; binary bodies are piped in
+pub fn httpie_command(request: &WebhookRequest, url: &str) -> String {
+    let message = &request.message_object;
+    let mut args = vec![format!(
+        "{}http {} {}",
+        binary_body_pipe(request),
+        message.method,
+        shell_quote(url)
+    )];
+
+    for (name, value) in replayable_headers(request) {
+        args.push(shell_quote(&format!("{}:{}", name, value)));
+    }
+    if let Some(body) = message
+        .body
+        .as_deref()
+        .filter(|body| !body.is_empty() && !message.is_binary())
+    {
+        args.push(format!("--raw {}", shell_quote(body)));
+    }
+
+    args.join(" \\\n  ")
+}
+
+/// Shell prefix that decodes a binary body to stdin, or nothing for text bodies
+fn binary_body_pipe(request: &WebhookRequest) -> String {
+    let message = &request.message_object;
+    match message.body.as_deref() {
+        Some(body) if message.is_binary() => {
+            format!("printf '%s' {} | base64 -d | ", shell_quote(body))
+        }
+        _ => String::new(),
+    }
+}
+
+/// Header name/value pairs in a stable order, without connection-specific headers
+fn replayable_headers(request: &WebhookRequest) -> Vec<(&str, &str)> {
+    let mut headers: Vec<(&str, &str)> = request
+        .message_object
+        .headers
+        .iter()
+        .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+        .flat_map(|(name, values)| {
+            values
+                .iter()
+                .map(move |value| (name.as_str(), value.as_str()))
+        })
+        .collect();
+    headers.sort_by_key(|(name, _)| *name);
+    headers
+}
+
+/// Wrap a value in single quotes for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
@@ -0,0 +1,101 @@
+use maxminddb::{MaxMindDBError, Reader, geoip2};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+/// Where a webhook came from, as far as the database knows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 code, e.g. `DE`
+    pub country_code: Option<String>,
+    /// English city name
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+}
+
+/// Autonomous system number, found in the traits of GeoIP2 Enterprise and Insights records
+/// and at the top level of GeoLite2-ASN records; GeoLite2-City has none
+#[derive(Deserialize)]
+struct AsnRecord {
+    autonomous_system_number: Option<u32>,
+    traits: Option<AsnTraits>,
+}
+
+#[derive(Deserialize)]
+struct AsnTraits {
+    autonomous_system_number: Option<u32>,
+}
+
+/// Looks up source IPs in a MaxMind City database (`GEO_DB_PATH`); without one every
+/// lookup comes back empty
+#[derive(Clone, Default)]
+pub struct GeoLookup {
+    reader: Option<Arc<Reader<Vec<u8>>>>,
+}
+
+impl GeoLookup {
+    /// Open the configured database. A missing or unreadable file only disables lookups, so
+    /// webhooks keep being accepted without location data
+    pub fn new(config: &Config) -> Self {
+        let Some(ref path) = config.geo_db_path else {
+            return Self::default();
+        };
+        match Reader::open_readfile(path) {
+            Ok(reader) => {
+                info!(
+                    "Loaded geolocation database {} ({})",
+                    path, reader.metadata.database_type
+                );
+                Self {
+                    reader: Some(Arc::new(reader)),
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to open GEO_DB_PATH {}, source IPs will not be geolocated: {}",
+                    path, e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        let Some(ref reader) = self.reader else {
+            return GeoInfo::default();
+        };
+
+        let city = match reader.lookup::<geoip2::City>(ip) {
+            Ok(city) => Some(city),
+            // Private and reserved addresses are not in the database
+            Err(MaxMindDBError::AddressNotFoundError(_)) => None,
+            Err(e) => {
+                warn!(
+                    "Failed to look up {} in the geolocation database: {}",
+                    ip, e
+                );
+                None
+            }
+        };
+        let asn = reader.lookup::<AsnRecord>(ip).ok().and_then(|record| {
+            record
+                .autonomous_system_number
+                .or(record.traits?.autonomous_system_number)
+        });
+
+        GeoInfo {
+            country_code: city
+                .as_ref()
+                .and_then(|city| city.country.as_ref()?.iso_code)
+                .map(str::to_string),
+            city: city
+                .as_ref()
+                .and_then(|city| city.city.as_ref()?.names.as_ref()?.get("en").copied())
+                .map(str::to_string),
+            asn,
+        }
+    }
+}
@@ -1,27 +1,66 @@
 use axum::{
     Router,
-    extract::{Path, State},
-    http::{HeaderMap, Method, Uri, header},
-    response::{Html, Json, Response},
-    routing::{any, delete, get, post},
+    body::{Body, Bytes},
+    extract::{
+        ConnectInfo, DefaultBodyLimit, MatchedPath, Path, Query, Request, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, header, request},
+    response::{
+        Html, IntoResponse, Json, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{any, delete, get, patch, post, put},
 };
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use std::collections::HashMap;
-use tower::ServiceBuilder;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
 use tower_http::cors::CorsLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{info, warn};
-use url::form_urlencoded;
+use tracing::{Span, info, warn};
 use uuid::Uuid;
 
+use crate::auth;
 use crate::config::Config;
+use crate::database::{DatabaseBackend, PingTimeout};
+use crate::diff;
 use crate::error::AppError;
-use crate::models::{TokenInfo, WebhookRequest};
-use crate::services::{TokenService, WebhookService};
+use crate::export;
+use crate::metrics::Metrics;
+use crate::middleware::{AuditActor, RequestId, RequireAdmin, SetRequestId, bearer_matches};
+use crate::models::{
+    AnalyzeReport, AuditEntry, AuditQuery, AuthToken, AuthTokenRequest, CountryCount,
+    CreateForwardTargetRequest, CreateNoncesRequest, CreateTokenBatchRequest, CreateTokenRequest,
+    CsvExportQuery, CustomResponse, DataExport, DeadLetter, DuplicateGroup, DuplicatesQuery,
+    EmailNotification, ExportQuery, ForwardAttempt, ForwardRegion, ForwardTarget, GlobalStats,
+    HEALTH_CHECK_INTEGRITY, HeaderCount, Heatmap, HeatmapQuery, HourlyBucket, ImportQuery,
+    ImportSummary, IntegrityReport, IpRule, MessageObject, NonceBatch, RESPONSE_SCHEMA_VERSION,
+    ReadonlyKey, ReplayResult, RequestDiff, RequestPageQuery, SetCorsOriginsRequest,
+    SetCustomResponseRequest, SetEmailNotificationRequest, SetForwardUrlRequest,
+    SetSlackNotificationRequest, SetWebhookSecretRequest, SlackNotification, TokenInfo,
+    TokenListQuery, TokenStats, TopHeadersQuery, UpdateRequestCommentRequest, UpdateTokenRequest,
+    UserAgentCount, VacuumReport, VolumeQuery, WebhookRequest, WebhookRequestFilter, WebhookSecret,
+};
+use crate::services::{TokenService, WebhookService, original_url};
 
 #[derive(Clone)]
 pub struct AppState {
     pub webhook_service: WebhookService,
     pub token_service: TokenService,
+    pub db: Arc<dyn DatabaseBackend>,
+    pub metrics: Arc<Metrics>,
+    pub config: Arc<Config>,
+    pub started_at: Instant,
+    /// Last result of `GET /api/stats` and when it was computed
+    pub stats_cache: Arc<RwLock<Option<(Instant, GlobalStats)>>>,
 }
 
 /// Extension trait for Router to add common file routes
@@ -47,30 +86,216 @@ pub fn create_router(app_state: AppState, config: &Config) -> Router {
         .add_common_file("robots.txt")
         .add_common_file("sitemap.xml")
         .add_common_file("manifest.json")
-        // API routes
-        .route("/api/tokens", post(create_token))
-        .route("/api/tokens", get(list_tokens))
-        .route("/api/tokens/{token}", delete(delete_token))
+        // Prometheus metrics
+        .route("/metrics", get(metrics_handler))
+        // API routes, protected by the admin API key when one is configured
+        .merge(api_router(config, app_state.db.clone()))
         // CLI-compatible logs endpoint
         .route("/{token}/log/{count}", get(get_webhook_logs))
-        // Webhook endpoint - accepts any HTTP method at /{token}
+        // Live stream of incoming webhooks (Server-Sent Events)
+        .route("/{token}/stream", get(stream_webhooks))
+        // Live stream of incoming webhooks (WebSocket)
+        .route("/{token}/ws", get(websocket_handler))
+        .layer(create_cors_layer(config))
+        // Webhook endpoint - accepts any HTTP method at /{token}; CORS follows the token's origins
         .route("/{token}", any(webhook_handler))
         // Webhook endpoint with additional path - accepts any HTTP method at /{token}/*path
         .route("/{token}/{*path}", any(webhook_handler))
         // Apply middleware
+        .layer(SetResponseHeaderLayer::overriding(
+            HeaderName::from_static("x-schema-version"),
+            HeaderValue::from_static(RESPONSE_SCHEMA_VERSION),
+        ))
         .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(create_cors_layer(config)),
+            TraceLayer::new_for_http()
+                .make_span_with(http_request_span)
+                .on_response(record_http_response),
         )
+        .layer(SetRequestId)
+        // Probes are added after the middleware so they stay out of the request trace logs
+        .route("/health", get(health_check))
+        .route("/ready", get(readiness_check))
         .with_state(app_state)
 }
 
+fn api_router(config: &Config, db: Arc<dyn DatabaseBackend>) -> Router<AppState> {
+    Router::new()
+        .route("/api/stats", get(global_stats))
+        .route("/api/tokens", post(create_token))
+        .route("/api/tokens/batch", post(create_tokens_batch))
+        .route("/api/tokens", get(list_tokens))
+        .route("/api/audit", get(list_audit))
+        .route("/api/export", get(export_data))
+        .route(
+            "/api/import",
+            post(import_data).layer(DefaultBodyLimit::max(MAX_IMPORT_SIZE)),
+        )
+        .route("/api/admin/vacuum", post(vacuum_database))
+        .route("/api/admin/analyze", post(analyze_database))
+        .route("/api/admin/integrity-check", post(check_integrity))
+        .route(
+            "/api/tokens/{token}",
+            get(get_token).patch(update_token).delete(delete_token),
+        )
+        .route("/api/tokens/{token}/disable", post(disable_token))
+        .route("/api/tokens/{token}/enable", post(enable_token))
+        .route("/api/tokens/{token}/clone", post(clone_token))
+        .route(
+            "/api/tokens/{token}/response",
+            post(set_custom_response).delete(clear_custom_response),
+        )
+        .route(
+            "/api/tokens/{token}/notifications/slack",
+            get(get_slack_notification)
+                .post(set_slack_notification)
+                .delete(clear_slack_notification),
+        )
+        .route(
+            "/api/tokens/{token}/notifications/email",
+            get(get_email_notification)
+                .post(set_email_notification)
+                .delete(clear_email_notification),
+        )
+        .route(
+            "/api/tokens/{token}/notifications/email/test",
+            post(send_test_email),
+        )
+        .route("/api/tokens/{token}/secret", post(set_webhook_secret))
+        .route(
+            "/api/tokens/{token}/secret/rotate",
+            post(rotate_webhook_secret),
+        )
+        .route(
+            "/api/tokens/{token}/readonly-key",
+            post(create_readonly_key).delete(revoke_readonly_key),
+        )
+        .route(
+            "/api/tokens/{token}/metadata",
+            get(get_token_metadata).put(set_token_metadata),
+        )
+        .route(
+            "/api/tokens/{token}/metadata/{key}",
+            delete(delete_token_metadata),
+        )
+        .route(
+            "/api/tokens/{token}/ip-rules",
+            post(set_ip_rules).delete(clear_ip_rules),
+        )
+        .route(
+            "/api/tokens/{token}/forward",
+            put(set_forward_url).delete(clear_forward_url),
+        )
+        .route(
+            "/api/tokens/{token}/forwards",
+            get(list_forward_targets).post(add_forward_target),
+        )
+        .route(
+            "/api/tokens/{token}/forwards/regions",
+            get(list_forward_regions),
+        )
+        .route(
+            "/api/tokens/{token}/forwards/{forward_id}",
+            delete(delete_forward_target),
+        )
+        .route("/api/tokens/{token}/cors", post(set_cors_origins))
+        .route("/api/tokens/{token}/nonces", post(create_nonces))
+        .route("/api/tokens/{token}/stats", get(token_stats))
+        .route("/api/tokens/{token}/stats/volume", get(hourly_volume))
+        .route("/api/tokens/{token}/stats/headers", get(top_headers))
+        .route("/api/tokens/{token}/stats/heatmap", get(heatmap))
+        .route(
+            "/api/tokens/{token}/stats/duplicates",
+            get(duplicate_requests),
+        )
+        .route("/api/tokens/{token}/stats/geo", get(geo_stats))
+        .route(
+            "/api/tokens/{token}/stats/user-agents",
+            get(user_agent_stats),
+        )
+        .route("/api/tokens/{token}/schema", get(request_schema))
+        .route("/api/tokens/{token}/requests", get(list_requests))
+        .route("/api/tokens/{token}/export.csv", get(export_csv))
+        .route("/api/tokens/{token}/export.ndjson", get(export_ndjson))
+        .route("/api/tokens/{token}/export.har", get(export_har))
+        .route(
+            "/api/tokens/{token}/requests/{request_id}",
+            patch(update_request_comment).delete(delete_request),
+        )
+        .route(
+            "/api/tokens/{token}/requests/{request_id}/flag",
+            post(flag_request).delete(unflag_request),
+        )
+        .route("/api/tokens/{token}/flags", delete(clear_request_flags))
+        .route(
+            "/api/tokens/{token}/requests/{request_id}/replay",
+            post(replay_request),
+        )
+        .route(
+            "/api/tokens/{token}/requests/{request_id}/curl",
+            get(request_as_curl),
+        )
+        .route(
+            "/api/tokens/{token}/requests/{request_id}/httpie",
+            get(request_as_httpie),
+        )
+        .route(
+            "/api/tokens/{token}/requests/{request_id}/diff/{other_id}",
+            get(diff_requests),
+        )
+        .route(
+            "/api/tokens/{token}/requests/{request_id}/forward-attempts",
+            get(list_forward_attempts),
+        )
+        .route("/api/tokens/{token}/dead-letters", get(list_dead_letters))
+        .route(
+            "/api/tokens/{token}/dead-letters/{id}",
+            delete(discard_dead_letter),
+        )
+        .route(
+            "/api/tokens/{token}/dead-letters/{id}/requeue",
+            post(requeue_dead_letter),
+        )
+        .route_layer(RequireAdmin::new(config, db))
+        .route_layer(AuditActor::new(config))
+        // Exchanges the admin key for a JWT, so it cannot require one itself
+        .route("/api/auth/token", post(issue_auth_token))
+}
+
+/// Request span named and attributed after the OpenTelemetry HTTP server conventions
+fn http_request_span(request: &Request) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(request.uri().path(), MatchedPath::as_str);
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|RequestId(id)| id.as_str());
+    tracing::info_span!(
+        "HTTP request",
+        otel.name = %format_args!("{} {}", request.method(), route),
+        otel.kind = "server",
+        otel.status_code = tracing::field::Empty,
+        http.request.method = %request.method(),
+        http.route = route,
+        url.path = request.uri().path(),
+        http.request.id = request_id,
+        http.response.status_code = tracing::field::Empty,
+    )
+}
+
+fn record_http_response(response: &Response, _latency: Duration, span: &Span) {
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
 fn create_cors_layer(config: &Config) -> CorsLayer {
     if config.cors_permissive {
         CorsLayer::permissive()
     } else {
-        use axum::http::HeaderValue;
         let origins: Vec<HeaderValue> = config
             .cors_allowed_origins
             .iter()
@@ -84,19 +309,30 @@ fn create_cors_layer(config: &Config) -> CorsLayer {
             .collect();
         CorsLayer::new()
             .allow_origin(origins)
-            .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
-            .allow_headers([header::CONTENT_TYPE])
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+                Method::OPTIONS,
+            ])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
     }
 }
 
+/// Largest accepted `POST /api/import` body
+const MAX_IMPORT_SIZE: usize = 256 * 1_048_576;
+
 async fn webhook_handler(
     State(state): State<AppState>,
     Path(params): Path<HashMap<String, String>>,
-    method: Method,
-    uri: Uri,
-    headers: HeaderMap,
-    body: axum::body::Bytes,
-) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+) -> std::result::Result<Response, AppError> {
+    let started = Instant::now();
+    let (parts, body) = request.into_parts();
+
     // Extract token from path parameters
     let token = params.get("token").ok_or(AppError::InvalidToken)?;
 
@@ -106,45 +342,125 @@ async fn webhook_handler(
         AppError::InvalidToken
     })?;
 
-    // Parse query parameters
-    let query_params: Vec<String> = uri
-        .query()
-        .map(|q| {
-            form_urlencoded::parse(q.as_bytes())
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect()
-        })
-        .unwrap_or_default();
-
-    // Convert headers to the expected format
-    let header_map = convert_headers(&headers);
+    // Preflights are answered here rather than captured as webhooks
+    let preflight = parts.method == Method::OPTIONS
+        && parts.headers.contains_key(header::ORIGIN)
+        && parts
+            .headers
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
 
-    // Parse body with a basic size cap (1 MiB)
-    if body.len() > 1_048_576 {
-        return Err(AppError::PayloadTooLarge);
+    // Throttle noisy senders before any database work
+    if !preflight {
+        state
+            .webhook_service
+            .check_rate_limit(token, parts.method.as_str())?;
     }
-    let body_str = String::from_utf8(body.to_vec()).unwrap_or_default();
-    let body_object = if body_str.is_empty() {
-        None
-    } else {
-        serde_json::from_str(&body_str).ok()
+
+    // One lookup serves every per-token setting used below
+    let token_info = state.webhook_service.webhook_token(token).await?;
+
+    // Browsers only see the response when the token allows their origin
+    let allow_origin = match (
+        &token_info,
+        parts
+            .headers
+            .get(header::ORIGIN)
+            .and_then(|v| v.to_str().ok()),
+    ) {
+        (Some(token_info), Some(origin)) => state
+            .webhook_service
+            .cors_allow_origin(token_info, origin)
+            .and_then(|origin| HeaderValue::from_str(&origin).ok()),
+        _ => None,
     };
 
-    // Process webhook through service layer
+    if preflight {
+        return Ok(cors_preflight_response(&parts.headers, allow_origin));
+    }
+
+    let mut response = receive_webhook(&state, token, token_info.as_ref(), peer, parts, body)
+        .await
+        .into_response();
+    state.metrics.record_webhook_duration(started.elapsed());
+    if let Some(origin) = allow_origin {
+        let response_headers = response.headers_mut();
+        response_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        response_headers.append(header::VARY, HeaderValue::from_static("origin"));
+    }
+    Ok(response)
+}
+
+/// Allow whatever method and headers the browser asks for, but only for the token's origins
+fn cors_preflight_response(headers: &HeaderMap, allow_origin: Option<HeaderValue>) -> Response {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::VARY, HeaderValue::from_static("origin"));
+
+    if let Some(origin) = allow_origin {
+        response_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if let Some(method) = headers.get(header::ACCESS_CONTROL_REQUEST_METHOD) {
+            response_headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, method.clone());
+        }
+        if let Some(request_headers) = headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            response_headers.insert(
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                request_headers.clone(),
+            );
+        }
+        response_headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from_static("3600"),
+        );
+    }
+    response
+}
+
+/// Read a request body, giving up as soon as it grows past `limit` bytes
+async fn read_body_with_limit(body: Body, limit: usize) -> std::result::Result<Bytes, AppError> {
+    match Limited::new(body, limit).collect().await {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(e) if e.is::<LengthLimitError>() => Err(AppError::PayloadTooLarge { limit }),
+        Err(e) => Err(AppError::InvalidRequest(format!(
+            "Failed to read request body: {}",
+            e
+        ))),
+    }
+}
+
+async fn receive_webhook(
+    state: &AppState,
+    token: &str,
+    token_info: Option<&TokenInfo>,
+    peer: SocketAddr,
+    parts: request::Parts,
+    body: Body,
+) -> std::result::Result<Response, AppError> {
+    let request::Parts {
+        method,
+        uri,
+        headers,
+        ..
+    } = parts;
+    // The token's own limit if it has one, else the server's; checked as the body is read
+    // and again after decompression
+    let max_payload_bytes = state.webhook_service.max_payload_bytes(token_info);
+    let body = read_body_with_limit(body, max_payload_bytes).await?;
+
+    let client_ip = client_ip(&headers, peer, state.config.trust_proxy);
+    let message =
+        MessageObject::from_parts_with_limit(&method, &uri, &headers, &body, max_payload_bytes)
+            .await?;
+
+    // Process webhook through service layer; signatures cover the body as it was sent
     let request_id = state
         .webhook_service
         .process_webhook(
             token,
-            method.as_ref(),
-            &uri.to_string(),
-            header_map,
-            query_params,
-            if body_str.is_empty() {
-                None
-            } else {
-                Some(body_str)
-            },
-            body_object,
+            token_info,
+            message,
+            &body,
+            client_ip,
+            peer.ip().to_canonical(),
         )
         .await?;
 
@@ -153,30 +469,559 @@ async fn webhook_handler(
         method, token, request_id
     );
 
-    // Return a simple response
-    Ok(Json(serde_json::json!({
-        "status": "received",
-        "id": request_id,
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    })))
+    // Simulate a slow receiver; the request is already stored at this point
+    let delay = state.webhook_service.response_delay(token_info);
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+
+    // Mock server mode: reply with the token's configured response verbatim
+    if let Some(custom) = state.webhook_service.custom_response(token_info).await? {
+        return build_custom_response(custom);
+    }
+
+    // Return a simple response in the format the sender accepts
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let (content_type, body) = format_response(accept, &request_id, delay);
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+/// Render the acknowledgement of a received webhook as plain text, XML or JSON (the default),
+/// whichever the `Accept` header prefers
+fn format_response(accept: &str, id: &str, delay: Duration) -> (HeaderValue, axum::body::Bytes) {
+    let mut accepted: Vec<(&str, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().filter(|media_type| !media_type.is_empty())?;
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            Some((media_type, quality))
+        })
+        .filter(|&(_, quality)| quality > 0.0)
+        .collect();
+    // Stable sort keeps the sender's order among equally preferred types
+    accepted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let preferred = accepted.iter().find_map(|&(media_type, _)| {
+        match media_type.to_ascii_lowercase().as_str() {
+            "text/plain" => Some("text/plain"),
+            "application/xml" | "text/xml" => Some("application/xml"),
+            "application/json" | "*/*" | "application/*" => Some("application/json"),
+            _ => None,
+        }
+    });
+
+    match preferred {
+        Some("text/plain") => (
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+            axum::body::Bytes::from(format!("received {}", id)),
+        ),
+        Some("application/xml") => (
+            HeaderValue::from_static("application/xml"),
+            axum::body::Bytes::from(format!(
+                "<response><status>received</status><id>{}</id></response>",
+                id
+            )),
+        ),
+        _ => (
+            HeaderValue::from_static("application/json"),
+            axum::body::Bytes::from(
+                serde_json::json!({
+                    "status": "received",
+                    "id": id,
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "response_delay_ms": delay.as_millis()
+                })
+                .to_string(),
+            ),
+        ),
+    }
+}
+
+/// Headers naming the original client, in order of preference
+const CLIENT_IP_HEADERS: &[&str] = &["x-forwarded-for", "x-real-ip", "cf-connecting-ip"];
+
+/// The sender's address, taken from forwarding headers only when the proxy is trusted
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_proxy: bool) -> IpAddr {
+    let forwarded = trust_proxy
+        .then(|| {
+            CLIENT_IP_HEADERS.iter().find_map(|name| {
+                headers
+                    .get(*name)?
+                    .to_str()
+                    .ok()?
+                    .split(',')
+                    .next()?
+                    .trim()
+                    .parse::<IpAddr>()
+                    .ok()
+            })
+        })
+        .flatten();
+
+    forwarded.unwrap_or(peer.ip()).to_canonical()
+}
+
+fn build_custom_response(custom: CustomResponse) -> std::result::Result<Response, AppError> {
+    let mut builder = Response::builder().status(custom.status_code);
+    for (name, value) in &custom.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder
+        .body(axum::body::Body::from(custom.body))
+        .map_err(|_| AppError::internal())
 }
 
 async fn create_token(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    // The request body is optional; an empty body means default settings
+    let request: CreateTokenRequest = if body.is_empty() {
+        CreateTokenRequest::default()
+    } else {
+        serde_json::from_slice(&body)?
+    };
+
+    let client_ip = client_ip(&headers, peer, state.config.trust_proxy);
+    let token_info = match request.id {
+        Some(id) => {
+            let id = Uuid::parse_str(&id)
+                .ok()
+                .filter(|id| id.get_version_num() == 4)
+                .ok_or_else(|| AppError::InvalidRequest("id must be a UUID v4".into()))?;
+            state
+                .token_service
+                .create_token_with_id(id, client_ip, &headers, request.label, request.ttl_seconds)
+                .await?
+        }
+        None => {
+            state
+                .token_service
+                .create_token(client_ip, &headers, request.label, request.ttl_seconds)
+                .await?
+        }
+    };
+    Ok(Json(token_info))
+}
+
+async fn create_nonces(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    body: axum::body::Bytes,
+) -> std::result::Result<Json<NonceBatch>, AppError> {
+    // The request body is optional; an empty body issues the default batch
+    let request: CreateNoncesRequest = if body.is_empty() {
+        CreateNoncesRequest::default()
+    } else {
+        serde_json::from_slice(&body)?
+    };
+
+    let batch = state
+        .token_service
+        .create_nonces(&token, request.count, request.ttl_seconds)
+        .await?;
+    Ok(Json(batch))
+}
+
+async fn clone_token(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let client_ip = client_ip(&headers, peer, state.config.trust_proxy);
+    let token_info = state
+        .token_service
+        .clone_token(&token, client_ip, &headers)
+        .await?;
+    Ok(Json(token_info))
+}
+
+async fn create_tokens_batch(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<CreateTokenBatchRequest>,
+) -> std::result::Result<Json<Vec<TokenInfo>>, AppError> {
+    let client_ip = client_ip(&headers, peer, state.config.trust_proxy);
+    let tokens = state
+        .token_service
+        .create_tokens_batch(
+            client_ip,
+            &headers,
+            request.count,
+            request.label_prefix.as_deref(),
+            request.ttl_seconds,
+        )
+        .await?;
+    Ok(Json(tokens))
+}
+
+async fn list_audit(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> std::result::Result<Json<Vec<AuditEntry>>, AppError> {
+    let entries = state.token_service.list_audit(&query).await?;
+    Ok(Json(entries))
+}
+
+async fn export_data(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> std::result::Result<Json<DataExport>, AppError> {
+    let export = state.token_service.export_page(query.page).await?;
+    Ok(Json(export))
+}
+
+async fn import_data(
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
     headers: HeaderMap,
+    Json(data): Json<DataExport>,
+) -> std::result::Result<Json<ImportSummary>, AppError> {
+    let summary = state
+        .token_service
+        .import_data(&headers, data, query.dry_run)
+        .await?;
+    Ok(Json(summary))
+}
+
+async fn vacuum_database(
+    State(state): State<AppState>,
+) -> std::result::Result<Json<VacuumReport>, AppError> {
+    let report = state.token_service.vacuum_database().await?;
+    Ok(Json(report))
+}
+
+async fn analyze_database(
+    State(state): State<AppState>,
+) -> std::result::Result<Json<AnalyzeReport>, AppError> {
+    let report = state.token_service.analyze_database().await?;
+    Ok(Json(report))
+}
+
+async fn check_integrity(
+    State(state): State<AppState>,
+) -> std::result::Result<Json<IntegrityReport>, AppError> {
+    let report = state.token_service.check_integrity().await?;
+    Ok(Json(report))
+}
+
+async fn get_token(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
 ) -> std::result::Result<Json<TokenInfo>, AppError> {
-    let header_map = convert_headers(&headers);
-    let token_info = state.token_service.create_token(&header_map).await?;
+    let token_info = state.token_service.get_token(&token).await?;
     Ok(Json(token_info))
 }
 
 async fn list_tokens(
     State(state): State<AppState>,
+    Query(mut query): Query<TokenListQuery>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> std::result::Result<Json<Vec<TokenInfo>>, AppError> {
-    let tokens = state.token_service.list_tokens().await?;
+    query.metadata = params
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.strip_prefix("metadata.")?.to_string(), value)))
+        .collect();
+    let tokens = state.token_service.list_tokens(&query).await?;
     Ok(Json(tokens))
 }
 
+async fn issue_auth_token(
+    State(state): State<AppState>,
+    Json(request): Json<AuthTokenRequest>,
+) -> std::result::Result<Json<AuthToken>, AppError> {
+    let Some(ref jwt_secret) = state.config.jwt_secret else {
+        return Err(AppError::InvalidRequest(
+            "JWT authentication is not enabled; set JWT_SECRET".into(),
+        ));
+    };
+    let Some(ref admin_key) = state.config.admin_api_key else {
+        return Err(AppError::InvalidRequest(
+            "No ADMIN_API_KEY is configured, the API does not require authentication".into(),
+        ));
+    };
+
+    if !bool::from(request.admin_key.as_bytes().ct_eq(admin_key.as_bytes())) {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(Json(auth::issue_admin_token(jwt_secret)?))
+}
+
+async fn update_token(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<UpdateTokenRequest>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state.token_service.update_token(&token, request).await?;
+    Ok(Json(token_info))
+}
+
+async fn disable_token(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state.token_service.disable_token(&token).await?;
+    Ok(Json(token_info))
+}
+
+async fn enable_token(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state.token_service.enable_token(&token).await?;
+    Ok(Json(token_info))
+}
+
+async fn set_forward_url(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<SetForwardUrlRequest>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state
+        .token_service
+        .set_forward_url(&token, Some(request.url))
+        .await?;
+    Ok(Json(token_info))
+}
+
+async fn clear_forward_url(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state.token_service.set_forward_url(&token, None).await?;
+    Ok(Json(token_info))
+}
+
+async fn list_forward_targets(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<Vec<ForwardTarget>>, AppError> {
+    let targets = state.token_service.list_forward_targets(&token).await?;
+    Ok(Json(targets))
+}
+
+async fn list_forward_regions(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<Vec<ForwardRegion>>, AppError> {
+    let regions = state.token_service.forward_regions(&token).await?;
+    Ok(Json(regions))
+}
+
+async fn add_forward_target(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<CreateForwardTargetRequest>,
+) -> std::result::Result<Json<ForwardTarget>, AppError> {
+    let target = state
+        .token_service
+        .add_forward_target(&token, request)
+        .await?;
+    Ok(Json(target))
+}
+
+async fn delete_forward_target(
+    State(state): State<AppState>,
+    Path((token, forward_id)): Path<(String, String)>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state
+        .token_service
+        .delete_forward_target(&token, &forward_id)
+        .await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn set_cors_origins(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<SetCorsOriginsRequest>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state
+        .token_service
+        .set_cors_origins(&token, request.origins)
+        .await?;
+    Ok(Json(token_info))
+}
+
+async fn set_webhook_secret(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    request: Option<Json<SetWebhookSecretRequest>>,
+) -> std::result::Result<Json<WebhookSecret>, AppError> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let secret = state
+        .token_service
+        .set_webhook_secret(&token, request.secret)
+        .await?;
+    Ok(Json(secret))
+}
+
+async fn rotate_webhook_secret(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<WebhookSecret>, AppError> {
+    let secret = state.token_service.rotate_webhook_secret(&token).await?;
+    Ok(Json(secret))
+}
+
+async fn create_readonly_key(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<ReadonlyKey>, AppError> {
+    let key = state.token_service.create_readonly_key(&token).await?;
+    Ok(Json(key))
+}
+
+async fn revoke_readonly_key(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<TokenInfo>, AppError> {
+    let token_info = state.token_service.revoke_readonly_key(&token).await?;
+    Ok(Json(token_info))
+}
+
+async fn get_token_metadata(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<HashMap<String, String>>, AppError> {
+    let metadata = state.token_service.get_metadata(&token).await?;
+    Ok(Json(metadata))
+}
+
+async fn set_token_metadata(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(metadata): Json<HashMap<String, String>>,
+) -> std::result::Result<Json<HashMap<String, String>>, AppError> {
+    let metadata = state.token_service.set_metadata(&token, metadata).await?;
+    Ok(Json(metadata))
+}
+
+async fn delete_token_metadata(
+    State(state): State<AppState>,
+    Path((token, key)): Path<(String, String)>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state
+        .token_service
+        .delete_metadata_key(&token, &key)
+        .await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn set_ip_rules(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(rules): Json<Vec<IpRule>>,
+) -> std::result::Result<Json<Vec<IpRule>>, AppError> {
+    let rules = state.token_service.set_ip_rules(&token, rules).await?;
+    Ok(Json(rules))
+}
+
+async fn clear_ip_rules(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state.token_service.set_ip_rules(&token, Vec::new()).await?;
+    Ok(Json(serde_json::json!({ "status": "cleared" })))
+}
+
+async fn set_custom_response(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<SetCustomResponseRequest>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state
+        .token_service
+        .set_custom_response(&token, request)
+        .await?;
+    Ok(Json(serde_json::json!({ "status": "configured" })))
+}
+
+async fn clear_custom_response(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state.token_service.clear_custom_response(&token).await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn get_slack_notification(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<SlackNotification>, AppError> {
+    let notification = state.token_service.get_slack_notification(&token).await?;
+    Ok(Json(notification))
+}
+
+async fn set_slack_notification(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<SetSlackNotificationRequest>,
+) -> std::result::Result<Json<SlackNotification>, AppError> {
+    let notification = state
+        .token_service
+        .set_slack_notification(&token, request)
+        .await?;
+    Ok(Json(notification))
+}
+
+async fn clear_slack_notification(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state.token_service.clear_slack_notification(&token).await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn get_email_notification(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<EmailNotification>, AppError> {
+    let notification = state.token_service.get_email_notification(&token).await?;
+    Ok(Json(notification))
+}
+
+async fn set_email_notification(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(request): Json<SetEmailNotificationRequest>,
+) -> std::result::Result<Json<EmailNotification>, AppError> {
+    let notification = state
+        .token_service
+        .set_email_notification(&token, request)
+        .await?;
+    Ok(Json(notification))
+}
+
+async fn clear_email_notification(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state.token_service.clear_email_notification(&token).await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn send_test_email(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    let notification = state.token_service.send_test_email(&token).await?;
+    Ok(Json(
+        serde_json::json!({ "status": "sent", "to": notification.to }),
+    ))
+}
+
 async fn delete_token(
     State(state): State<AppState>,
     Path(token): Path<String>,
@@ -196,6 +1041,549 @@ async fn get_webhook_logs(
     Ok(Json(requests))
 }
 
+/// Lets polling clients revalidate the request log on every poll instead of reusing it
+const REQUESTS_CACHE_CONTROL: &str = "private, max-age=0, must-revalidate";
+
+async fn list_requests(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<RequestPageQuery>,
+    Query(filter): Query<WebhookRequestFilter>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> std::result::Result<Response, AppError> {
+    let etag = state
+        .webhook_service
+        .requests_etag(&token, uri.query())
+        .await?;
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, REQUESTS_CACHE_CONTROL.to_string()),
+    ];
+    if etag_matches(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    if let Some(ref q) = query.q {
+        let results = state
+            .webhook_service
+            .search_request_bodies(&token, q, query.limit)
+            .await?;
+        return Ok((cache_headers, Json(results)).into_response());
+    }
+
+    let page = state
+        .webhook_service
+        .search_requests(&token, &filter, query.limit, query.before_id.as_deref())
+        .await?;
+    Ok((cache_headers, Json(page)).into_response())
+}
+
+/// Whether `If-None-Match` lists `etag`, compared weakly as GET requests allow
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    fn opaque(tag: &str) -> &str {
+        tag.trim().trim_start_matches("W/")
+    }
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+async fn token_stats(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<TokenStats>, AppError> {
+    let stats = state.webhook_service.token_stats(&token).await?;
+    Ok(Json(stats))
+}
+
+async fn hourly_volume(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<VolumeQuery>,
+) -> std::result::Result<Json<Vec<HourlyBucket>>, AppError> {
+    let buckets = state
+        .webhook_service
+        .hourly_volume(&token, query.days)
+        .await?;
+    Ok(Json(buckets))
+}
+
+async fn heatmap(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<HeatmapQuery>,
+) -> std::result::Result<Json<Heatmap>, AppError> {
+    let heatmap = state.webhook_service.heatmap(&token, query.weeks).await?;
+    Ok(Json(heatmap))
+}
+
+async fn duplicate_requests(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<DuplicatesQuery>,
+) -> std::result::Result<Json<Vec<DuplicateGroup>>, AppError> {
+    let groups = state
+        .webhook_service
+        .duplicate_requests(&token, query.window_minutes)
+        .await?;
+    Ok(Json(groups))
+}
+
+async fn geo_stats(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<Vec<CountryCount>>, AppError> {
+    let countries = state.webhook_service.geo_stats(&token).await?;
+    Ok(Json(countries))
+}
+
+async fn user_agent_stats(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<Vec<UserAgentCount>>, AppError> {
+    let user_agents = state.webhook_service.user_agent_stats(&token).await?;
+    Ok(Json(user_agents))
+}
+
+async fn top_headers(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<TopHeadersQuery>,
+) -> std::result::Result<Json<Vec<HeaderCount>>, AppError> {
+    let headers = state.webhook_service.top_headers(&token, query.n).await?;
+    Ok(Json(headers))
+}
+
+async fn request_schema(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    let schema = state.webhook_service.infer_schema(&token).await?;
+    Ok(Json(schema))
+}
+
+/// How long `GET /api/stats` serves a cached result before querying the database again
+const GLOBAL_STATS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+async fn global_stats(
+    State(state): State<AppState>,
+) -> std::result::Result<Json<GlobalStats>, AppError> {
+    if let Some((computed_at, stats)) = state.stats_cache.read().await.as_ref()
+        && computed_at.elapsed() < GLOBAL_STATS_CACHE_TTL
+    {
+        return Ok(Json(stats.clone()));
+    }
+
+    let mut stats = state.db.get_global_stats().await.map_err(|e| {
+        warn!("Failed to compute global stats: {}", e);
+        AppError::internal()
+    })?;
+    stats.max_tokens = Some(state.config.max_tokens).filter(|&max| max > 0);
+    *state.stats_cache.write().await = Some((Instant::now(), stats.clone()));
+    Ok(Json(stats))
+}
+
+async fn export_csv(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<CsvExportQuery>,
+) -> std::result::Result<Response, AppError> {
+    let rows = state
+        .webhook_service
+        .export_csv(&token, query.include_body)
+        .await?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"webhooks-{}.csv\"", token),
+        )
+        .body(axum::body::Body::from_stream(rows))
+        .map_err(|_| AppError::internal())
+}
+
+async fn export_ndjson(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(filter): Query<WebhookRequestFilter>,
+) -> std::result::Result<Response, AppError> {
+    let lines = state.webhook_service.export_ndjson(&token, filter).await?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .body(axum::body::Body::from_stream(lines))
+        .map_err(|_| AppError::internal())
+}
+
+async fn export_har(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Response, AppError> {
+    let archive = state
+        .webhook_service
+        .export_har(&token, state.config.base_url.clone())
+        .await?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"webhooks-{}.har\"", token),
+        )
+        .body(axum::body::Body::from_stream(archive))
+        .map_err(|_| AppError::internal())
+}
+
+async fn update_request_comment(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+    Json(request): Json<UpdateRequestCommentRequest>,
+) -> std::result::Result<Json<WebhookRequest>, AppError> {
+    let request = state
+        .webhook_service
+        .update_request_comment(&token, &request_id, request.comment)
+        .await?;
+    Ok(Json(request))
+}
+
+async fn flag_request(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<Json<WebhookRequest>, AppError> {
+    let request = state
+        .webhook_service
+        .set_request_flag(&token, &request_id, true)
+        .await?;
+    Ok(Json(request))
+}
+
+async fn unflag_request(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<Json<WebhookRequest>, AppError> {
+    let request = state
+        .webhook_service
+        .set_request_flag(&token, &request_id, false)
+        .await?;
+    Ok(Json(request))
+}
+
+async fn clear_request_flags(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    let unflagged = state.webhook_service.clear_request_flags(&token).await?;
+    Ok(Json(serde_json::json!({ "unflagged": unflagged })))
+}
+
+async fn delete_request(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state
+        .webhook_service
+        .delete_request(&token, &request_id)
+        .await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn request_as_curl(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<impl IntoResponse, AppError> {
+    let request = state
+        .webhook_service
+        .get_request(&token, &request_id)
+        .await?;
+    let url = original_url(&state.config.base_url, &request);
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        export::curl_command(&request, &url),
+    ))
+}
+
+async fn diff_requests(
+    State(state): State<AppState>,
+    Path((token, request_id, other_id)): Path<(String, String, String)>,
+) -> std::result::Result<Json<RequestDiff>, AppError> {
+    let a = state
+        .webhook_service
+        .get_request(&token, &request_id)
+        .await?;
+    let b = state.webhook_service.get_request(&token, &other_id).await?;
+    Ok(Json(diff::diff_requests(&a, &b)))
+}
+
+async fn request_as_httpie(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<impl IntoResponse, AppError> {
+    let request = state
+        .webhook_service
+        .get_request(&token, &request_id)
+        .await?;
+    let url = original_url(&state.config.base_url, &request);
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        export::httpie_command(&request, &url),
+    ))
+}
+
+async fn replay_request(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<Json<ReplayResult>, AppError> {
+    let result = state
+        .webhook_service
+        .replay_request(&token, &request_id)
+        .await?;
+    Ok(Json(result))
+}
+
+async fn list_dead_letters(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Json<Vec<DeadLetter>>, AppError> {
+    let dead_letters = state.webhook_service.list_dead_letters(&token).await?;
+    Ok(Json(dead_letters))
+}
+
+async fn requeue_dead_letter(
+    State(state): State<AppState>,
+    Path((token, id)): Path<(String, String)>,
+) -> std::result::Result<Json<DeadLetter>, AppError> {
+    let dead_letter = state
+        .webhook_service
+        .requeue_dead_letter(&token, &id)
+        .await?;
+    Ok(Json(dead_letter))
+}
+
+async fn discard_dead_letter(
+    State(state): State<AppState>,
+    Path((token, id)): Path<(String, String)>,
+) -> std::result::Result<Json<serde_json::Value>, AppError> {
+    state
+        .webhook_service
+        .discard_dead_letter(&token, &id)
+        .await?;
+    Ok(Json(serde_json::json!({ "status": "deleted" })))
+}
+
+async fn list_forward_attempts(
+    State(state): State<AppState>,
+    Path((token, request_id)): Path<(String, String)>,
+) -> std::result::Result<Json<Vec<ForwardAttempt>>, AppError> {
+    let attempts = state
+        .webhook_service
+        .list_forward_attempts(&token, &request_id)
+        .await?;
+    Ok(Json(attempts))
+}
+
+async fn stream_webhooks(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> std::result::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let receiver = state.webhook_service.subscribe(&token).await?;
+
+    info!("SSE client subscribed to token {}", token);
+
+    // Lagged receivers skip the missed messages rather than terminating the stream
+    let stream = BroadcastStream::new(receiver).filter_map(|message| {
+        let request = message.ok()?;
+        match Event::default().id(request.id.clone()).json_data(&request) {
+            Ok(event) => Some(Ok(event)),
+            Err(e) => {
+                warn!("Failed to serialize webhook request {}: {}", request.id, e);
+                None
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(30))
+            .text("keepalive"),
+    ))
+}
+
+/// Close code sent when the requested token does not exist
+const WS_CLOSE_TOKEN_NOT_FOUND: u16 = 4004;
+/// Close code sent when the subscription fails for any other reason
+const WS_CLOSE_INTERNAL_ERROR: u16 = 1011;
+
+async fn websocket_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_websocket(socket, state, token))
+}
+
+async fn handle_websocket(mut socket: WebSocket, state: AppState, token: String) {
+    let mut receiver = match state.webhook_service.subscribe(&token).await {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            let code = match e {
+                AppError::TokenNotFound | AppError::InvalidToken => WS_CLOSE_TOKEN_NOT_FOUND,
+                _ => WS_CLOSE_INTERNAL_ERROR,
+            };
+            let frame = CloseFrame {
+                code,
+                reason: e.to_string().into(),
+            };
+            let _ = socket.send(Message::Close(Some(frame))).await;
+            return;
+        }
+    };
+
+    info!("WebSocket client subscribed to token {}", token);
+
+    loop {
+        tokio::select! {
+            message = receiver.recv() => match message {
+                Ok(request) => {
+                    let text = match serde_json::to_string(&request) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            warn!("Failed to serialize webhook request {}: {}", request.id, e);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("WebSocket client for token {} lagged, skipped {} webhooks", token, skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                // Client messages (acknowledgements, commands) are accepted but not acted upon
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    // Dropping the receiver here lets the broadcaster clean up idle channels
+    info!("WebSocket client for token {} disconnected", token);
+}
+
+async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let uptime_secs = state.started_at.elapsed().as_secs();
+
+    match state.db.ping().await {
+        Ok(()) => {
+            let integrity_check = match state.db.get_last_health_check(HEALTH_CHECK_INTEGRITY).await
+            {
+                Ok(check) => check.map(IntegrityReport::from_health_check),
+                Err(e) => {
+                    warn!("Failed to get the last integrity check: {}", e);
+                    None
+                }
+            };
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "status": "ok",
+                    "db": "ok",
+                    "uptime_secs": uptime_secs,
+                    "integrity_check": integrity_check
+                })),
+            )
+        }
+        Err(e) => {
+            warn!("Health check failed: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "status": "degraded",
+                    "db": "error",
+                    "error": ping_error(&e),
+                    "uptime_secs": uptime_secs
+                })),
+            )
+        }
+    }
+}
+
+/// What health responses say about a failed database check; the details are only logged
+fn ping_error(error: &anyhow::Error) -> &'static str {
+    if error.is::<PingTimeout>() {
+        "database ping timed out"
+    } else {
+        "database unreachable"
+    }
+}
+
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let result = match state.db.ping().await {
+        Ok(()) => state.db.schema_ready().await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(true) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "status": "ready" })),
+        ),
+        Ok(false) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "status": "not_ready",
+                "error": "database schema has not been applied"
+            })),
+        ),
+        Err(e) => {
+            warn!("Readiness check failed: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "status": "not_ready",
+                    "error": ping_error(&e)
+                })),
+            )
+        }
+    }
+}
+
+async fn metrics_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> std::result::Result<Response, AppError> {
+    if let Some(ref expected) = state.config.metrics_auth_token
+        && !bearer_matches(&headers, expected)
+    {
+        return Err(AppError::Unauthorized);
+    }
+
+    match state.db.size_bytes().await {
+        Ok(size) => state.metrics.set_db_size(size),
+        Err(e) => warn!("Failed to read database size: {}", e),
+    }
+
+    let body = state.metrics.render().map_err(|e| {
+        warn!("Failed to encode metrics: {}", e);
+        AppError::internal()
+    })?;
+
+    Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )
+        .body(axum::body::Body::from(body))
+        .map_err(|_| AppError::internal())
+}
+
 async fn web_interface() -> Html<&'static str> {
     Html(include_str!("web_interface.html"))
 }
@@ -215,25 +1603,15 @@ async fn static_files(Path(path): Path<String>) -> std::result::Result<Response<
             Ok(Response::builder()
                 .header("content-type", "text/css; charset=utf-8")
                 .body(content)
-                .map_err(|_| AppError::InternalServerError)?)
+                .map_err(|_| AppError::internal())?)
         }
         "script.js" => {
             let content = include_str!("script.js").to_string();
             Ok(Response::builder()
                 .header("content-type", "application/javascript; charset=utf-8")
                 .body(content)
-                .map_err(|_| AppError::InternalServerError)?)
+                .map_err(|_| AppError::internal())?)
         }
         _ => Err(AppError::NotFound),
     }
 }
-
-fn convert_headers(headers: &HeaderMap) -> HashMap<String, Vec<String>> {
-    let mut header_map: HashMap<String, Vec<String>> = HashMap::new();
-    for (key, value) in headers.iter() {
-        let key_str = key.as_str().to_string();
-        let value_str = String::from_utf8_lossy(value.as_bytes()).to_string();
-        header_map.entry(key_str).or_default().push(value_str);
-    }
-    header_map
-}
@@ -0,0 +1,31 @@
+//! Webhook test service: receives, stores, forwards and replays webhooks.
+//! The `webhook-service` binary runs the HTTP server on top of this library, which other
+//! services can use to build `WebhookRequest` values or to embed the router.
+
+pub mod audit;
+pub mod auth;
+pub mod body;
+pub mod circuit_breaker;
+pub mod config;
+pub mod database;
+pub mod diff;
+pub mod error;
+pub mod export;
+pub mod geo;
+pub mod handlers;
+pub mod metrics;
+pub mod middleware;
+pub mod models;
+pub mod schema;
+pub mod services;
+pub mod telemetry;
+pub mod tls;
+pub mod transform;
+#[cfg(unix)]
+pub mod unix_socket;
+pub mod user_agent;
+
+pub use config::{Config, ConfigError};
+pub use error::{AppError, ParseError};
+pub use handlers::{AppState, create_router};
+pub use models::{MessageObject, TokenInfo, WebhookRequest};
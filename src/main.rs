@@ -1,42 +1,359 @@
+use axum_server::tls_rustls::RustlsConfig;
+use dashmap::DashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, RwLock};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
-mod config;
-mod database;
-mod error;
-mod handlers;
-mod models;
-mod services;
-
-use config::Config;
-use database::Database;
-use handlers::create_router;
-use services::{TokenService, WebhookService};
+use webhook_service::circuit_breaker::CircuitBreaker;
+use webhook_service::config::Config;
+use webhook_service::database::{self, DatabaseBackend};
+use webhook_service::handlers::{self, create_router};
+use webhook_service::metrics::Metrics;
+use webhook_service::middleware::{InFlightRequests, IpRateLimiter};
+use webhook_service::services::{
+    ForwardingService, INTEGRITY_CHECK_INTERVAL, NotificationService, RateLimiter, TokenCache,
+    TokenService, WebhookService,
+};
+#[cfg(unix)]
+use webhook_service::unix_socket;
+use webhook_service::{telemetry, tls};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    init_tracing()?;
 
     // Initialize configuration
     let config = Config::from_env()?;
 
     // Initialize database
-    let db = Arc::new(Database::new().await?);
+    let db = database::connect(&config).await?;
+
+    let token_cache = TokenCache::new(&config);
+
+    spawn_expired_token_cleanup(db.clone(), token_cache.clone());
+    if config.retention_days > 0 || config.retention_max_per_token > 0 {
+        spawn_request_retention(
+            db.clone(),
+            config.retention_days,
+            config.retention_max_per_token,
+        );
+    }
+
+    let rate_limiter = RateLimiter::new(&config);
+    if rate_limiter.is_enabled() {
+        spawn_rate_limiter_cleanup(rate_limiter.clone());
+    }
+
+    let circuit_breaker = CircuitBreaker::new(&config);
+
+    // Initialize metrics
+    let metrics = Arc::new(Metrics::new()?);
+
+    let ip_rate_limiter = IpRateLimiter::new(&config, metrics.clone());
+    if ip_rate_limiter.is_enabled() {
+        spawn_ip_rate_limiter_cleanup(ip_rate_limiter.clone());
+    }
+
+    let forwarding = ForwardingService::new(
+        db.clone(),
+        circuit_breaker.clone(),
+        metrics.clone(),
+        &config,
+    )?;
+    let notifications = NotificationService::new(db.clone(), &forwarding, &config)?;
+    let webhook_service = WebhookService::new(
+        db.clone(),
+        metrics.clone(),
+        forwarding,
+        notifications.clone(),
+        rate_limiter,
+        token_cache.clone(),
+        &config,
+    );
+    if config.idempotency_window_secs > 0 {
+        spawn_idempotency_cleanup(webhook_service.clone());
+    }
+    spawn_nonce_cleanup(webhook_service.clone());
+
+    let token_service = TokenService::new(
+        db.clone(),
+        &config,
+        metrics.clone(),
+        token_cache,
+        circuit_breaker,
+        Arc::new(DashMap::new()),
+        notifications,
+    );
+    if config.ip_token_create_limit > 0 {
+        spawn_token_creation_cleanup(token_service.clone());
+    }
+    spawn_integrity_check(token_service.clone());
 
     let app_state = handlers::AppState {
-        webhook_service: WebhookService::new(db.clone()),
-        token_service: TokenService::new(db, config.base_url.clone()),
+        webhook_service,
+        token_service,
+        db,
+        metrics,
+        config: Arc::new(config.clone()),
+        started_at: Instant::now(),
+        stats_cache: Arc::new(RwLock::new(None)),
     };
 
-    // Build the application
-    let app = create_router(app_state, &config);
+    // Build the application; the per-IP limit rejects requests before they are traced
+    let in_flight = InFlightRequests::new();
+    let app = create_router(app_state, &config)
+        .layer(ip_rate_limiter)
+        .layer(in_flight.clone());
 
-    let listener = tokio::net::TcpListener::bind(&config.bind_addr).await?;
+    let tls_config = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls_config =
+                RustlsConfig::from_config(tls::load_server_config(cert_path, key_path)?);
+            tls::spawn_reload_on_sighup(tls_config.clone(), cert_path.clone(), key_path.clone());
+            Some(tls_config)
+        }
+        _ => None,
+    };
 
-    // Log startup information
-    config.log_startup_info();
+    // Requests still in flight this long after the shutdown signal are abandoned
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let shutdown_started = Arc::new(Notify::new());
+    let shutdown = {
+        let shutdown_started = shutdown_started.clone();
+        let in_flight = in_flight.clone();
+        async move {
+            shutdown_signal(in_flight).await;
+            shutdown_started.notify_one();
+        }
+    };
+    let serve = async {
+        match (&config.bind_unix, tls_config) {
+            (Some(socket_path), _) => {
+                let (listener, _socket_file) = unix_socket::bind(socket_path)?;
+                config.log_startup_info();
+                unix_socket::serve(listener, app, shutdown).await?
+            }
+            (None, Some(tls_config)) => {
+                let listener = tokio::net::TcpListener::bind(&config.bind_addr).await?;
+                config.log_startup_info();
+                tls::serve(listener, app, tls_config, shutdown).await?
+            }
+            (None, None) => {
+                let listener = tokio::net::TcpListener::bind(&config.bind_addr).await?;
+                config.log_startup_info();
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(shutdown)
+                .await?
+            }
+        }
 
-    axum::serve(listener, app).await?;
+        anyhow::Ok(())
+    };
 
+    // The serve future is dropped on timeout, which still removes the unix socket file
+    let timed_out = tokio::select! {
+        result = serve => {
+            result?;
+            false
+        }
+        () = async {
+            shutdown_started.notified().await;
+            tokio::time::sleep(shutdown_timeout).await;
+        } => {
+            tracing::warn!(
+                "Graceful shutdown timed out after {}s with {} requests still in flight",
+                shutdown_timeout.as_secs(),
+                in_flight.count()
+            );
+            true
+        }
+    };
+
+    telemetry::shutdown();
+    if timed_out {
+        anyhow::bail!("graceful shutdown timed out");
+    }
+    tracing::info!("Shutdown complete");
     Ok(())
 }
+
+/// Human-readable logs by default; `LOG_FORMAT=json` emits one JSON object per line.
+/// Spans are also exported over OTLP when built with the `opentelemetry` feature.
+fn init_tracing() -> anyhow::Result<()> {
+    let log_format = std::env::var("LOG_FORMAT");
+    let fmt_layer = match log_format.as_deref() {
+        Ok("json") => tracing_subscriber::fmt::layer().json().boxed(),
+        _ => tracing_subscriber::fmt::layer().boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(telemetry::layer()?)
+        .with(LevelFilter::INFO)
+        .init();
+
+    if let Ok(other) = log_format.as_deref()
+        && other != "json"
+        && other != "text"
+    {
+        tracing::warn!("Unknown LOG_FORMAT '{}', using text logs", other);
+    }
+    Ok(())
+}
+
+/// Resolve on Ctrl+C or SIGTERM
+async fn shutdown_signal(in_flight: InFlightRequests) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    tracing::info!(
+        "Shutting down, waiting for {} in-flight requests to finish",
+        in_flight.count()
+    );
+}
+
+/// Periodically remove expired tokens and their logs
+fn spawn_expired_token_cleanup(db: Arc<dyn DatabaseBackend>, token_cache: TokenCache) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match db.prune_expired_tokens().await {
+                // Deleted tokens must not be served from the cache
+                Ok(pruned) if pruned > 0 => token_cache.clear(),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to prune expired tokens: {}", e),
+            }
+        }
+    });
+}
+
+/// Prune old and excess webhook requests on startup and then once a day
+fn spawn_request_retention(db: Arc<dyn DatabaseBackend>, retention_days: u32, max_per_token: u32) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(24 * 60 * 60));
+        loop {
+            interval.tick().await;
+
+            if retention_days > 0 {
+                match db.prune_old_requests(retention_days).await {
+                    Ok(pruned) => tracing::info!(
+                        "Pruned {} webhook requests older than {} days",
+                        pruned,
+                        retention_days
+                    ),
+                    Err(e) => tracing::warn!("Failed to prune old webhook requests: {}", e),
+                }
+            }
+
+            if max_per_token > 0 {
+                match db.prune_excess_requests_per_token(max_per_token).await {
+                    Ok(pruned) => tracing::info!(
+                        "Pruned {} webhook requests beyond {} per token",
+                        pruned,
+                        max_per_token
+                    ),
+                    Err(e) => tracing::warn!("Failed to prune excess webhook requests: {}", e),
+                }
+            }
+        }
+    });
+}
+
+fn spawn_idempotency_cleanup(webhook_service: WebhookService) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(5 * 60));
+        loop {
+            interval.tick().await;
+            webhook_service.prune_idempotency_keys().await;
+        }
+    });
+}
+
+/// Check the database's integrity weekly, counting from the last check before a restart
+fn spawn_integrity_check(token_service: TokenService) {
+    tokio::spawn(async move {
+        tokio::time::sleep(token_service.integrity_check_due_in().await).await;
+        let mut interval = tokio::time::interval(INTEGRITY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = token_service.check_integrity().await {
+                tracing::warn!("Scheduled integrity check failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Periodically remove nonces past their expiry
+fn spawn_nonce_cleanup(webhook_service: WebhookService) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            webhook_service.prune_expired_nonces().await;
+        }
+    });
+}
+
+fn spawn_rate_limiter_cleanup(rate_limiter: RateLimiter) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            rate_limiter.cleanup();
+        }
+    });
+}
+
+fn spawn_token_creation_cleanup(token_service: TokenService) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            token_service.cleanup_token_creations();
+        }
+    });
+}
+
+fn spawn_ip_rate_limiter_cleanup(ip_rate_limiter: IpRateLimiter) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            ip_rate_limiter.cleanup();
+        }
+    });
+}
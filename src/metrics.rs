@@ -0,0 +1,135 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::time::Duration;
+
+/// Upper bounds of the `webhook_payload_bytes` buckets, from 256 B to 1 MiB
+const PAYLOAD_BYTES_BUCKETS: &[f64] = &[
+    256.0,
+    1_024.0,
+    4_096.0,
+    16_384.0,
+    65_536.0,
+    262_144.0,
+    1_048_576.0,
+];
+
+/// Prometheus metrics exposed at `/metrics`
+pub struct Metrics {
+    registry: Registry,
+    webhook_requests_total: IntCounterVec,
+    webhook_tokens_total: IntCounter,
+    webhook_db_size_bytes: IntGauge,
+    webhook_payload_bytes_total: IntCounter,
+    webhook_payload_bytes: Histogram,
+    webhook_request_duration_seconds: Histogram,
+    ip_rate_limit_hits_total: IntCounter,
+    webhook_forward_timeout_total: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+
+        let webhook_requests_total = IntCounterVec::new(
+            Opts::new("webhook_requests_total", "Webhook requests received"),
+            &["token", "method", "status"],
+        )?;
+        let webhook_tokens_total =
+            IntCounter::new("webhook_tokens_total", "Webhook tokens created")?;
+        let webhook_db_size_bytes =
+            IntGauge::new("webhook_db_size_bytes", "Size of the database in bytes")?;
+
+        let webhook_payload_bytes_total = IntCounter::new(
+            "webhook_payload_bytes_total",
+            "Bytes of webhook bodies stored, as received",
+        )?;
+        let webhook_payload_bytes = Histogram::with_opts(
+            HistogramOpts::new(
+                "webhook_payload_bytes",
+                "Size of stored webhook bodies, as received",
+            )
+            .buckets(PAYLOAD_BYTES_BUCKETS.to_vec()),
+        )?;
+        let webhook_request_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "webhook_request_duration_seconds",
+            "Time from receiving a webhook to answering it",
+        ))?;
+        let ip_rate_limit_hits_total = IntCounter::new(
+            "ip_rate_limit_hits_total",
+            "Requests rejected by the per-IP rate limit",
+        )?;
+
+        let webhook_forward_timeout_total = IntCounterVec::new(
+            Opts::new(
+                "webhook_forward_timeout_total",
+                "Forward attempts that timed out, by connect or read timeout",
+            ),
+            &["type"],
+        )?;
+
+        registry.register(Box::new(webhook_requests_total.clone()))?;
+        registry.register(Box::new(webhook_tokens_total.clone()))?;
+        registry.register(Box::new(webhook_db_size_bytes.clone()))?;
+        registry.register(Box::new(webhook_payload_bytes_total.clone()))?;
+        registry.register(Box::new(webhook_payload_bytes.clone()))?;
+        registry.register(Box::new(webhook_request_duration_seconds.clone()))?;
+        registry.register(Box::new(ip_rate_limit_hits_total.clone()))?;
+        registry.register(Box::new(webhook_forward_timeout_total.clone()))?;
+
+        Ok(Self {
+            registry,
+            webhook_requests_total,
+            webhook_tokens_total,
+            webhook_db_size_bytes,
+            webhook_payload_bytes_total,
+            webhook_payload_bytes,
+            webhook_request_duration_seconds,
+            ip_rate_limit_hits_total,
+            webhook_forward_timeout_total,
+        })
+    }
+
+    pub fn record_webhook(&self, token: &str, method: &str, status: u16) {
+        self.webhook_requests_total
+            .with_label_values(&[token, method, &status.to_string()])
+            .inc();
+    }
+
+    pub fn record_payload(&self, bytes: usize) {
+        self.webhook_payload_bytes_total.inc_by(bytes as u64);
+        self.webhook_payload_bytes.observe(bytes as f64);
+    }
+
+    pub fn record_webhook_duration(&self, duration: Duration) {
+        self.webhook_request_duration_seconds
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn record_token_created(&self) {
+        self.webhook_tokens_total.inc();
+    }
+
+    pub fn record_ip_rate_limit_hit(&self) {
+        self.ip_rate_limit_hits_total.inc();
+    }
+
+    /// Count a forward attempt that timed out; `kind` is `connect_timeout` or `read_timeout`
+    pub fn record_forward_timeout(&self, kind: &str) {
+        self.webhook_forward_timeout_total
+            .with_label_values(&[kind])
+            .inc();
+    }
+
+    pub fn set_db_size(&self, bytes: i64) {
+        self.webhook_db_size_bytes.set(bytes);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> prometheus::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
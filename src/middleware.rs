@@ -0,0 +1,510 @@
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::{ConnectInfo, MatchedPath, Request};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, header};
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+use http_body::{Frame, SizeHint};
+use ipnetwork::IpNetwork;
+use jsonwebtoken::DecodingKey;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+use crate::audit;
+use crate::auth;
+use crate::config::Config;
+use crate::database::DatabaseBackend;
+use crate::error::AppError;
+use crate::handlers::client_ip;
+use crate::metrics::Metrics;
+use crate::services::{check_window, readonly_key_hash};
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Routes a token's read-only key may `GET`, all of which only read its stored requests
+const READONLY_KEY_ROUTES: &[&str] = &[
+    "/api/tokens/{token}/requests",
+    "/api/tokens/{token}/export.csv",
+    "/api/tokens/{token}/export.ndjson",
+    "/api/tokens/{token}/export.har",
+    "/api/tokens/{token}/requests/{request_id}/curl",
+    "/api/tokens/{token}/requests/{request_id}/httpie",
+    "/api/tokens/{token}/requests/{request_id}/diff/{other_id}",
+    "/api/tokens/{token}/requests/{request_id}/forward-attempts",
+    "/api/tokens/{token}/dead-letters",
+];
+
+/// The key of an `Authorization: Bearer <key>` header
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Whether the `Authorization: Bearer <key>` header matches `expected`, compared in constant time
+pub fn bearer_matches(headers: &HeaderMap, expected: &str) -> bool {
+    let provided = bearer(headers).unwrap_or("");
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+/// Rejects requests without the admin API key or a valid admin JWT (when `JWT_SECRET` is set);
+/// a no-op when no key is configured. A token's read-only key is accepted in their place for
+/// reading that token's requests, and rejected like any other key for anything else
+#[derive(Clone)]
+pub struct RequireAdmin {
+    key: Option<Arc<str>>,
+    jwt_key: Option<Arc<DecodingKey>>,
+    db: Arc<dyn DatabaseBackend>,
+}
+
+impl RequireAdmin {
+    pub fn new(config: &Config, db: Arc<dyn DatabaseBackend>) -> Self {
+        Self {
+            key: config.admin_api_key.as_deref().map(Arc::from),
+            jwt_key: config
+                .jwt_secret
+                .as_deref()
+                .map(|secret| Arc::new(DecodingKey::from_secret(secret.as_bytes()))),
+            db,
+        }
+    }
+}
+
+impl<S> Layer<S> for RequireAdmin {
+    type Service = RequireAdminService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireAdminService {
+            inner,
+            key: self.key.clone(),
+            jwt_key: self.jwt_key.clone(),
+            db: self.db.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequireAdminService<S> {
+    inner: S,
+    key: Option<Arc<str>>,
+    jwt_key: Option<Arc<DecodingKey>>,
+    db: Arc<dyn DatabaseBackend>,
+}
+
+impl<S> RequireAdminService<S> {
+    fn authorize(&self, headers: &HeaderMap, key: &str) -> Result<(), AppError> {
+        if bearer_matches(headers, key) {
+            return Ok(());
+        }
+
+        match (bearer(headers), &self.jwt_key) {
+            (Some(token), Some(jwt_key)) => auth::verify_admin_token(token, jwt_key).map(|_| ()),
+            _ => Err(AppError::Unauthorized),
+        }
+    }
+}
+
+/// Whether `key` is the read-only key of `token`, compared in constant time
+async fn is_readonly_key(
+    db: &dyn DatabaseBackend,
+    token: &str,
+    key: &str,
+) -> Result<bool, AppError> {
+    let hash = db.get_readonly_key_hash(token).await.map_err(|e| {
+        tracing::warn!("Failed to get read-only key: {}", e);
+        AppError::internal()
+    })?;
+    Ok(hash.is_some_and(|hash| {
+        hash.as_bytes()
+            .ct_eq(readonly_key_hash(key).as_bytes())
+            .into()
+    }))
+}
+
+/// Whether a read-only key may make this request
+fn readonly_key_permitted(request: &Request) -> bool {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str);
+    matches!(*request.method(), Method::GET | Method::HEAD)
+        && route.is_some_and(|route| READONLY_KEY_ROUTES.contains(&route))
+}
+
+/// The token in an `/api/tokens/{token}/...` path, if it is a UUID
+fn path_token(request: &Request) -> Option<&str> {
+    request
+        .uri()
+        .path()
+        .strip_prefix("/api/tokens/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|token| Uuid::parse_str(token).is_ok())
+}
+
+impl<S> Service<Request> for RequireAdminService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let admin = match self.key {
+            Some(ref key) => self.authorize(request.headers(), key),
+            None => Ok(()),
+        };
+
+        // A rejected bearer may still be the read-only key of the token in the path; the
+        // database is only asked on the routes such a key may use
+        let readonly_candidate = match admin {
+            Err(_) if readonly_key_permitted(&request) => {
+                bearer(request.headers()).zip(path_token(&request))
+            }
+            _ => None,
+        };
+        let Some((key, token)) = readonly_candidate else {
+            return match admin {
+                Ok(()) => Box::pin(self.inner.call(request)),
+                Err(e) => Box::pin(async { Ok(e.into_response()) }),
+            };
+        };
+
+        let (key, token) = (key.to_string(), token.to_string());
+        let db = self.db.clone();
+        // Take the service that was polled ready, leaving a clone for the next request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            match is_readonly_key(db.as_ref(), &token, &key).await {
+                Ok(true) => inner.call(request).await,
+                Ok(false) => match admin {
+                    Ok(()) => inner.call(request).await,
+                    Err(e) => Ok(e.into_response()),
+                },
+                Err(e) => Ok(e.into_response()),
+            }
+        })
+    }
+}
+
+/// Records the client's address as the actor of audit entries appended while handling a request
+#[derive(Clone)]
+pub struct AuditActor {
+    trust_proxy: bool,
+}
+
+impl AuditActor {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            trust_proxy: config.trust_proxy,
+        }
+    }
+}
+
+impl<S> Layer<S> for AuditActor {
+    type Service = AuditActorService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuditActorService {
+            inner,
+            trust_proxy: self.trust_proxy,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuditActorService<S> {
+    inner: S,
+    trust_proxy: bool,
+}
+
+impl<S> Service<Request> for AuditActorService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let actor = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| client_ip(request.headers(), *peer, self.trust_proxy));
+        Box::pin(audit::with_actor(actor, self.inner.call(request)))
+    }
+}
+
+tokio::task_local! {
+    /// ID of the HTTP request being handled
+    static REQUEST_ID: String;
+}
+
+/// ID of the HTTP request currently being handled, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// ID assigned to a request by [`SetRequestId`], stored in the request's extensions
+#[derive(Clone)]
+pub struct RequestId(pub String);
+
+/// Assigns every request a UUID that is returned in the `X-Request-ID` response header and
+/// included in error responses, so a reported error can be matched to its log entries
+#[derive(Clone, Default)]
+pub struct SetRequestId;
+
+impl<S> Layer<S> for SetRequestId {
+    type Service = SetRequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SetRequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct SetRequestIdService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for SetRequestIdService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // Kept out of the request headers so stored webhooks show what the sender sent
+        let id = Uuid::new_v4().to_string();
+        request.extensions_mut().insert(RequestId(id.clone()));
+        let header_value = HeaderValue::from_str(&id).expect("UUIDs are valid header values");
+        let future = self.inner.call(request);
+        Box::pin(REQUEST_ID.scope(id, async move {
+            let mut response = future.await?;
+            response.headers_mut().insert(X_REQUEST_ID, header_value);
+            Ok(response)
+        }))
+    }
+}
+
+/// Limits requests across all routes per client IP with a sliding window; a no-op when
+/// `IP_RATE_LIMIT_REQUESTS` is 0
+#[derive(Clone)]
+pub struct IpRateLimiter {
+    requests: Arc<DashMap<IpAddr, VecDeque<Instant>>>,
+    max_requests: usize,
+    window: Duration,
+    allowlist: Arc<[IpNetwork]>,
+    trust_proxy: bool,
+    metrics: Arc<Metrics>,
+}
+
+impl IpRateLimiter {
+    pub fn new(config: &Config, metrics: Arc<Metrics>) -> Self {
+        Self {
+            requests: Arc::new(DashMap::new()),
+            max_requests: config.ip_rate_limit_requests as usize,
+            window: Duration::from_secs(config.ip_rate_limit_window_secs),
+            allowlist: config.ip_rate_limit_allowlist.clone().into(),
+            trust_proxy: config.trust_proxy,
+            metrics,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_requests > 0
+    }
+
+    /// Count a request from the IP, rejecting it once the window is full
+    fn check(&self, ip: IpAddr) -> Result<(), AppError> {
+        if self.allowlist.iter().any(|network| network.contains(ip)) {
+            return Ok(());
+        }
+
+        let mut timestamps = self.requests.entry(ip).or_default();
+        check_window(&mut timestamps, 1, self.max_requests, self.window).inspect_err(|_| {
+            self.metrics.record_ip_rate_limit_hit();
+        })
+    }
+
+    /// Drop IPs with no requests inside the current window
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.requests.retain(|_, timestamps| {
+            timestamps
+                .back()
+                .is_some_and(|&t| now.duration_since(t) < self.window)
+        });
+    }
+}
+
+impl<S> Layer<S> for IpRateLimiter {
+    type Service = IpRateLimiterService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IpRateLimiterService {
+            inner,
+            limiter: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IpRateLimiterService<S> {
+    inner: S,
+    limiter: IpRateLimiter,
+}
+
+impl<S> Service<Request> for IpRateLimiterService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| client_ip(request.headers(), *peer, self.limiter.trust_proxy));
+        if self.limiter.is_enabled()
+            && let Some(ip) = ip
+            && let Err(e) = self.limiter.check(ip)
+        {
+            return Box::pin(async { Ok(e.into_response()) });
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Counts the requests currently being handled (until their response body has been sent),
+/// so shutdown can report what it is waiting for
+#[derive(Clone, Default)]
+pub struct InFlightRequests {
+    count: Arc<AtomicUsize>,
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+/// Decrements the in-flight count when dropped together with the request's future or response body
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> Layer<S> for InFlightRequests {
+    type Service = InFlightRequestsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InFlightRequestsService {
+            inner,
+            count: self.count.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InFlightRequestsService<S> {
+    inner: S,
+    count: Arc<AtomicUsize>,
+}
+
+impl<S> Service<Request> for InFlightRequestsService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let guard = InFlightGuard(self.count.clone());
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            Ok(response.map(|body| {
+                Body::new(GuardedBody {
+                    inner: body,
+                    _guard: guard,
+                })
+            }))
+        })
+    }
+}
+
+/// Response body that keeps its request counted as in flight until it is fully sent or dropped
+struct GuardedBody {
+    inner: Body,
+    _guard: InFlightGuard,
+}
+
+impl HttpBody for GuardedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -1,5 +1,53 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use axum::body::Bytes;
+use axum::http::{HeaderMap, Method, Request, Uri, header};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use cookie::Cookie;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use url::form_urlencoded;
+use uuid::Uuid;
+
+use crate::body;
+use crate::error::ParseError;
+use crate::transform::{self, Rule};
+
+/// `BodyEncoding` of bodies that are not valid UTF-8
+pub const BODY_ENCODING_BASE64: &str = "base64";
+
+/// Largest accepted body in bytes, after decompression, unless `MAX_PAYLOAD_BYTES` says otherwise
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1_048_576;
+
+/// Version of the stored request format, sent as `X-Schema-Version`. 2 changed
+/// `QueryParameters` from a list of single-entry objects to an object of value lists
+pub const RESPONSE_SCHEMA_VERSION: &str = "2";
+
+/// Kind of software that sent a request, told by its `User-Agent` header. Webhooks come from
+/// HTTP libraries; a browser usually means someone opened the webhook URL by accident
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientKind {
+    Library,
+    Browser,
+}
+
+impl ClientKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClientKind::Library => "library",
+            ClientKind::Browser => "browser",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "library" => Some(ClientKind::Library),
+            "browser" => Some(ClientKind::Browser),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WebhookRequest {
@@ -13,6 +61,50 @@ pub struct WebhookRequest {
     pub message_object: MessageObject,
     #[serde(rename = "Message")]
     pub message: Option<String>,
+    #[serde(rename = "ReplayedFrom", default)]
+    pub replayed_from: Option<String>,
+    #[serde(rename = "ForwardStatus", default)]
+    pub forward_status: Option<u16>,
+    /// Whether the `X-Hub-Signature-256` header matched; `None` when the token has no secret
+    #[serde(rename = "SignatureValid", default)]
+    pub signature_valid: Option<bool>,
+    /// Client address, taken from forwarding headers when `TRUST_PROXY` is set
+    #[serde(rename = "SourceIp", default)]
+    pub source_ip: Option<String>,
+    /// Address of the TCP peer, recorded even when forwarding headers are trusted
+    #[serde(rename = "PeerIp", default)]
+    pub peer_ip: Option<String>,
+    /// Country of `source_ip` (ISO 3166-1 alpha-2), when `GEO_DB_PATH` is set
+    #[serde(rename = "SourceCountry", default)]
+    pub source_country: Option<String>,
+    #[serde(rename = "SourceCity", default)]
+    pub source_city: Option<String>,
+    /// Autonomous system of `source_ip`, if the geolocation database has them
+    #[serde(rename = "SourceAsn", default)]
+    pub source_asn: Option<u32>,
+    /// Browser or HTTP library from the `User-Agent` header, e.g. `Chrome` or `python-requests`
+    #[serde(rename = "UaBrowser", default)]
+    pub ua_browser: Option<String>,
+    #[serde(rename = "UaOs", default)]
+    pub ua_os: Option<String>,
+    /// `pc`, `smartphone`, `mobilephone`, `appliance`, `crawler` or `misc`
+    #[serde(rename = "UaDeviceType", default)]
+    pub ua_device_type: Option<String>,
+    /// Whether the request was sent by an HTTP library or a browser, if either
+    #[serde(rename = "UaClient", default)]
+    pub ua_client: Option<ClientKind>,
+    /// Note left by a user, e.g. "this one triggered the bug"
+    #[serde(rename = "Comment", default)]
+    pub comment: Option<String>,
+    /// Marked for follow-up; flagged requests are kept by retention pruning
+    #[serde(rename = "Flagged", default)]
+    pub flagged: bool,
+    /// Headers whose values were replaced with `[REDACTED]` before storage
+    #[serde(rename = "RedactedHeaders", default)]
+    pub redacted_headers: Vec<String>,
+    /// W3C trace ID from the sender's `traceparent` header, or generated when it had none
+    #[serde(rename = "TraceId", default)]
+    pub trace_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -23,12 +115,207 @@ pub struct MessageObject {
     pub value: String,
     #[serde(rename = "Headers")]
     pub headers: HashMap<String, Vec<String>>,
+    /// Values of each parameter in URL order, e.g. `{"tag": ["a", "b"]}` for `?tag=a&tag=b`
     #[serde(rename = "QueryParameters")]
-    pub query_parameters: Vec<String>,
+    pub query_parameters: HashMap<String, Vec<String>>,
     #[serde(rename = "Body")]
     pub body: Option<String>,
     #[serde(rename = "BodyObject")]
     pub body_object: Option<serde_json::Value>,
+    #[serde(rename = "ContentType", default)]
+    pub content_type: Option<String>,
+    /// Size of the body as received, when it was sent with a `Content-Encoding`
+    #[serde(rename = "CompressedSize", default)]
+    pub compressed_size: Option<usize>,
+    /// `"base64"` when `Body` holds binary data; `None` for UTF-8 text
+    #[serde(rename = "BodyEncoding", default)]
+    pub body_encoding: Option<String>,
+    /// Size of the body in bytes as received, before any decoding
+    #[serde(rename = "BodySizeBytes", default)]
+    pub body_size_bytes: Option<usize>,
+    /// Hex-encoded SHA-256 of the decoded body
+    #[serde(rename = "BodySha256", default)]
+    pub body_sha256: Option<String>,
+    /// Cookies sent in the `Cookie` header, by name; values are `[REDACTED]` when the header is
+    #[serde(rename = "Cookies", default)]
+    pub cookies: HashMap<String, String>,
+}
+
+impl WebhookRequest {
+    /// The request as the service would store it, taking the token from the first segment of
+    /// the path. Meant for embedding and tests: the `Id` and `Date` are generated and no token
+    /// settings apply. Unlike the service, which keeps such bodies as text, a body declared as
+    /// JSON must parse.
+    pub async fn from_http_request(req: &Request<Bytes>) -> Result<Self, ParseError> {
+        let message_object =
+            MessageObject::from_parts(req.method(), req.uri(), req.headers(), req.body()).await?;
+        let declares_json = message_object
+            .content_type
+            .as_deref()
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase())
+            .is_some_and(|mime| mime == "application/json" || mime.ends_with("+json"));
+        if declares_json && message_object.body_object.is_none() && !req.body().is_empty() {
+            serde_json::from_slice::<serde_json::Value>(req.body())?;
+        }
+
+        let token_id = req
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            date: Utc::now().to_rfc3339(),
+            token_id: token_id.to_string(),
+            message_object,
+            message: None,
+            replayed_from: None,
+            forward_status: None,
+            signature_valid: None,
+            source_ip: None,
+            peer_ip: None,
+            source_country: None,
+            source_city: None,
+            source_asn: None,
+            ua_browser: None,
+            ua_os: None,
+            ua_device_type: None,
+            ua_client: None,
+            comment: None,
+            flagged: false,
+            redacted_headers: Vec::new(),
+            trace_id: None,
+        })
+    }
+}
+
+impl MessageObject {
+    /// Read a received request with a body of up to `DEFAULT_MAX_PAYLOAD_BYTES`
+    pub async fn from_parts(
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Self, ParseError> {
+        Self::from_parts_with_limit(method, uri, headers, body, DEFAULT_MAX_PAYLOAD_BYTES).await
+    }
+
+    /// Read a received request, undoing its `Content-Encoding` and parsing the body; the limit
+    /// applies to the decoded body
+    pub async fn from_parts_with_limit(
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        body: &[u8],
+        max_payload_bytes: usize,
+    ) -> Result<Self, ParseError> {
+        let query_parameters = uri
+            .query()
+            .map(|q| {
+                form_urlencoded::parse(q.as_bytes()).fold(
+                    HashMap::<String, Vec<String>>::new(),
+                    |mut params, (k, v)| {
+                        params
+                            .entry(k.into_owned())
+                            .or_default()
+                            .push(v.into_owned());
+                        params
+                    },
+                )
+            })
+            .unwrap_or_default();
+
+        // Undo Content-Encoding so the stored body is readable; the size cap applies to the decoded body
+        let content_encoding = headers
+            .get(header::CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok());
+        let (decoded, compressed_size) = match content_encoding {
+            Some(encoding) => match body::decompress(encoding, body, max_payload_bytes)? {
+                Some(decoded) => (decoded, Some(body.len())),
+                None => (body.to_vec(), None),
+            },
+            None => (body.to_vec(), None),
+        };
+
+        if decoded.len() > max_payload_bytes {
+            return Err(ParseError::TooLarge {
+                limit: max_payload_bytes,
+            });
+        }
+        // Binary bodies are kept base64-encoded rather than dropped
+        let (body_str, body_encoding) = match String::from_utf8(decoded.clone()) {
+            Ok(text) => (text, None),
+            Err(_) => (
+                base64::engine::general_purpose::STANDARD.encode(&decoded),
+                Some(BODY_ENCODING_BASE64.to_string()),
+            ),
+        };
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let body_object = body::parse_body(content_type, &decoded).await;
+
+        Ok(Self {
+            method: method.to_string(),
+            value: uri.to_string(),
+            headers: convert_headers(headers),
+            query_parameters,
+            body: if body_str.is_empty() {
+                None
+            } else {
+                Some(body_str)
+            },
+            body_object,
+            content_type: content_type.map(str::to_string),
+            compressed_size,
+            body_encoding,
+            body_size_bytes: Some(body.len()),
+            // Empty bodies are not hashed so body-less requests never count as duplicates
+            body_sha256: (!decoded.is_empty()).then(|| hex::encode(Sha256::digest(&decoded))),
+            cookies: parse_cookies(headers),
+        })
+    }
+
+    /// Whether `Body` holds base64-encoded binary data
+    pub fn is_binary(&self) -> bool {
+        self.body_encoding.as_deref() == Some(BODY_ENCODING_BASE64)
+    }
+
+    /// The body as it was received, decoding binary bodies
+    pub fn body_bytes(&self) -> Option<Vec<u8>> {
+        let body = self.body.as_ref()?;
+        if self.is_binary() {
+            base64::engine::general_purpose::STANDARD.decode(body).ok()
+        } else {
+            Some(body.clone().into_bytes())
+        }
+    }
+}
+
+/// Convert request headers into a map of header name to all of its values
+fn convert_headers(headers: &HeaderMap) -> HashMap<String, Vec<String>> {
+    let mut header_map: HashMap<String, Vec<String>> = HashMap::new();
+    for (key, value) in headers.iter() {
+        let key_str = key.as_str().to_string();
+        let value_str = String::from_utf8_lossy(value.as_bytes()).to_string();
+        header_map.entry(key_str).or_default().push(value_str);
+    }
+    header_map
+}
+
+/// Parse the name-value pairs of all `Cookie` headers; malformed pairs are skipped
+fn parse_cookies(headers: &HeaderMap) -> HashMap<String, String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(Cookie::split_parse)
+        .filter_map(|cookie| cookie.ok())
+        .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
+        .collect()
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -36,4 +323,907 @@ pub struct TokenInfo {
     pub token: String,
     pub created_at: String,
     pub webhook_url: String,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default)]
+    pub forward_url: Option<String>,
+    /// Whether incoming webhooks must carry a valid signature
+    #[serde(default)]
+    pub has_secret: bool,
+    /// Date of the most recent webhook received by this token
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    /// Stored requests after which new webhooks are rejected; `None` uses the server default
+    #[serde(default)]
+    pub max_requests: Option<u32>,
+    /// Browser origins allowed to call the webhook URL (`*` allows any)
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    /// Requests with the same body as one received this many seconds earlier are not stored again
+    #[serde(default)]
+    pub deduplicate_window_secs: Option<u32>,
+    /// Milliseconds to wait before answering a webhook, for testing client timeouts
+    #[serde(default)]
+    pub response_delay_ms: Option<u32>,
+    /// Headers whose values are not stored; `None` uses the server's `REDACT_HEADERS`
+    #[serde(default)]
+    pub redact_headers: Option<Vec<String>>,
+    /// HTTP methods webhooks may use; empty allows all
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    /// Media types webhooks may be sent as, without parameters; `None` allows all
+    #[serde(default)]
+    pub allowed_content_types: Option<Vec<String>>,
+    /// Webhooks whose `Date`/`X-Timestamp` header is older than this are rejected
+    #[serde(default)]
+    pub max_age_seconds: Option<u32>,
+    /// Webhooks must carry an unused `X-Nonce` issued by `POST /api/tokens/{token}/nonces`
+    #[serde(default)]
+    pub nonce_required: bool,
+    /// Receives a small JSON notice (not the request itself) on the token's `notification_events`
+    #[serde(default)]
+    pub notification_url: Option<String>,
+    /// Events reported to `notification_url`; `None` reports `webhook.received` only
+    #[serde(default)]
+    pub notification_events: Option<Vec<String>>,
+    /// Webhooks are rejected while the stored requests stay readable
+    #[serde(default)]
+    pub readonly: bool,
+    /// Whether a read-only API key was issued for the token
+    #[serde(default)]
+    pub has_readonly_key: bool,
+    /// Whether webhooks are answered with a configured mock response
+    #[serde(default)]
+    pub has_custom_response: bool,
+    /// Largest accepted webhook body in bytes; `None` uses the server's `MAX_PAYLOAD_BYTES`
+    #[serde(default)]
+    pub max_payload_bytes: Option<u32>,
+    /// Changes made to parsed bodies before they are stored, applied in order
+    #[serde(default)]
+    pub transform_rules: Vec<Rule>,
+    /// Where requests get their `Message` from; `None` tries the body's `type`, `event` and
+    /// `action` fields
+    #[serde(default)]
+    pub message_config: Option<MessageExtractor>,
+    /// Free-form key-value pairs, e.g. the environment or owner of the token
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// Number of stored webhook requests
+    #[serde(default)]
+    pub request_count: i64,
+}
+
+/// Where a token's requests get their `Message`, the short description shown in the request list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum MessageExtractor {
+    /// A field of the parsed body, e.g. `$.event.message`
+    BodyPath { path: String },
+    /// The first value of a request header
+    Header { name: String },
+}
+
+/// Body fields tried in order for tokens without a `message_config`
+const DEFAULT_MESSAGE_POINTERS: &[&str] = &["/type", "/event", "/action"];
+
+/// Longest `Message` taken from a request, in characters
+const MAX_MESSAGE_LENGTH: usize = 200;
+
+impl MessageExtractor {
+    /// Check the path and normalize the header name to lower case
+    pub fn validate(self) -> Result<Self, String> {
+        match self {
+            MessageExtractor::BodyPath { path } => {
+                transform::json_pointer(&path)?;
+                Ok(MessageExtractor::BodyPath { path })
+            }
+            MessageExtractor::Header { name } => {
+                let name = axum::http::HeaderName::from_bytes(name.trim().as_bytes())
+                    .map_err(|_| format!("invalid header name '{}'", name))?;
+                Ok(MessageExtractor::Header {
+                    name: name.as_str().to_string(),
+                })
+            }
+        }
+    }
+
+    /// The message for a request; `None` if the field or header is missing, or the field is
+    /// an object or array
+    pub fn extract(&self, message: &MessageObject) -> Option<String> {
+        let value = match self {
+            MessageExtractor::BodyPath { path } => {
+                let pointer = transform::json_pointer(path).ok()?;
+                scalar_text(message.body_object.as_ref()?.pointer(&pointer)?)?
+            }
+            MessageExtractor::Header { name } => message.headers.get(name)?.first()?.clone(),
+        };
+        short_message(value)
+    }
+}
+
+/// The first of the body's `type`, `event` and `action` fields that holds a plain value
+pub fn default_message(message: &MessageObject) -> Option<String> {
+    let body = message.body_object.as_ref()?;
+    DEFAULT_MESSAGE_POINTERS
+        .iter()
+        .find_map(|pointer| scalar_text(body.pointer(pointer)?))
+        .and_then(short_message)
+}
+
+fn scalar_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+fn short_message(text: String) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.chars().take(MAX_MESSAGE_LENGTH).collect())
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CreateTokenRequest {
+    pub ttl_seconds: Option<u64>,
+    pub label: Option<String>,
+    /// Caller-chosen token UUID (v4); a random one is generated when absent
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateTokenBatchRequest {
+    pub count: u32,
+    /// Tokens are labelled `<label_prefix><index>`, counting from 1
+    pub label_prefix: Option<String>,
+    pub ttl_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CreateNoncesRequest {
+    pub count: Option<u32>,
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Nonces issued together, each accepted with one webhook until `expires_at`
+#[derive(Debug, Clone, Serialize)]
+pub struct NonceBatch {
+    pub nonces: Vec<String>,
+    pub expires_at: String,
+}
+
+/// Outcome of presenting a nonce with a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceStatus {
+    Accepted,
+    AlreadyUsed,
+    /// Never issued for this token, or expired
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RequestPageQuery {
+    pub limit: Option<u32>,
+    pub before_id: Option<String>,
+    /// Search term; switches the listing to a search in bodies, URLs and messages
+    pub q: Option<String>,
+}
+
+/// Optional criteria for narrowing down a token's webhook requests
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookRequestFilter {
+    pub method: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub content_type: Option<String>,
+    /// Other requests whose body is identical to this request's
+    pub duplicate_of: Option<String>,
+    /// Full-text search in comments; all words must match
+    pub comment: Option<String>,
+    /// Only requests with this query parameter
+    pub query_key: Option<String>,
+    /// Only requests where `query_key` has this value
+    pub query_value: Option<String>,
+    /// Only flagged (`true`) or unflagged (`false`) requests
+    pub flagged: Option<bool>,
+}
+
+/// A request found by a search, with the field the search term was found in
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestSearchHit {
+    #[serde(flatten)]
+    pub request: WebhookRequest,
+    /// `body`, `value` (the URL) or `message`
+    pub match_field: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestSearchResults {
+    pub requests: Vec<RequestSearchHit>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateRequestCommentRequest {
+    /// `null` or an empty string removes the comment
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CsvExportQuery {
+    /// Add a `body` column (`?include_body=1`)
+    #[serde(default, deserialize_with = "flag")]
+    pub include_body: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestPage {
+    pub requests: Vec<WebhookRequest>,
+    /// Pass as `before_id` to fetch the next page; `None` once exhausted
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthTokenRequest {
+    pub admin_key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthToken {
+    pub token: String,
+    pub token_type: &'static str,
+    /// Seconds until the token expires
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetForwardUrlRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetCorsOriginsRequest {
+    pub origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpRuleAction {
+    Allow,
+    Deny,
+}
+
+impl IpRuleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IpRuleAction::Allow => "allow",
+            IpRuleAction::Deny => "deny",
+        }
+    }
+}
+
+/// Allows or denies incoming webhooks from a CIDR range, e.g. `185.107.80.0/22`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IpRule {
+    pub cidr: String,
+    pub action: IpRuleAction,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SetWebhookSecretRequest {
+    /// Secret to use; a random one is generated when omitted
+    pub secret: Option<String>,
+}
+
+/// Returned once when a signing secret is set; the secret cannot be read back later
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookSecret {
+    pub secret: String,
+    /// Until when the replaced secret is still accepted, after a rotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_expires_at: Option<String>,
+}
+
+/// Returned once when a read-only key is issued; only its digest is stored
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadonlyKey {
+    pub readonly_key: String,
+}
+
+/// Request volume of a single token
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenStats {
+    pub request_count: i64,
+    pub total_bytes: i64,
+    pub avg_bytes: f64,
+    pub max_bytes: i64,
+    pub oldest_request: Option<String>,
+    pub newest_request: Option<String>,
+}
+
+/// Number of requests with one method received during one hour
+#[derive(Debug, Clone, Serialize)]
+pub struct HourlyBucket {
+    /// UTC hour as `YYYY-MM-DDTHH`
+    pub hour: String,
+    pub method: String,
+    pub count: i64,
+}
+
+/// Average number of requests a token received in one hour of the week (UTC)
+#[derive(Debug, Clone, Serialize)]
+pub struct HeatmapCell {
+    /// Day of the week, 0 being Sunday
+    pub dow: u8,
+    pub hour: u8,
+    pub avg_count: f64,
+}
+
+/// Request volume by day of the week and hour, one cell for each of the 168 hours
+#[derive(Debug, Clone, Serialize)]
+pub struct Heatmap {
+    pub cells: Vec<HeatmapCell>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQuery {
+    /// Number of weeks to average over
+    #[serde(default = "default_heatmap_weeks")]
+    pub weeks: u32,
+}
+
+fn default_heatmap_weeks() -> u32 {
+    8
+}
+
+/// How many of a token's requests came from a country
+#[derive(Debug, Clone, Serialize)]
+pub struct CountryCount {
+    pub country: String,
+    pub count: i64,
+}
+
+/// How many of a token's requests came from the same parsed user agent
+#[derive(Debug, Clone, Serialize)]
+pub struct UserAgentCount {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device_type: Option<String>,
+    pub client: Option<ClientKind>,
+    pub count: i64,
+}
+
+/// How many of a token's requests carried a header
+#[derive(Debug, Clone, Serialize)]
+pub struct HeaderCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Requests with the same body received within the window of `GET .../stats/duplicates`
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    /// `BodySha256` shared by the requests
+    pub hash: String,
+    pub count: i64,
+    pub first_id: String,
+    pub last_id: String,
+    /// Method of the first request
+    pub method: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DuplicatesQuery {
+    /// How far back to look, in minutes
+    #[serde(default = "default_duplicates_window_minutes")]
+    pub window_minutes: u32,
+}
+
+fn default_duplicates_window_minutes() -> u32 {
+    60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolumeQuery {
+    /// Number of days to look back
+    #[serde(default = "default_volume_days")]
+    pub days: u32,
+}
+
+fn default_volume_days() -> u32 {
+    7
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TopHeadersQuery {
+    /// Number of header names to return
+    #[serde(default = "default_top_headers")]
+    pub n: usize,
+}
+
+fn default_top_headers() -> usize {
+    10
+}
+
+/// Aggregate statistics across all tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalStats {
+    pub total_tokens: i64,
+    /// Tokens that received a webhook in the last 24 hours
+    pub active_tokens: i64,
+    pub total_requests: i64,
+    /// Requests since midnight UTC
+    pub requests_today: i64,
+    /// Requests since Monday midnight UTC
+    pub requests_this_week: i64,
+    pub db_size_bytes: i64,
+    pub largest_token_by_count: Option<TokenRequestCount>,
+    /// `MAX_TOKENS` of the instance; `None` when unlimited
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenRequestCount {
+    pub token: String,
+    pub request_count: i64,
+}
+
+/// A delivery to the forward URL that still failed after all retries
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub id: String,
+    pub token_id: String,
+    pub request_id: String,
+    pub forward_url: String,
+    /// Error or response status of the final attempt
+    pub last_error: Option<String>,
+    pub failed_at: String,
+}
+
+/// A single attempt to deliver a request to its forward URL
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardAttempt {
+    pub request_id: String,
+    /// 1-based attempt number
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub attempted_at: String,
+}
+
+/// An extra URL that every incoming webhook is mirrored to, next to the token's forward URL
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardTarget {
+    pub id: String,
+    pub url: String,
+    pub enabled: bool,
+    /// Headers added to every mirrored request
+    pub headers: HashMap<String, String>,
+    /// Region the target runs in, matched against the instance's `SERVICE_REGION`
+    pub forward_region: Option<String>,
+    pub created_at: String,
+    /// Outcome of the most recent delivery to this target
+    pub last_result: Option<ForwardResult>,
+    /// `closed`, `open` while deliveries are paused after repeated failures, or `half_open`
+    pub circuit_state: &'static str,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateForwardTargetRequest {
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub forward_region: Option<String>,
+}
+
+/// Mirror targets of a token grouped by `forward_region`
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardRegion {
+    /// `null` groups the targets without a region
+    pub region: Option<String>,
+    /// Whether this is the instance's own `SERVICE_REGION`
+    pub local: bool,
+    pub targets: usize,
+    pub enabled: usize,
+    /// Enabled targets whose circuit is not open
+    pub healthy: usize,
+    /// `healthy`, `degraded` when some enabled targets are failing, `down` when all are,
+    /// or `disabled` when the region has no enabled targets
+    pub status: &'static str,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Outcome of mirroring one request to one forward target
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardResult {
+    pub forward_id: String,
+    pub request_id: String,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub delivered_at: String,
+}
+
+/// Outcome of re-delivering a stored request to the token's forward URL
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayResult {
+    /// ID of the newly stored copy of the replayed request
+    pub request_id: String,
+    pub status: u16,
+    pub body: String,
+}
+
+/// A value that differs between two compared requests
+#[derive(Debug, Clone, Serialize)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+/// Differences between two stored requests of a token
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestDiff {
+    pub method_changed: bool,
+    /// Path and query string; `None` when both requests used the same one
+    pub url_diff: Option<Change<String>>,
+    pub headers_added: BTreeMap<String, Vec<String>>,
+    pub headers_removed: BTreeMap<String, Vec<String>>,
+    pub headers_changed: BTreeMap<String, Change<Vec<String>>>,
+    /// Unified diff of the bodies (JSON pretty-printed first); empty when they are equal
+    pub body_diff: String,
+}
+
+/// Mock response returned to webhook senders instead of the default acknowledgement
+#[derive(Debug, Clone)]
+pub struct CustomResponse {
+    pub status_code: u16,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetCustomResponseRequest {
+    pub status_code: u16,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Response body as text
+    pub body: Option<String>,
+    /// Response body as base64, for binary payloads; takes precedence over `body`
+    pub body_base64: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenSort {
+    /// Newest tokens first
+    #[default]
+    Created,
+    /// Most recently used tokens first; never used tokens last
+    LastUsed,
+}
+
+/// One page of `GET /api/export`, also the body accepted by `POST /api/import`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DataExport {
+    pub tokens: Vec<TokenInfo>,
+    /// All stored requests of `tokens`
+    #[serde(default)]
+    pub requests: Vec<WebhookRequest>,
+    /// Page to fetch next; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportQuery {
+    /// Zero-based page number
+    pub page: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ImportQuery {
+    /// Validate and count what would be imported without writing anything
+    pub dry_run: bool,
+}
+
+/// Outcome of `POST /api/import`; existing tokens and requests are skipped, not overwritten
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub tokens_imported: u64,
+    pub tokens_skipped: u64,
+    pub requests_imported: u64,
+    pub requests_skipped: u64,
+    pub dry_run: bool,
+}
+
+/// Page usage of the database, as reported around `POST /api/admin/vacuum`
+#[derive(Debug, Clone, Serialize)]
+pub struct PageStats {
+    pub page_count: i64,
+    /// Unused pages inside the file; `None` on PostgreSQL, which has no database-wide free list
+    pub freelist_count: Option<i64>,
+}
+
+/// Outcome of `POST /api/admin/vacuum`
+#[derive(Debug, Clone, Serialize)]
+pub struct VacuumReport {
+    pub before: PageStats,
+    pub after: PageStats,
+    pub duration_ms: u64,
+}
+
+/// Outcome of `POST /api/admin/analyze`
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyzeReport {
+    pub duration_ms: u64,
+}
+
+/// Outcome of a database integrity check, from `POST /api/admin/integrity-check` or the
+/// weekly schedule
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    /// `ok`, or `errors` when the check found problems
+    pub integrity: &'static str,
+    pub errors: Vec<String>,
+    pub checked_at: String,
+}
+
+impl IntegrityReport {
+    pub fn new(errors: Vec<String>, checked_at: String) -> Self {
+        Self {
+            integrity: if errors.is_empty() { "ok" } else { "errors" },
+            errors,
+            checked_at,
+        }
+    }
+
+    /// Rebuild the report stored by [`IntegrityReport::to_health_check`]
+    pub fn from_health_check(check: HealthCheck) -> Self {
+        let errors = if check.result == "ok" {
+            Vec::new()
+        } else {
+            check.result.lines().map(str::to_string).collect()
+        };
+        Self::new(errors, check.checked_at)
+    }
+
+    pub fn to_health_check(&self) -> HealthCheck {
+        HealthCheck {
+            id: Uuid::new_v4().to_string(),
+            checked_at: self.checked_at.clone(),
+            check_type: HEALTH_CHECK_INTEGRITY.to_string(),
+            result: if self.errors.is_empty() {
+                "ok".to_string()
+            } else {
+                self.errors.join("\n")
+            },
+        }
+    }
+}
+
+/// `check_type` of the database integrity check in the `health_checks` table
+pub const HEALTH_CHECK_INTEGRITY: &str = "integrity";
+
+/// A stored result of a periodic or on-demand health check
+#[derive(Debug, Clone)]
+pub struct HealthCheck {
+    pub id: String,
+    pub checked_at: String,
+    pub check_type: String,
+    /// `ok`, or one problem per line
+    pub result: String,
+}
+
+/// A state-changing operation recorded in the audit log
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub occurred_at: String,
+    /// What was done, e.g. `token.create` or `request.delete`
+    pub action: String,
+    /// Address of the API client that made the change
+    pub actor_ip: Option<String>,
+    pub token_id: Option<String>,
+    pub details: Option<serde_json::Value>,
+}
+
+/// Query parameters of `GET /api/audit`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditQuery {
+    /// Only entries at or after this RFC 3339 date
+    pub from: Option<String>,
+    /// Only entries before this RFC 3339 date
+    pub to: Option<String>,
+    pub action: Option<String>,
+    pub token_id: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Query parameters of `GET /api/tokens`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TokenListQuery {
+    pub sort: TokenSort,
+    /// Only tokens that have not received a webhook in this many days
+    pub inactive_since: Option<u32>,
+    /// Only tokens with all of these metadata values, from `metadata.<key>=<value>` parameters
+    #[serde(skip)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Partial update of a token; absent fields are left unchanged, `null` clears them
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateTokenRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub label: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub max_requests: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub deduplicate_window_secs: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub response_delay_ms: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub redact_headers: Option<Option<Vec<String>>>,
+    /// `[]` allows all methods again
+    pub allowed_methods: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub allowed_content_types: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub max_age_seconds: Option<Option<u32>>,
+    pub nonce_required: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub notification_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub notification_events: Option<Option<Vec<String>>>,
+    pub readonly: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub max_payload_bytes: Option<Option<u32>>,
+    /// `[]` removes all rules
+    pub transform_rules: Option<Vec<Rule>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub message_config: Option<Option<MessageExtractor>>,
+}
+
+impl UpdateTokenRequest {
+    /// The fields the update sets, by name; `null` for the cleared ones
+    pub fn changes(&self) -> serde_json::Map<String, serde_json::Value> {
+        fn set<T: Serialize>(
+            changes: &mut serde_json::Map<String, serde_json::Value>,
+            name: &str,
+            value: Option<&T>,
+        ) {
+            if let Some(value) = value {
+                changes.insert(name.to_string(), serde_json::json!(value));
+            }
+        }
+
+        let mut changes = serde_json::Map::new();
+        set(&mut changes, "label", self.label.as_ref());
+        set(&mut changes, "max_requests", self.max_requests.as_ref());
+        set(
+            &mut changes,
+            "deduplicate_window_secs",
+            self.deduplicate_window_secs.as_ref(),
+        );
+        set(
+            &mut changes,
+            "response_delay_ms",
+            self.response_delay_ms.as_ref(),
+        );
+        set(&mut changes, "redact_headers", self.redact_headers.as_ref());
+        set(
+            &mut changes,
+            "allowed_methods",
+            self.allowed_methods.as_ref(),
+        );
+        set(
+            &mut changes,
+            "allowed_content_types",
+            self.allowed_content_types.as_ref(),
+        );
+        set(
+            &mut changes,
+            "max_age_seconds",
+            self.max_age_seconds.as_ref(),
+        );
+        set(&mut changes, "nonce_required", self.nonce_required.as_ref());
+        set(
+            &mut changes,
+            "notification_url",
+            self.notification_url.as_ref(),
+        );
+        set(
+            &mut changes,
+            "notification_events",
+            self.notification_events.as_ref(),
+        );
+        set(&mut changes, "readonly", self.readonly.as_ref());
+        set(
+            &mut changes,
+            "max_payload_bytes",
+            self.max_payload_bytes.as_ref(),
+        );
+        set(
+            &mut changes,
+            "transform_rules",
+            self.transform_rules.as_ref(),
+        );
+        set(&mut changes, "message_config", self.message_config.as_ref());
+        changes
+    }
+}
+
+/// Body of the POST sent to a token's `notification_url`
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub event: String,
+    pub token_id: String,
+    pub request_id: String,
+    pub timestamp: String,
+}
+
+/// Slack incoming webhook a token posts a message to for every webhook it receives
+#[derive(Debug, Clone, Serialize)]
+pub struct SlackNotification {
+    pub webhook_url: String,
+    /// Message text with `{{method}}`, `{{url}}` and `{{body}}` placeholders
+    pub template: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetSlackNotificationRequest {
+    pub webhook_url: String,
+    pub template: Option<String>,
+}
+
+/// Address a token emails when one of the subscribed events occurs
+#[derive(Debug, Clone, Serialize)]
+pub struct EmailNotification {
+    pub to: String,
+    pub events: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetEmailNotificationRequest {
+    pub to: String,
+    /// Defaults to `webhook.received`
+    pub events: Option<Vec<String>>,
+}
+
+/// Accept `1`/`true`/`yes` (any case) as set and everything else as unset
+fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    Ok(matches!(
+        value.to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes"
+    ))
+}
+
+/// Distinguish an explicit `null` from a missing field
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
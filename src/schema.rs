@@ -0,0 +1,135 @@
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Schema dialect of the generated documents
+const SCHEMA_DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Share of the objects that must contain a property for it to become required, in percent
+const REQUIRED_THRESHOLD_PERCENT: usize = 90;
+
+/// Infers a JSON Schema (draft-07) from sample JSON documents
+#[derive(Debug, Default)]
+pub struct SchemaInferrer {
+    root: Node,
+    samples: usize,
+}
+
+/// What was seen at one position of the samples
+#[derive(Debug, Default)]
+struct Node {
+    types: BTreeSet<&'static str>,
+    /// Number of objects seen here, to tell how often each property was present
+    objects: usize,
+    properties: BTreeMap<String, Node>,
+    /// Number of objects each property was present in
+    occurrences: BTreeMap<String, usize>,
+    items: Option<Box<Node>>,
+}
+
+impl SchemaInferrer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one sample document
+    pub fn add(&mut self, value: &Value) {
+        self.root.observe(value);
+        self.samples += 1;
+    }
+
+    /// The schema describing all samples added so far
+    pub fn schema(&self) -> Value {
+        let mut schema = Map::new();
+        schema.insert("$schema".into(), json!(SCHEMA_DRAFT_07));
+        schema.insert(
+            "description".into(),
+            json!(format!("Inferred from {} samples", self.samples)),
+        );
+        if let Value::Object(inferred) = self.root.schema() {
+            schema.extend(inferred);
+        }
+        Value::Object(schema)
+    }
+}
+
+impl Node {
+    fn observe(&mut self, value: &Value) {
+        self.types.insert(type_name(value));
+        match value {
+            Value::Object(fields) => {
+                self.objects += 1;
+                for (name, field) in fields {
+                    self.properties
+                        .entry(name.clone())
+                        .or_default()
+                        .observe(field);
+                    *self.occurrences.entry(name.clone()).or_default() += 1;
+                }
+            }
+            Value::Array(elements) => {
+                let items = self.items.get_or_insert_default();
+                for element in elements {
+                    items.observe(element);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn schema(&self) -> Value {
+        let mut schema = Map::new();
+        // Unions list `null` last, as in `["string", "null"]`
+        let mut types: Vec<&str> = self
+            .types
+            .iter()
+            .copied()
+            .filter(|&name| name != "null")
+            .collect();
+        if self.types.contains("null") {
+            types.push("null");
+        }
+        match types.as_slice() {
+            [] => {}
+            [single] => {
+                schema.insert("type".into(), json!(single));
+            }
+            _ => {
+                schema.insert("type".into(), json!(types));
+            }
+        }
+
+        if self.objects > 0 {
+            let properties: Map<String, Value> = self
+                .properties
+                .iter()
+                .map(|(name, node)| (name.clone(), node.schema()))
+                .collect();
+            let required: Vec<&String> = self
+                .occurrences
+                .iter()
+                .filter(|&(_, &count)| count * 100 > self.objects * REQUIRED_THRESHOLD_PERCENT)
+                .map(|(name, _)| name)
+                .collect();
+            schema.insert("properties".into(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".into(), json!(required));
+            }
+        }
+        if let Some(items) = &self.items {
+            schema.insert("items".into(), items.schema());
+        }
+        Value::Object(schema)
+    }
+}
+
+/// JSON Schema type of a value
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -15,6 +15,7 @@ class WebhookService {
         document.getElementById('refresh-tokens').addEventListener('click', () => this.loadTokens());
         document.getElementById('send-webhook').addEventListener('click', () => this.sendWebhook());
         document.getElementById('load-logs').addEventListener('click', () => this.loadLogs());
+        document.getElementById('unflag-all').addEventListener('click', () => this.unflagAll());
         
         // Auto-refresh tokens dropdown when tokens are loaded
         document.getElementById('selected-token-logs').addEventListener('change', (e) => {
@@ -24,13 +25,36 @@ class WebhookService {
         });
     }
 
+    // Calls the management API, asking for the admin API key if the server requires one
+    async apiFetch(path, options = {}, retry = true) {
+        const headers = { ...(options.headers || {}) };
+        const apiKey = localStorage.getItem('adminApiKey');
+        if (apiKey) {
+            headers['Authorization'] = `Bearer ${apiKey}`;
+        }
+
+        const response = await fetch(`${this.baseUrl}${path}`, { ...options, headers });
+        if (response.status === 401 && retry) {
+            const key = prompt('This server requires an admin API key:');
+            if (key) {
+                localStorage.setItem('adminApiKey', key.trim());
+                return this.apiFetch(path, options, false);
+            }
+        }
+        return response;
+    }
+
     async createToken() {
+        const labelInput = document.getElementById('token-label');
+        const label = labelInput.value.trim();
+
         try {
-            const response = await fetch(`${this.baseUrl}/api/tokens`, {
+            const response = await this.apiFetch(`/api/tokens`, {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/json',
                 },
+                body: JSON.stringify(label ? { label } : {}),
             });
 
             if (!response.ok) {
@@ -38,6 +62,7 @@ class WebhookService {
             }
 
             const token = await response.json();
+            labelInput.value = '';
             this.tokens.unshift(token);
             this.renderTokens();
             this.updateTokenDropdowns();
@@ -49,7 +74,7 @@ class WebhookService {
 
     async loadTokens() {
         try {
-            const response = await fetch(`${this.baseUrl}/api/tokens`);
+            const response = await this.apiFetch(`/api/tokens`);
             
             if (!response.ok) {
                 throw new Error(`HTTP ${response.status}: ${response.statusText}`);
@@ -69,7 +94,7 @@ class WebhookService {
         }
 
         try {
-            const response = await fetch(`${this.baseUrl}/api/tokens/${token}`, {
+            const response = await this.apiFetch(`/api/tokens/${token}`, {
                 method: 'DELETE',
             });
 
@@ -86,6 +111,26 @@ class WebhookService {
         }
     }
 
+    async setTokenDisabled(token, disabled) {
+        const action = disabled ? 'disable' : 'enable';
+        try {
+            const response = await this.apiFetch(`/api/tokens/${token}/${action}`, {
+                method: 'POST',
+            });
+
+            if (!response.ok) {
+                throw new Error(`HTTP ${response.status}: ${response.statusText}`);
+            }
+
+            const updated = await response.json();
+            this.tokens = this.tokens.map(t => t.token === token ? updated : t);
+            this.renderTokens();
+            this.showMessage(`Token ${action}d successfully!`, 'success');
+        } catch (error) {
+            this.showMessage(`Error trying to ${action} token: ${error.message}`, 'error');
+        }
+    }
+
     renderTokens() {
         const container = document.getElementById('token-list');
         
@@ -95,15 +140,19 @@ class WebhookService {
         }
 
         container.innerHTML = this.tokens.map(token => `
-            <div class="token-item">
+            <div class="token-item${token.disabled ? ' token-disabled' : ''}">
                 <div class="token-info">
-                    <div class="token-value">${token.token}</div>
+                    ${token.label ? `<div class="token-label">${this.escapeHtml(token.label)}</div>` : ''}
+                    <div class="token-value"><a href="/api/tokens/${token.token}" target="_blank" title="Token details">${token.token}</a>${token.disabled ? ' <span class="token-badge">disabled</span>' : ''}</div>
                     <div class="token-url">${token.webhook_url}</div>
                     <div class="token-created">Created: ${new Date(token.created_at).toLocaleString()}</div>
+                    <div class="token-created">Last used: ${token.last_used_at ? new Date(token.last_used_at).toLocaleString() : 'never'}</div>
+                    ${token.expires_at ? `<div class="token-expires">Expires: ${new Date(token.expires_at).toLocaleString()}</div>` : ''}
                 </div>
                 <div class="token-actions">
                     <button class="btn btn-secondary" onclick="webhookService.selectToken('${token.token}')">Select</button>
                     <button class="btn btn-info" onclick="webhookService.copyCommand('${token.token}')" title="Copy monitoring command">📋 CLI Command</button>
+                    <button class="btn btn-secondary" onclick="webhookService.setTokenDisabled('${token.token}', ${!token.disabled})">${token.disabled ? 'Enable' : 'Disable'}</button>
                     <button class="btn btn-danger" onclick="webhookService.deleteToken('${token.token}')">Delete</button>
                 </div>
             </div>
@@ -115,7 +164,7 @@ class WebhookService {
         const current = logsDropdown.value;
         logsDropdown.innerHTML = '<option value="">Select a token to view logs</option>' +
             this.tokens.map(token => 
-                `<option value="${token.token}">${token.token}</option>`
+                `<option value="${token.token}">${token.label ? `${this.escapeHtml(token.label)} (${token.token})` : token.token}</option>`
             ).join('');
         if (current && this.tokens.some(t => t.token === current)) {
             logsDropdown.value = current;
@@ -198,9 +247,12 @@ class WebhookService {
 
         if (!token) {
             document.getElementById('logs-container').innerHTML = '<div class="loading">Select a token to view logs</div>';
+            document.getElementById('analytics-container').innerHTML = '';
             return;
         }
 
+        this.loadAnalytics(token);
+
         try {
             const response = await fetch(`${this.baseUrl}/${token}/log/${count}`);
             
@@ -216,6 +268,159 @@ class WebhookService {
         }
     }
 
+    async loadAnalytics(token) {
+        const container = document.getElementById('analytics-container');
+        try {
+            const responses = await Promise.all([
+                this.apiFetch(`/api/tokens/${token}/stats/volume?days=7`),
+                this.apiFetch(`/api/tokens/${token}/stats/headers?n=10`),
+                this.apiFetch(`/api/tokens/${token}/stats/heatmap?weeks=8`),
+            ]);
+            const failed = responses.find(response => !response.ok);
+            if (failed) {
+                throw new Error(`HTTP ${failed.status}`);
+            }
+
+            const [volume, headers, heatmap] = await Promise.all(responses.map(response => response.json()));
+            this.renderAnalytics(volume, headers, heatmap);
+        } catch (error) {
+            container.innerHTML = '';
+        }
+    }
+
+    renderAnalytics(volume, headers, heatmap) {
+        const container = document.getElementById('analytics-container');
+        if (volume.length === 0 && headers.length === 0) {
+            container.innerHTML = '';
+            return;
+        }
+
+        const hours = new Map();
+        for (const bucket of volume) {
+            const methods = hours.get(bucket.hour) || [];
+            methods.push(`${bucket.method} ${bucket.count}`);
+            hours.set(bucket.hour, methods);
+        }
+        const volumeLines = Array.from(hours, ([hour, methods]) => `${hour}:00  ${methods.join(', ')}`);
+        const headerLines = headers.map(header => `${header.name}: ${header.count}`);
+
+        container.innerHTML = `
+            <details class="analytics-panel">
+                <summary>Analytics</summary>
+                <div class="analytics-grid">
+                    <div>
+                        <h4>Requests per hour (UTC, last 7 days)</h4>
+                        <pre>${this.escapeHtml(volumeLines.join('\n') || 'No requests')}</pre>
+                    </div>
+                    <div>
+                        <h4>Most frequent headers</h4>
+                        <pre>${this.escapeHtml(headerLines.join('\n') || 'No headers')}</pre>
+                    </div>
+                    <div>
+                        <h4>Activity by weekday and hour (UTC, last 8 weeks)</h4>
+                        ${this.renderHeatmap(heatmap.cells)}
+                    </div>
+                </div>
+            </details>
+        `;
+    }
+
+    renderHeatmap(cells) {
+        const days = ['Sun', 'Mon', 'Tue', 'Wed', 'Thu', 'Fri', 'Sat'];
+        const max = Math.max(...cells.map(cell => cell.avg_count));
+        const rows = days.map((day, dow) => {
+            const hours = cells
+                .filter(cell => cell.dow === dow)
+                .map(cell => {
+                    // Five shades, as in a contribution graph; empty hours stay blank
+                    const level = max > 0 ? Math.ceil((cell.avg_count / max) * 4) : 0;
+                    const title = `${day} ${String(cell.hour).padStart(2, '0')}:00 - ${cell.avg_count.toFixed(1)} per week`;
+                    return `<span class="heatmap-cell heatmap-level-${level}" title="${title}"></span>`;
+                })
+                .join('');
+            return `<div class="heatmap-row"><span class="heatmap-day">${day}</span>${hours}</div>`;
+        });
+        return `<div class="heatmap">${rows.join('')}</div>`;
+    }
+
+    async deleteLog(token, requestId) {
+        if (!confirm('Delete this webhook request?')) {
+            return;
+        }
+
+        try {
+            const response = await this.apiFetch(`/api/tokens/${token}/requests/${requestId}`, {
+                method: 'DELETE',
+            });
+
+            if (!response.ok) {
+                throw new Error(`HTTP ${response.status}: ${response.statusText}`);
+            }
+
+            this.showMessage('Webhook request deleted', 'success');
+            this.loadLogs();
+        } catch (error) {
+            this.showMessage(`Error deleting webhook request: ${error.message}`, 'error');
+        }
+    }
+
+    async saveComment(token, requestId, comment) {
+        try {
+            const response = await this.apiFetch(`/api/tokens/${token}/requests/${requestId}`, {
+                method: 'PATCH',
+                headers: {
+                    'Content-Type': 'application/json',
+                },
+                body: JSON.stringify({ comment: comment.trim() || null }),
+            });
+
+            if (!response.ok) {
+                throw new Error(`HTTP ${response.status}: ${response.statusText}`);
+            }
+
+            this.showMessage('Comment saved', 'success');
+        } catch (error) {
+            this.showMessage(`Error saving comment: ${error.message}`, 'error');
+        }
+    }
+
+    async setFlag(token, requestId, flagged) {
+        try {
+            const response = await this.apiFetch(`/api/tokens/${token}/requests/${requestId}/flag`, {
+                method: flagged ? 'POST' : 'DELETE',
+            });
+
+            if (!response.ok) {
+                throw new Error(`HTTP ${response.status}: ${response.statusText}`);
+            }
+
+            this.loadLogs();
+        } catch (error) {
+            this.showMessage(`Error updating flag: ${error.message}`, 'error');
+        }
+    }
+
+    async unflagAll() {
+        const token = document.getElementById('selected-token-logs').value;
+        if (!token) {
+            this.showMessage('Please select a token first', 'error');
+            return;
+        }
+
+        try {
+            const response = await this.apiFetch(`/api/tokens/${token}/flags`, { method: 'DELETE' });
+            if (!response.ok) {
+                throw new Error(`HTTP ${response.status}: ${response.statusText}`);
+            }
+
+            const { unflagged } = await response.json();
+            this.showMessage(`Unflagged ${unflagged} request${unflagged === 1 ? '' : 's'}`, 'success');
+            this.loadLogs();
+        } catch (error) {
+            this.showMessage(`Error unflagging requests: ${error.message}`, 'error');
+        }
+    }
+
     renderLogs(logs) {
         const container = document.getElementById('logs-container');
         
@@ -225,23 +430,31 @@ class WebhookService {
         }
 
         container.innerHTML = logs.map(log => `
-            <div class="log-item">
+            <div class="log-item${log.Flagged ? ' log-flagged' : ''}">
                 <div class="log-header">
+                    ${log.Flagged ? '<span class="log-flag" title="Flagged for follow-up">⚑</span>' : ''}
                     <span class="log-method method-${this.escapeHtml(log.MessageObject.Method.toLowerCase())}">${this.escapeHtml(log.MessageObject.Method)}</span>
+                    ${log.Message ? `<span class="log-message" title="Message">${this.escapeHtml(log.Message)}</span>` : ''}
                     <span class="log-id">ID: ${this.escapeHtml(log.Id)}</span>
                     <span class="log-timestamp">${this.escapeHtml(new Date(log.Date).toLocaleString())}</span>
+                    ${this.formatUserAgent(log)}
+                    ${log.SourceIp ? `<span class="log-ip" title="${log.PeerIp && log.PeerIp !== log.SourceIp ? `via ${this.escapeHtml(log.PeerIp)}` : ''}">From: ${this.escapeHtml(log.SourceIp)}</span>` : ''}
+                    <button class="btn btn-secondary btn-small" onclick="webhookService.setFlag('${this.escapeHtml(log.TokenId)}', '${this.escapeHtml(log.Id)}', ${!log.Flagged})">${log.Flagged ? 'Unflag' : 'Flag'}</button>
+                    <button class="btn btn-danger btn-small" onclick="webhookService.deleteLog('${this.escapeHtml(log.TokenId)}', '${this.escapeHtml(log.Id)}')">Delete</button>
                 </div>
                 <div class="log-details">
                     <div class="log-url">${this.escapeHtml(log.MessageObject.Value)}</div>
-                    ${log.MessageObject.Body ? `
-                        <div class="log-body"><pre>${this.formatJson(log.MessageObject.Body)}</pre></div>
-                    ` : ''}
+                    <input type="text" class="log-comment" maxlength="500" placeholder="Add a comment..."
+                        value="${this.escapeHtml(log.Comment || '')}"
+                        onchange="webhookService.saveComment('${this.escapeHtml(log.TokenId)}', '${this.escapeHtml(log.Id)}', this.value)">
+                    ${log.MessageObject.Body ? this.formatBody(log) : ''}
                     ${Object.keys(log.MessageObject.Headers).length > 0 ? `
                         <div class="log-headers">
                             <h4>Headers:</h4>
-                            <pre>${this.formatHeaders(log.MessageObject.Headers)}</pre>
+                            <pre>${this.formatHeaders(log.MessageObject.Headers, log.RedactedHeaders || [], log.MessageObject.Cookies)}</pre>
                         </div>
                     ` : ''}
+                    ${this.formatCookies(log.MessageObject.Cookies)}
                 </div>
             </div>
         `).join('');
@@ -261,6 +474,37 @@ class WebhookService {
             .replace(/`/g, '&#x60;');
     }
 
+    formatBody(log) {
+        const message = log.MessageObject;
+        if (message.BodyEncoding !== 'base64') {
+            return `<div class="log-body"><pre>${this.formatJson(message.Body)}</pre></div>`;
+        }
+
+        const bytes = Uint8Array.from(atob(message.Body), c => c.charCodeAt(0));
+        const shown = bytes.slice(0, 512);
+        const more = bytes.length > shown.length ? `\n... ${bytes.length - shown.length} more bytes` : '';
+        return `
+            <div class="log-body">
+                <div class="log-body-info">
+                    Binary body (${bytes.length} bytes${message.ContentType ? `, ${this.escapeHtml(message.ContentType)}` : ''})
+                    <a href="data:application/octet-stream;base64,${this.escapeHtml(message.Body)}" download="body-${this.escapeHtml(log.Id)}.bin">Download</a>
+                </div>
+                <pre>${this.escapeHtml(this.hexDump(shown) + more)}</pre>
+            </div>
+        `;
+    }
+
+    hexDump(bytes) {
+        const lines = [];
+        for (let offset = 0; offset < bytes.length; offset += 16) {
+            const row = Array.from(bytes.slice(offset, offset + 16));
+            const hex = row.map(b => b.toString(16).padStart(2, '0')).join(' ');
+            const ascii = row.map(b => (b >= 0x20 && b < 0x7f ? String.fromCharCode(b) : '.')).join('');
+            lines.push(`${offset.toString(16).padStart(8, '0')}  ${hex.padEnd(47)}  ${ascii}`);
+        }
+        return lines.join('\n');
+    }
+
     formatJson(str) {
         try {
             const obj = JSON.parse(str);
@@ -270,10 +514,53 @@ class WebhookService {
         }
     }
 
-    formatHeaders(headers) {
-        return this.escapeHtml(Object.entries(headers)
-            .map(([key, values]) => `${key}: ${values.join(', ')}`)
-            .join('\n'));
+    // Icon for the parsed User-Agent; the raw header stays in the headers list
+    formatUserAgent(log) {
+        if (!log.UaBrowser && !log.UaOs) {
+            return '';
+        }
+        const icons = {
+            pc: '🖥️',
+            smartphone: '📱',
+            mobilephone: '📱',
+            appliance: '📺',
+            crawler: '🕷️',
+        };
+        const icon = log.UaClient === 'library' ? '⚙️' : (icons[log.UaDeviceType] || '🌐');
+        const name = [log.UaBrowser, log.UaOs].filter(Boolean).join(' on ');
+        const kind = log.UaClient ? ` (${log.UaClient})` : '';
+        return `<span class="log-ua log-ua-${this.escapeHtml(log.UaClient || 'other')}" title="${this.escapeHtml(name + kind)}">${icon}</span>`;
+    }
+
+    formatHeaders(headers, redacted = [], cookies = {}) {
+        // Parsed cookies get their own section
+        const hideCookie = Object.keys(cookies || {}).length > 0;
+        return Object.entries(headers)
+            .filter(([key]) => !(hideCookie && key === 'cookie'))
+            .map(([key, values]) => {
+                const line = this.escapeHtml(`${key}: ${values.join(', ')}`);
+                return redacted.includes(key)
+                    ? `<span class="header-redacted" title="Value withheld from storage">${line}</span>`
+                    : line;
+            })
+            .join('\n');
+    }
+
+    formatCookies(cookies) {
+        const entries = Object.entries(cookies || {});
+        if (entries.length === 0) {
+            return '';
+        }
+
+        const lines = entries
+            .map(([name, value]) => this.escapeHtml(`${name} = ${value}`))
+            .join('\n');
+        return `
+            <details class="log-cookies">
+                <summary>Cookies (${entries.length})</summary>
+                <pre>${lines}</pre>
+            </details>
+        `;
     }
 
     showMessage(message, type) {
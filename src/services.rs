@@ -1,39 +1,394 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use tracing::{info, warn};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
+use base64::Engine;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use ipnetwork::IpNetwork;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use crate::database::Database;
+use crate::audit;
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::config::{Config, SmtpTls};
+use crate::database::DatabaseBackend;
 use crate::error::AppError;
-use crate::models::{MessageObject, TokenInfo, WebhookRequest};
+use crate::export;
+use crate::geo::GeoLookup;
+use crate::metrics::Metrics;
+use crate::models::{
+    AnalyzeReport, AuditEntry, AuditQuery, CountryCount, CreateForwardTargetRequest,
+    CustomResponse, DataExport, DeadLetter, DuplicateGroup, EmailNotification, ForwardAttempt,
+    ForwardRegion, ForwardResult, ForwardTarget, HEALTH_CHECK_INTEGRITY, HeaderCount, Heatmap,
+    HeatmapCell, HourlyBucket, ImportSummary, IntegrityReport, IpRule, IpRuleAction,
+    MessageExtractor, MessageObject, NonceBatch, NonceStatus, Notification, ReadonlyKey,
+    ReplayResult, RequestPage, RequestSearchHit, RequestSearchResults, SetCustomResponseRequest,
+    SetEmailNotificationRequest, SetSlackNotificationRequest, SlackNotification, TokenInfo,
+    TokenListQuery, TokenStats, UpdateTokenRequest, UserAgentCount, VacuumReport, WebhookRequest,
+    WebhookRequestFilter, WebhookSecret, default_message,
+};
+use crate::schema::SchemaInferrer;
+use crate::telemetry;
+use crate::transform::{Rule, Transformer};
+use crate::user_agent;
+
+/// Maximum length of a token label, in characters
+const MAX_LABEL_LENGTH: usize = 128;
+
+/// Maximum length of a request comment, in characters
+const MAX_COMMENT_LENGTH: usize = 500;
+
+/// Maximum length of a mirror target's forward_region, in characters
+const MAX_REGION_LENGTH: usize = 64;
+
+/// Stored in place of the values of redacted headers
+const REDACTED: &str = "[REDACTED]";
+
+/// Maximum number of tokens created by a single batch request
+const MAX_TOKEN_BATCH_SIZE: u32 = 100;
+
+/// Tokens per page of `GET /api/export`; each page also carries all their requests
+const EXPORT_TOKENS_PER_PAGE: u32 = 20;
+
+/// Maximum number of metadata entries on a token
+const MAX_METADATA_KEYS: usize = 20;
+
+/// Maximum length of a metadata key, in characters
+const MAX_METADATA_KEY_LENGTH: usize = 64;
+
+/// Maximum length of a metadata value, in characters
+const MAX_METADATA_VALUE_LENGTH: usize = 256;
+
+/// Maximum number of requests returned by a single log query
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Longest response delay a token can be configured with, in milliseconds
+const MAX_TOKEN_RESPONSE_DELAY_MS: u32 = 30_000;
+
+/// Time between the scheduled database integrity checks
+pub const INTEGRITY_CHECK_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Most transform rules a token can have
+const MAX_TRANSFORM_RULES: usize = 50;
+
+/// Largest `max_payload_bytes` a token can be configured with
+pub const MAX_TOKEN_PAYLOAD_BYTES: u32 = 64 * 1_048_576;
+
+/// Longest period covered by the hourly request volume, in days
+const MAX_VOLUME_DAYS: u32 = 90;
+
+/// Longest period the activity heatmap averages over, in weeks
+const MAX_HEATMAP_WEEKS: u32 = 52;
+
+/// Longest window of the duplicate bodies report, in minutes (30 days)
+const MAX_DUPLICATES_WINDOW_MINUTES: u32 = 30 * 24 * 60;
+
+/// Maximum number of header names returned by the top headers statistic
+const MAX_TOP_HEADERS: usize = 100;
+
+/// Number of recent request bodies the inferred schema is based on
+const SCHEMA_SAMPLE_SIZE: u32 = 50;
+
+/// Most nonces issued by a single request
+const MAX_NONCE_BATCH_SIZE: u32 = 100;
+
+/// Nonces issued when the request does not ask for a number
+const DEFAULT_NONCE_BATCH_SIZE: u32 = 10;
+
+/// How long issued nonces stay valid when no `ttl_seconds` is given
+const DEFAULT_NONCE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Size of generated nonces, in bytes
+const NONCE_BYTES: usize = 16;
+
+/// Header carrying a nonce issued by `POST /api/tokens/{token}/nonces`
+const NONCE_HEADER: &str = "x-nonce";
+
+/// Maximum number of requests returned by a search
+const MAX_SEARCH_RESULTS: u32 = 100;
+
+/// Page size used when the client does not specify one
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// Capacity of each per-token broadcast channel
+const BROADCAST_CAPACITY: usize = 100;
+
+/// Number of streamed records sent to the client per chunk
+const EXPORT_BATCH_SIZE: usize = 200;
+
+/// Header carrying the GitHub-style payload signature
+const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// Size of generated webhook secrets, in bytes
+const WEBHOOK_SECRET_BYTES: usize = 32;
+
+/// Notification event sent when a webhook has been stored
+const EVENT_WEBHOOK_RECEIVED: &str = "webhook.received";
+
+/// Notification event sent when delivery to the forward URL failed after all retries
+const EVENT_FORWARD_FAILED: &str = "webhook.failed_forward";
+
+/// Notification event sent when a forward delivery was moved to the dead letter queue
+const EVENT_DELIVERY_FAILED: &str = "webhook.delivery_failed";
+
+/// Events a token can subscribe its `notification_url` to
+const NOTIFICATION_EVENTS: &[&str] = &[
+    EVENT_WEBHOOK_RECEIVED,
+    EVENT_FORWARD_FAILED,
+    EVENT_DELIVERY_FAILED,
+];
+
+/// Header naming the event of an outgoing notification; webhooks carrying it trigger none,
+/// so a notification URL pointing back at this service cannot loop
+const NOTIFICATION_EVENT_HEADER: &str = "x-notification-event";
+
+/// How long a notification may take before it is given up
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Slack incoming webhooks are posted to at most once per this interval and token
+const SLACK_MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Characters of the body shown in a Slack message
+const SLACK_PREVIEW_CHARS: usize = 200;
+
+/// Maximum length of a Slack message template, in characters
+const MAX_SLACK_TEMPLATE_LENGTH: usize = 2000;
+
+/// Characters of the body included in a notification email
+const EMAIL_PREVIEW_CHARS: usize = 2000;
+
+/// W3C TraceContext header, `00-<trace-id>-<parent-id>-<flags>`
+const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Validate an allowed CORS origin, reducing it to `scheme://host[:port]`; `*` allows any origin
+fn normalize_origin(origin: &str) -> Result<String, AppError> {
+    let origin = origin.trim();
+    if origin == "*" {
+        return Ok(origin.to_string());
+    }
+
+    let invalid = || AppError::InvalidRequest(format!("invalid CORS origin: '{}'", origin));
+    let parsed = url::Url::parse(origin).map_err(|_| invalid())?;
+    if !matches!(parsed.scheme(), "http" | "https") || !matches!(parsed.path(), "" | "/") {
+        return Err(invalid());
+    }
+    Ok(parsed.origin().ascii_serialization())
+}
+
+/// Trim a user-supplied label, treating blank labels as absent
+fn normalize_label(label: Option<String>) -> Result<Option<String>, AppError> {
+    let Some(label) = label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+    else {
+        return Ok(None);
+    };
+    if label.chars().count() > MAX_LABEL_LENGTH {
+        return Err(AppError::InvalidRequest(format!(
+            "label must be at most {} characters",
+            MAX_LABEL_LENGTH
+        )));
+    }
+    Ok(Some(label))
+}
+
+/// Check a `sha256=<hex>` signature against the HMAC-SHA256 of the raw body
+fn verify_signature(secret: &str, signature: Option<&str>, body: &[u8]) -> bool {
+    let Some(provided) = signature
+        .and_then(|s| s.trim().strip_prefix("sha256="))
+        .and_then(|hex| hex::decode(hex).ok())
+    else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let expected = mac.finalize().into_bytes();
+
+    expected[..].ct_eq(&provided).into()
+}
+
+/// Digest under which a token's read-only key is stored; the key itself is never kept
+pub fn readonly_key_hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Whether the token's IP rules let the client through. Deny rules win over
+/// allow rules; once any allow rule exists, unmatched addresses are rejected
+fn ip_permitted(rules: &[IpRule], ip: IpAddr) -> bool {
+    let matches = |action: IpRuleAction| {
+        rules
+            .iter()
+            .filter(|rule| rule.action == action)
+            .filter_map(|rule| rule.cidr.parse::<IpNetwork>().ok())
+            .any(|network| network.contains(ip))
+    };
+
+    if matches(IpRuleAction::Deny) {
+        return false;
+    }
+    !rules.iter().any(|rule| rule.action == IpRuleAction::Allow) || matches(IpRuleAction::Allow)
+}
+
+/// Replace the values of the headers named in `names` (lowercase) with [`REDACTED`] and
+/// list them in `redacted_headers`. Returns the original headers if anything was replaced
+fn redact_headers(
+    request: &mut WebhookRequest,
+    names: &[String],
+) -> Option<HashMap<String, Vec<String>>> {
+    let mut redacted: Vec<String> = request
+        .message_object
+        .headers
+        .keys()
+        .filter(|name| names.iter().any(|redact| redact.eq_ignore_ascii_case(name)))
+        .cloned()
+        .collect();
+    if redacted.is_empty() {
+        return None;
+    }
+
+    let original = request.message_object.headers.clone();
+    for name in &redacted {
+        request
+            .message_object
+            .headers
+            .insert(name.clone(), vec![REDACTED.to_string()]);
+    }
+    // Keep the cookie names so it stays visible which cookies were sent
+    if redacted.iter().any(|name| name == header::COOKIE.as_str()) {
+        for value in request.message_object.cookies.values_mut() {
+            *value = REDACTED.to_string();
+        }
+    }
+    redacted.sort();
+    request.redacted_headers = redacted;
+    Some(original)
+}
+
+/// Headers carrying the time a webhook was sent, in order of preference
+const TIMESTAMP_HEADERS: &[&str] = &["x-timestamp", "date"];
+
+/// Parse a send time given as Unix seconds or milliseconds, RFC 3339 or an HTTP date
+fn parse_request_timestamp(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = value.trim();
+    if let Ok(number) = value.parse::<i64>() {
+        // Values this large are milliseconds
+        return if number.abs() >= 100_000_000_000 {
+            chrono::DateTime::from_timestamp_millis(number)
+        } else {
+            chrono::DateTime::from_timestamp(number, 0)
+        };
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .or_else(|_| chrono::DateTime::parse_from_rfc2822(value))
+        .ok()
+        .map(|date| date.with_timezone(&chrono::Utc))
+}
+
+/// The media type of a `Content-Type` value without its parameters, e.g. `application/json`
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// A query parameter value can only be matched together with its name
+fn check_query_filter(filter: &WebhookRequestFilter) -> Result<(), AppError> {
+    if filter.query_value.is_some() && filter.query_key.is_none() {
+        return Err(AppError::InvalidRequest(
+            "query_value requires query_key".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a database error is caused by inserting a row whose key already exists
+fn is_unique_violation(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<sqlx::Error>()
+        .and_then(sqlx::Error::as_database_error)
+        .is_some_and(|e| e.is_unique_violation())
+}
+
+/// Expiry date of a token created at `created_at` that lives for `ttl_seconds`
+fn expiry_from_ttl(
+    created_at: chrono::DateTime<chrono::Utc>,
+    ttl_seconds: Option<u64>,
+) -> Result<Option<String>, AppError> {
+    ttl_seconds
+        .map(|ttl| {
+            i64::try_from(ttl)
+                .ok()
+                .and_then(chrono::TimeDelta::try_seconds)
+                .and_then(|ttl| created_at.checked_add_signed(ttl))
+                .map(|expires_at| expires_at.to_rfc3339())
+                .ok_or_else(|| AppError::InvalidRequest("ttl_seconds is out of range".into()))
+        })
+        .transpose()
+}
 
 /// Generate webhook URL based on configuration or request headers
-pub fn generate_webhook_url(
+pub fn generate_webhook_url(base_url: &Option<String>, headers: &HeaderMap, token: &str) -> String {
+    // `HeaderMap` lookups ignore case, whatever case the proxy sent the names in
+    let base = base_url_from_headers(base_url, |name| {
+        headers.get(name).and_then(|value| value.to_str().ok())
+    });
+    format!("{}/{}", base, token)
+}
+
+/// Public origin of the service, without a trailing slash, from the headers of a stored request
+pub fn public_base_url(
     base_url: &Option<String>,
     headers: &HashMap<String, Vec<String>>,
-    token: &str,
+) -> String {
+    base_url_from_headers(base_url, |name| {
+        headers
+            .get(name)
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    })
+}
+
+/// Public origin of the service, without a trailing slash; `header` returns the first value
+/// of a request header
+fn base_url_from_headers<'a>(
+    base_url: &Option<String>,
+    header: impl Fn(&str) -> Option<&'a str>,
 ) -> String {
     // First try to use configured BASE_URL
     if let Some(configured_base) = base_url {
-        let normalized_base = configured_base.trim_end_matches('/');
-        return format!("{}/{}", normalized_base, token);
+        return configured_base.trim_end_matches('/').to_string();
     }
 
     // Fallback: extract from request headers and URI
     // Prefer forwarded headers set by proxies/CDNs
-    let first = |name: &str| {
-        headers
-            .get(name)
-            .and_then(|values| values.first())
-            .map(|s| s.split(',').next().unwrap_or("").trim())
-    };
+    let first = |name: &str| header(name).map(|s| s.split(',').next().unwrap_or("").trim());
     let fwd_proto = first("x-forwarded-proto");
     let fwd_host = first("x-forwarded-host");
     let (scheme, host) = match (fwd_proto, fwd_host) {
         (Some(proto), Some(h)) if matches!(proto, "http" | "https") && !h.is_empty() => (proto, h),
         _ => {
-            let host = headers.get("host").and_then(|values| values.first());
-            let host = host.map(|s| s.as_str()).unwrap_or("localhost:3000");
+            let host = header("host").unwrap_or("localhost:3000");
             let scheme = if host.starts_with("localhost") || host.starts_with("127.0.0.1") {
                 "http"
             } else {
@@ -44,149 +399,4131 @@ pub fn generate_webhook_url(
     };
 
     let base = format!("{}://{}", scheme, host);
-    let normalized_base = base.trim_end_matches('/');
-    format!("{}/{}", normalized_base, token)
+    base.trim_end_matches('/').to_string()
+}
+
+/// The URL a request was originally sent to, as seen by the client
+pub fn original_url(base_url: &Option<String>, request: &WebhookRequest) -> String {
+    let base = public_base_url(base_url, &request.message_object.headers);
+    format!("{}{}", base, request.message_object.value)
+}
+
+/// Which field a search term was found in: the first one containing all of its words, else
+/// the first one containing any of them (a full-text match may spread words over fields)
+fn search_match_field(request: &WebhookRequest, query: &str) -> &'static str {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let fields: Vec<(&'static str, String)> = [
+        ("body", request.message_object.body.as_deref()),
+        ("value", Some(request.message_object.value.as_str())),
+        ("message", request.message.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(name, text)| Some((name, text?.to_lowercase())))
+    .collect();
+
+    fields
+        .iter()
+        .find(|(_, text)| words.iter().all(|word| text.contains(word.as_str())))
+        .or_else(|| {
+            fields
+                .iter()
+                .find(|(_, text)| words.iter().any(|word| text.contains(word.as_str())))
+        })
+        .map_or("body", |(name, _)| *name)
+}
+
+/// Headers that describe the original connection and must not be copied to an outgoing request
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "connection",
+    "keep-alive",
+    "transfer-encoding",
+    "upgrade",
+    "te",
+    "trailer",
+    "proxy-connection",
+    "proxy-authorization",
+];
+
+/// Build an outgoing request that reproduces a stored webhook against another URL
+fn build_forward_request(
+    client: &reqwest::Client,
+    url: &str,
+    message: &MessageObject,
+) -> Result<reqwest::RequestBuilder, AppError> {
+    let method = reqwest::Method::from_bytes(message.method.as_bytes())
+        .map_err(|e| AppError::ForwardFailed(format!("invalid method: {}", e)))?;
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, values) in &message.headers {
+        if HOP_BY_HOP_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        let Ok(name) = reqwest::header::HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+        for value in values {
+            if let Ok(value) = reqwest::header::HeaderValue::from_str(value) {
+                headers.append(name.clone(), value);
+            }
+        }
+    }
+
+    let mut request = client.request(method, url).headers(headers);
+    if let Some(body) = message.body_bytes() {
+        request = request.body(body);
+    }
+    Ok(request)
+}
+
+/// The trace ID of the sender's `traceparent` header, if it has a valid one
+fn sender_trace_id(headers: &HashMap<String, Vec<String>>) -> Option<String> {
+    let traceparent = headers.get(TRACEPARENT_HEADER)?.first()?;
+    let trace_id = traceparent.trim().split('-').nth(1)?;
+    let valid = trace_id.len() == 32
+        && trace_id.bytes().all(|b| b.is_ascii_hexdigit())
+        && trace_id.bytes().any(|b| b != b'0');
+    valid.then(|| trace_id.to_ascii_lowercase())
+}
+
+/// Whether a forward attempt with this outcome is worth retrying
+fn is_retryable(status: Option<u16>) -> bool {
+    match status {
+        // Network errors and timeouts
+        None => true,
+        Some(status) => status >= 500 || status == 429,
+    }
+}
+
+/// Whether the token's `expires_at` has passed
+fn token_expired(token_info: &TokenInfo) -> bool {
+    token_info.expires_at.as_deref().is_some_and(|expires_at| {
+        chrono::DateTime::parse_from_rfc3339(expires_at)
+            .is_ok_and(|expires_at| expires_at <= chrono::Utc::now())
+    })
+}
+
+/// The token's notification URL, if it is subscribed to `event`
+fn notification_url<'a>(token_info: &'a TokenInfo, event: &str) -> Option<&'a str> {
+    let subscribed = match &token_info.notification_events {
+        Some(events) => events.iter().any(|subscribed| subscribed == event),
+        None => event == EVENT_WEBHOOK_RECEIVED,
+    };
+    token_info
+        .notification_url
+        .as_deref()
+        .filter(|_| subscribed)
+}
+
+/// Check that every event is one of `NOTIFICATION_EVENTS`, sorted and without duplicates
+fn notification_events(mut events: Vec<String>) -> Result<Vec<String>, AppError> {
+    if let Some(unknown) = events
+        .iter()
+        .find(|event| !NOTIFICATION_EVENTS.contains(&event.as_str()))
+    {
+        return Err(AppError::InvalidRequest(format!(
+            "unknown notification event '{}'; expected one of {}",
+            unknown,
+            NOTIFICATION_EVENTS.join(", ")
+        )));
+    }
+    events.sort();
+    events.dedup();
+    Ok(events)
+}
+
+/// `value`, unless it is `Some(0)`
+fn at_least_one(value: Option<u32>, message: &str) -> Result<Option<u32>, AppError> {
+    if value == Some(0) {
+        return Err(AppError::InvalidRequest(message.into()));
+    }
+    Ok(value)
+}
+
+/// Check and normalize header names to redact, sorted and without duplicates
+fn redact_header_names(headers: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut names = headers
+        .iter()
+        .map(|name| {
+            HeaderName::from_bytes(name.trim().as_bytes())
+                .map(|name| name.as_str().to_string())
+                .map_err(|_| AppError::InvalidRequest(format!("invalid header name '{}'", name)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Check that every method is an uppercase name, sorted and without duplicates
+fn allowed_methods(methods: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut methods = methods
+        .into_iter()
+        .map(|method| {
+            if method.is_empty() || !method.bytes().all(|b| b.is_ascii_uppercase()) {
+                return Err(AppError::InvalidRequest(format!(
+                    "invalid HTTP method '{}'; use uppercase names such as POST",
+                    method
+                )));
+            }
+            Ok(method)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    methods.sort();
+    methods.dedup();
+    Ok(methods)
+}
+
+/// The media types of `content_types`, sorted and without duplicates
+fn allowed_media_types(content_types: Vec<String>) -> Result<Vec<String>, AppError> {
+    if content_types.is_empty() {
+        return Err(AppError::InvalidRequest(
+            "allowed_content_types must not be empty; use null to accept any".into(),
+        ));
+    }
+    let mut media_types = content_types
+        .iter()
+        .map(|content_type| {
+            let media_type = media_type(content_type);
+            let valid = media_type.split_once('/').is_some_and(|(kind, subtype)| {
+                !kind.is_empty()
+                    && !subtype.is_empty()
+                    && !media_type.contains(char::is_whitespace)
+                    && !subtype.contains('/')
+            });
+            if !valid {
+                return Err(AppError::InvalidRequest(format!(
+                    "invalid content type '{}'",
+                    content_type
+                )));
+            }
+            Ok(media_type)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    media_types.sort();
+    media_types.dedup();
+    Ok(media_types)
+}
+
+/// Check that a notification URL is an http(s) URL
+fn valid_notification_url(url: String) -> Result<String, AppError> {
+    let parsed = url::Url::parse(&url)
+        .map_err(|e| AppError::InvalidRequest(format!("invalid notification URL: {}", e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(AppError::InvalidRequest(
+            "notification URL must use http or https".into(),
+        ));
+    }
+    Ok(url)
+}
+
+/// Check the number of rules and each rule's path
+fn transform_rules(rules: Vec<Rule>) -> Result<Vec<Rule>, AppError> {
+    if rules.len() > MAX_TRANSFORM_RULES {
+        return Err(AppError::InvalidRequest(format!(
+            "a token can have at most {} transform rules",
+            MAX_TRANSFORM_RULES
+        )));
+    }
+    for rule in &rules {
+        rule.validate().map_err(AppError::InvalidRequest)?;
+    }
+    Ok(rules)
+}
+
+/// The client for forwards, mirrors and notifications, sending through the configured proxies
+fn forward_client(config: &Config) -> anyhow::Result<reqwest::Client> {
+    let no_proxy = || {
+        config
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string)
+    };
+
+    // Only the proxies configured here are used, not ones the client would detect itself
+    let mut builder = reqwest::Client::builder()
+        .no_proxy()
+        .connect_timeout(Duration::from_millis(config.forward_connect_timeout_ms))
+        .timeout(Duration::from_millis(config.forward_read_timeout_ms));
+    if let Some(url) = &config.http_proxy {
+        builder = builder.proxy(reqwest::Proxy::http(url)?.no_proxy(no_proxy()));
+    }
+    if let Some(url) = &config.https_proxy {
+        builder = builder.proxy(reqwest::Proxy::https(url)?.no_proxy(no_proxy()));
+    }
+    if let Some(url) = &config.socks5_proxy {
+        builder = builder.proxy(reqwest::Proxy::all(url)?.no_proxy(no_proxy()));
+    }
+    Ok(builder.build()?)
 }
 
+/// Delivers webhooks to forward URLs, retrying transient failures with exponential backoff
 #[derive(Clone)]
-pub struct WebhookService {
-    db: Arc<Database>,
+pub struct ForwardingService {
+    db: Arc<dyn DatabaseBackend>,
+    client: reqwest::Client,
+    circuit_breaker: CircuitBreaker,
+    metrics: Arc<Metrics>,
+    max_retries: u32,
+    retry_base: Duration,
+    /// `SERVICE_REGION` of this instance
+    region: Option<String>,
+    /// Round-robin position among each token's mirror targets in `region`
+    next_target: Arc<DashMap<String, usize>>,
+    /// Headers naming the stored request and its token on every forwarded request
+    request_id_header: HeaderName,
+    token_id_header: HeaderName,
 }
 
-impl WebhookService {
-    pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+impl ForwardingService {
+    pub fn new(
+        db: Arc<dyn DatabaseBackend>,
+        circuit_breaker: CircuitBreaker,
+        metrics: Arc<Metrics>,
+        config: &Config,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            db,
+            client: forward_client(config)?,
+            circuit_breaker,
+            metrics,
+            max_retries: config.forward_max_retries,
+            retry_base: Duration::from_millis(config.forward_retry_base_ms),
+            region: config.service_region.clone(),
+            next_target: Arc::new(DashMap::new()),
+            request_id_header: HeaderName::try_from(format!(
+                "{}-Request-ID",
+                config.correlation_header_name
+            ))?,
+            token_id_header: HeaderName::try_from(format!(
+                "{}-Token-ID",
+                config.correlation_header_name
+            ))?,
+        })
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub async fn process_webhook(
-        &self,
-        token: &str,
-        method: &str,
-        uri: &str,
-        headers: HashMap<String, Vec<String>>,
-        query_params: Vec<String>,
-        body: Option<String>,
-        body_object: Option<serde_json::Value>,
-    ) -> Result<String, AppError> {
-        // Validate token format (should be a UUID)
-        Uuid::parse_str(token).map_err(|e| {
-            warn!(
-                "Invalid UUID token received in webhook processing: '{}' - {}",
-                token, e
-            );
-            AppError::InvalidToken
-        })?;
+    /// The mirror targets a request goes to. With a `SERVICE_REGION`, one target of that region
+    /// is picked round-robin, skipping targets whose circuit is open. Without a region, or when
+    /// no target in it is usable, every enabled target gets the request.
+    pub fn select_targets(&self, token: &str, targets: Vec<ForwardTarget>) -> Vec<ForwardTarget> {
+        let mut enabled: Vec<_> = targets
+            .into_iter()
+            .filter(|target| target.enabled)
+            .collect();
+        let Some(region) = &self.region else {
+            return enabled;
+        };
 
-        // Verify token exists in the database
-        if !self.db.token_exists(token).await.map_err(|e| {
-            warn!("Failed to check if token exists: {}", e);
-            AppError::InternalServerError
-        })? {
-            return Err(AppError::TokenNotFound);
+        let local: Vec<usize> = enabled
+            .iter()
+            .enumerate()
+            .filter(|(_, target)| {
+                target.forward_region.as_ref() == Some(region)
+                    && !matches!(
+                        self.circuit_breaker.state(&target.url),
+                        CircuitState::Open(_)
+                    )
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if local.is_empty() {
+            return enabled;
         }
 
-        // Create webhook request
-        let webhook_request = WebhookRequest {
-            id: Uuid::new_v4().to_string(),
-            date: chrono::Utc::now().to_rfc3339(),
-            token_id: token.to_string(),
-            message_object: MessageObject {
-                method: method.to_string(),
-                value: uri.to_string(),
-                headers,
-                query_parameters: query_params,
-                body,
-                body_object,
-            },
-            message: None,
+        let index = {
+            let mut next = self.next_target.entry(token.to_string()).or_default();
+            let index = local[*next % local.len()];
+            *next = next.wrapping_add(1);
+            index
         };
+        vec![enabled.swap_remove(index)]
+    }
 
-        // Store the request
-        self.db
-            .store_webhook_request(&webhook_request)
-            .await
-            .map_err(|e| {
-                warn!("Failed to store webhook request: {}", e);
-                AppError::InternalServerError
-            })?;
+    /// Feed the outcome of a delivery to the target's circuit breaker
+    fn record_circuit_outcome(&self, url: &str, status: Option<u16>) {
+        if is_retryable(status) {
+            self.circuit_breaker.record_failure(url);
+        } else {
+            self.circuit_breaker.record_success(url);
+        }
+    }
 
-        info!(
-            "Received {} request for token {}: {}",
-            method, token, webhook_request.id
-        );
+    /// The outgoing request for a stored webhook, with correlation headers naming the stored
+    /// request and a `traceparent` continuing its trace when the sender did not send one
+    fn forward_request(
+        &self,
+        url: &str,
+        webhook_request: &WebhookRequest,
+        message: &MessageObject,
+    ) -> Result<reqwest::Request, AppError> {
+        let mut request = build_forward_request(&self.client, url, message)?
+            .build()
+            .map_err(|e| AppError::ForwardFailed(e.to_string()))?;
 
-        Ok(webhook_request.id)
+        let headers = request.headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&webhook_request.id) {
+            headers.insert(self.request_id_header.clone(), value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&webhook_request.token_id) {
+            headers.insert(self.token_id_header.clone(), value);
+        }
+        if sender_trace_id(&message.headers).is_none()
+            && let Some(trace_id) = &webhook_request.trace_id
+        {
+            // Each hop gets a parent ID of its own within the request's trace
+            let traceparent = format!(
+                "00-{}-{}-01",
+                trace_id,
+                hex::encode(rand::random::<[u8; 8]>())
+            );
+            if let Ok(value) = HeaderValue::from_str(&traceparent) {
+                headers.insert(TRACEPARENT_HEADER, value);
+            }
+        }
+        Ok(request)
     }
 
-    pub async fn get_webhook_logs(
+    /// Send a stored request once, without retries or attempt tracking. `message` is the
+    /// stored request's message, possibly with headers left out
+    pub async fn send(
         &self,
-        token: &str,
-        count: u32,
-    ) -> Result<Vec<WebhookRequest>, AppError> {
-        let count = count.min(1000);
-        let requests = self
-            .db
-            .get_webhook_requests(token, count)
+        url: &str,
+        webhook_request: &WebhookRequest,
+        message: &MessageObject,
+    ) -> Result<reqwest::Response, AppError> {
+        let request = self.forward_request(url, webhook_request, message)?;
+        self.client
+            .execute(request)
             .await
-            .map_err(|e| {
-                warn!("Failed to get webhook requests: {}", e);
-                AppError::InternalServerError
-            })?;
-        Ok(requests)
+            .map_err(|e| self.forward_error(e))
     }
-}
 
-#[derive(Clone)]
-pub struct TokenService {
-    db: Arc<Database>,
-    base_url: Option<String>,
-}
+    /// Turn a failed forward into an error, telling timeouts apart so they can be tuned
+    fn forward_error(&self, error: reqwest::Error) -> AppError {
+        if !error.is_timeout() {
+            return AppError::ForwardFailed(error.to_string());
+        }
+        let kind = if error.is_connect() {
+            "connect_timeout"
+        } else {
+            "read_timeout"
+        };
+        self.metrics.record_forward_timeout(kind);
+        AppError::ForwardTimeout(kind)
+    }
 
-impl TokenService {
-    pub fn new(db: Arc<Database>, base_url: Option<String>) -> Self {
-        Self { db, base_url }
+    /// Send a stored request to a mirror target, with the target's headers replacing the original ones
+    async fn send_to_target(
+        &self,
+        target: &ForwardTarget,
+        webhook_request: &WebhookRequest,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut request = self.forward_request(
+            &target.url,
+            webhook_request,
+            &webhook_request.message_object,
+        )?;
+        for (name, value) in &target.headers {
+            let (Ok(name), Ok(value)) = (
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                reqwest::header::HeaderValue::from_str(value),
+            ) else {
+                continue;
+            };
+            request.headers_mut().insert(name, value);
+        }
+
+        self.client
+            .execute(request)
+            .await
+            .map_err(|e| self.forward_error(e))
     }
 
-    pub async fn create_token(
+    /// Deliver a request to all mirror targets concurrently and record each outcome.
+    /// Every target gets its own task, so a slow or failing one does not hold up the others.
+    pub async fn mirror(&self, webhook_request: Arc<WebhookRequest>, targets: Vec<ForwardTarget>) {
+        let deliveries = targets.into_iter().map(|target| {
+            let forwarding = self.clone();
+            let webhook_request = webhook_request.clone();
+            tokio::spawn(async move {
+                forwarding
+                    .deliver_to_target(&webhook_request, &target)
+                    .await
+            })
+        });
+
+        for result in join_all(deliveries).await {
+            match result {
+                Ok(result) => {
+                    if let Err(e) = self.db.record_forward_result(&result).await {
+                        warn!("Failed to record forward result: {}", e);
+                    }
+                }
+                Err(e) => warn!(
+                    "Forward task for request {} failed: {}",
+                    webhook_request.id, e
+                ),
+            }
+        }
+    }
+
+    /// Deliver a request to one mirror target, retrying transient failures
+    async fn deliver_to_target(
         &self,
-        headers: &HashMap<String, Vec<String>>,
-    ) -> Result<TokenInfo, AppError> {
-        let token = Uuid::new_v4();
+        webhook_request: &WebhookRequest,
+        target: &ForwardTarget,
+    ) -> ForwardResult {
+        let (mut status, mut error) = (None, None);
 
-        // Generate webhook URL based on configuration or request
-        let webhook_url = generate_webhook_url(&self.base_url, headers, &token.to_string());
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.backoff(attempt - 1)).await;
+            }
+            if !self.circuit_breaker.allow(&target.url) {
+                if attempt == 0 {
+                    error = Some("circuit open, target is failing".to_string());
+                }
+                break;
+            }
 
-        let token_info = TokenInfo {
-            token: token.to_string(),
-            created_at: chrono::Utc::now().to_rfc3339(),
-            webhook_url,
+            (status, error) = match self.send_to_target(target, webhook_request).await {
+                Ok(response) => (Some(response.status().as_u16()), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            self.record_circuit_outcome(&target.url, status);
+            if !is_retryable(status) {
+                break;
+            }
+        }
+
+        match (status, &error) {
+            (Some(status), _) => info!(
+                "Mirrored request {} to {} (status {})",
+                webhook_request.id, target.url, status
+            ),
+            (None, Some(error)) => warn!(
+                "Failed to mirror request {} to {}: {}",
+                webhook_request.id, target.url, error
+            ),
+            (None, None) => {}
+        }
+
+        ForwardResult {
+            forward_id: target.id.clone(),
+            request_id: webhook_request.id.clone(),
+            status_code: status,
+            error,
+            delivered_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Tell a token's notification URL about an event of a stored request, without retries
+    pub async fn notify(&self, url: &str, event: &str, webhook_request: &WebhookRequest) {
+        let notification = Notification {
+            event: event.to_string(),
+            token_id: webhook_request.token_id.clone(),
+            request_id: webhook_request.id.clone(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
         };
+        let body = match serde_json::to_vec(&notification) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize notification: {}", e);
+                return;
+            }
+        };
+        let result = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(NOTIFICATION_EVENT_HEADER, event)
+            .timeout(NOTIFICATION_TIMEOUT)
+            .body(body)
+            .send()
+            .await;
+        match result {
+            Ok(response) => info!(
+                "Sent {} notification for request {} to {} (status {})",
+                event,
+                webhook_request.id,
+                url,
+                response.status().as_u16()
+            ),
+            Err(e) => warn!(
+                "Failed to send {} notification for request {} to {}: {}",
+                event, webhook_request.id, url, e
+            ),
+        }
+    }
 
-        self.db.create_token(&token_info).await.map_err(|e| {
-            warn!("Failed to create token: {}", e);
-            AppError::InternalServerError
-        })?;
+    /// Deliver a request with retries, recording each attempt and the final response status.
+    /// Returns that status, or the dead letter recorded when every attempt failed transiently.
+    pub async fn deliver(
+        &self,
+        webhook_request: &WebhookRequest,
+        url: &str,
+    ) -> Result<u16, DeadLetter> {
+        let mut final_status = None;
+        let mut last_error = None;
 
-        info!("Created new token: {}", token);
-        Ok(token_info)
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.backoff(attempt - 1)).await;
+            }
+            if !self.circuit_breaker.allow(url) {
+                warn!(
+                    "Not forwarding request {} to {}: circuit is open",
+                    webhook_request.id, url
+                );
+                if attempt == 0 {
+                    last_error = Some("circuit open, target is failing".to_string());
+                }
+                break;
+            }
+
+            let (status, error) = match self
+                .send(url, webhook_request, &webhook_request.message_object)
+                .await
+            {
+                Ok(response) => (Some(response.status().as_u16()), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            self.record_circuit_outcome(url, status);
+
+            let record = ForwardAttempt {
+                request_id: webhook_request.id.clone(),
+                attempt: attempt + 1,
+                status_code: status,
+                error: error.clone(),
+                attempted_at: chrono::Utc::now().to_rfc3339(),
+            };
+            if let Err(e) = self.db.record_forward_attempt(&record).await {
+                warn!("Failed to record forward attempt: {}", e);
+            }
+
+            last_error = match status {
+                Some(status) => Some(format!("HTTP {}", status)),
+                None => error.clone(),
+            };
+            match (status, error) {
+                (Some(status), _) => info!(
+                    "Forwarded request {} to {} (attempt {}, status {})",
+                    webhook_request.id,
+                    url,
+                    attempt + 1,
+                    status
+                ),
+                (None, Some(error)) => warn!(
+                    "Failed to forward request {} to {} (attempt {}): {}",
+                    webhook_request.id,
+                    url,
+                    attempt + 1,
+                    error
+                ),
+                (None, None) => {}
+            }
+
+            if status.is_some() {
+                final_status = status;
+            }
+            if !is_retryable(status) {
+                break;
+            }
+        }
+
+        if let Some(status) = final_status
+            && let Err(e) = self
+                .db
+                .set_request_forward_status(&webhook_request.id, status)
+                .await
+        {
+            warn!("Failed to record forward status: {}", e);
+        }
+        match final_status {
+            Some(status) if !is_retryable(Some(status)) => Ok(status),
+            _ => Err(self.dead_letter(webhook_request, url, last_error).await),
+        }
     }
 
-    pub async fn list_tokens(&self) -> Result<Vec<TokenInfo>, AppError> {
-        let tokens = self.db.list_tokens().await.map_err(|e| {
-            warn!("Failed to list tokens: {}", e);
-            AppError::InternalServerError
-        })?;
-        Ok(tokens)
+    /// Keep a delivery that failed for good in the dead letter queue, so it can be requeued
+    async fn dead_letter(
+        &self,
+        webhook_request: &WebhookRequest,
+        url: &str,
+        last_error: Option<String>,
+    ) -> DeadLetter {
+        let dead_letter = DeadLetter {
+            id: Uuid::new_v4().to_string(),
+            token_id: webhook_request.token_id.clone(),
+            request_id: webhook_request.id.clone(),
+            forward_url: url.to_string(),
+            last_error,
+            failed_at: chrono::Utc::now().to_rfc3339(),
+        };
+        match self.db.create_dead_letter(&dead_letter).await {
+            Ok(()) => warn!(
+                "Moved request {} to the dead letter queue after failing to forward it to {}",
+                webhook_request.id, url
+            ),
+            Err(e) => warn!("Failed to record dead letter: {}", e),
+        }
+        dead_letter
+    }
+
+    /// Delay before the given retry: `base * 2^retry` plus up to 20% random jitter
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self.retry_base.saturating_mul(2u32.saturating_pow(retry));
+        delay + delay.mul_f64(rand::random_range(0.0..0.2))
+    }
+}
+
+/// SMTP server and sender address of notification emails
+struct Mailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+/// Posts messages about received webhooks to chat integrations and sends them by email
+#[derive(Clone)]
+pub struct NotificationService {
+    db: Arc<dyn DatabaseBackend>,
+    client: reqwest::Client,
+    /// When each token last posted to Slack
+    slack_sent: Arc<DashMap<String, Instant>>,
+    /// `None` when `SMTP_HOST` is not set
+    mailer: Option<Arc<Mailer>>,
+}
+
+impl NotificationService {
+    /// Chat messages go out through the forwarding client and its proxies and timeouts
+    pub fn new(
+        db: Arc<dyn DatabaseBackend>,
+        forwarding: &ForwardingService,
+        config: &Config,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            db,
+            client: forwarding.client.clone(),
+            slack_sent: Arc::new(DashMap::new()),
+            mailer: mailer(config)?.map(Arc::new),
+        })
+    }
+
+    pub fn email_enabled(&self) -> bool {
+        self.mailer.is_some()
+    }
+
+    /// Send a plain-text email from `SMTP_FROM`
+    pub async fn send_email(&self, to: &str, subject: &str, body: &str) -> Result<(), AppError> {
+        let mailer = self.mailer.clone().ok_or(AppError::EmailNotConfigured)?;
+        let to: Mailbox = to
+            .parse()
+            .map_err(|e| AppError::InvalidRequest(format!("invalid email address: {}", e)))?;
+        let email = Message::builder()
+            .from(mailer.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())
+            .map_err(|e| AppError::EmailFailed(e.to_string()))?;
+
+        // The SMTP transport blocks, so it runs off the async workers
+        tokio::task::spawn_blocking(move || mailer.transport.send(&email))
+            .await
+            .map_err(|e| AppError::EmailFailed(e.to_string()))?
+            .map_err(|e| AppError::EmailFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Email a summary of the webhook to the token's address if it subscribed to the event
+    pub async fn notify_email(&self, event: &str, webhook_request: &WebhookRequest) {
+        if self.mailer.is_none() {
+            return;
+        }
+        let token = &webhook_request.token_id;
+        let email = match self.db.get_email_notification(token).await {
+            Ok(Some(email)) => email,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(
+                    "Failed to get email notification for token {}: {}",
+                    token, e
+                );
+                return;
+            }
+        };
+        if !email.events.iter().any(|subscribed| subscribed == event) {
+            return;
+        }
+
+        let message = &webhook_request.message_object;
+        let subject = format!("{}: {} {}", event, message.method, message.value);
+        match self
+            .send_email(&email.to, &subject, &email_summary(event, webhook_request))
+            .await
+        {
+            Ok(()) => debug!("Emailed {} for request {}", event, webhook_request.id),
+            Err(e) => warn!(
+                "Failed to email {} for request {}: {}",
+                event, webhook_request.id, e
+            ),
+        }
+    }
+
+    /// Post a message about a received webhook to the token's Slack channel, if it has one.
+    /// Slack limits incoming webhooks, so webhooks arriving within `SLACK_MIN_INTERVAL` of
+    /// the last message are not posted.
+    pub async fn notify_slack(&self, webhook_request: &WebhookRequest) {
+        let token = &webhook_request.token_id;
+        let slack = match self.db.get_slack_notification(token).await {
+            Ok(Some(slack)) => slack,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(
+                    "Failed to get Slack notification for token {}: {}",
+                    token, e
+                );
+                return;
+            }
+        };
+        if !self.claim_slack_slot(token) {
+            debug!(
+                "Not posting request {} to Slack: token {} posted less than {}s ago",
+                webhook_request.id,
+                token,
+                SLACK_MIN_INTERVAL.as_secs()
+            );
+            return;
+        }
+
+        let message = slack_message(webhook_request, slack.template.as_deref());
+        let result = self
+            .client
+            .post(&slack.webhook_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(NOTIFICATION_TIMEOUT)
+            .body(message.to_string())
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => {
+                debug!("Posted request {} to Slack", webhook_request.id)
+            }
+            Ok(response) => warn!(
+                "Slack rejected the message for request {} (status {})",
+                webhook_request.id,
+                response.status().as_u16()
+            ),
+            Err(e) => warn!(
+                "Failed to post request {} to Slack: {}",
+                webhook_request.id, e
+            ),
+        }
+    }
+
+    /// Record a Slack message for the token unless it sent one within `SLACK_MIN_INTERVAL`
+    fn claim_slack_slot(&self, token: &str) -> bool {
+        let now = Instant::now();
+        match self.slack_sent.entry(token.to_string()) {
+            Entry::Occupied(entry) if now.duration_since(*entry.get()) < SLACK_MIN_INTERVAL => {
+                false
+            }
+            Entry::Occupied(mut entry) => {
+                entry.insert(now);
+                true
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+/// SMTP transport configured by `SMTP_HOST` and the other `SMTP_*` settings
+fn mailer(config: &Config) -> anyhow::Result<Option<Mailer>> {
+    let (Some(host), Some(from)) = (&config.smtp_host, &config.smtp_from) else {
+        return Ok(None);
+    };
+    let builder = match config.smtp_tls {
+        SmtpTls::StartTls => SmtpTransport::starttls_relay(host)?,
+        SmtpTls::Tls => SmtpTransport::relay(host)?,
+        SmtpTls::None => SmtpTransport::builder_dangerous(host),
+    };
+    let mut builder = builder
+        .port(config.smtp_port)
+        .timeout(Some(NOTIFICATION_TIMEOUT));
+    if let (Some(user), Some(password)) = (&config.smtp_user, &config.smtp_password) {
+        builder = builder.credentials(Credentials::new(user.clone(), password.clone()));
+    }
+    Ok(Some(Mailer {
+        transport: builder.build(),
+        from: from.parse()?,
+    }))
+}
+
+/// Plain-text description of a webhook for a notification email
+fn email_summary(event: &str, webhook_request: &WebhookRequest) -> String {
+    let message = &webhook_request.message_object;
+    let mut summary = format!(
+        "Event: {}\nToken: {}\nRequest: {}\nReceived: {}\nSource IP: {}\n\nMethod: {}\nURL: {}\nContent-Type: {}\n",
+        event,
+        webhook_request.token_id,
+        webhook_request.id,
+        webhook_request.date,
+        webhook_request.source_ip.as_deref().unwrap_or("-"),
+        message.method,
+        message.value,
+        message.content_type.as_deref().unwrap_or("-"),
+    );
+    if let Some(status) = webhook_request.forward_status {
+        summary.push_str(&format!("Forward status: {}\n", status));
+    }
+
+    let preview = body_preview(message, EMAIL_PREVIEW_CHARS);
+    if preview.is_empty() {
+        summary.push_str("\nEmpty body\n");
+    } else {
+        summary.push_str(&format!("\n{}\n", preview));
+    }
+    summary
+}
+
+/// Slack Block Kit message describing a received webhook. The template replaces the heading;
+/// values substituted into it are escaped, the template itself may use Slack markup.
+fn slack_message(webhook_request: &WebhookRequest, template: Option<&str>) -> serde_json::Value {
+    let message = &webhook_request.message_object;
+    let (method, url) = (slack_escape(&message.method), slack_escape(&message.value));
+    let preview = slack_escape(&body_preview(message, SLACK_PREVIEW_CHARS));
+
+    let heading = match template {
+        Some(template) => template
+            .replace("{{method}}", &method)
+            .replace("{{url}}", &url)
+            .replace("{{body}}", &preview),
+        None => format!("*{}* {}", method, url),
+    };
+    let content_type = message
+        .content_type
+        .as_deref()
+        .map_or_else(|| "-".to_string(), slack_escape);
+    let body = if preview.is_empty() {
+        "_Empty body_".to_string()
+    } else {
+        format!("```{}```", preview)
+    };
+
+    serde_json::json!({
+        "text": heading,
+        "blocks": [
+            { "type": "section", "text": { "type": "mrkdwn", "text": heading } },
+            {
+                "type": "section",
+                "fields": [
+                    { "type": "mrkdwn", "text": format!("*Method*\n{}", method) },
+                    { "type": "mrkdwn", "text": format!("*URL*\n{}", url) },
+                    { "type": "mrkdwn", "text": format!("*Content-Type*\n{}", content_type) },
+                    { "type": "mrkdwn", "text": format!("*Received*\n{}", webhook_request.date) },
+                ]
+            },
+            { "type": "section", "text": { "type": "mrkdwn", "text": body } },
+        ]
+    })
+}
+
+/// The first `max_chars` of the body as text, with binary bodies described by their size
+fn body_preview(message: &MessageObject, max_chars: usize) -> String {
+    let Some(body) = message.body.as_deref() else {
+        return String::new();
+    };
+    if message.is_binary() {
+        return format!(
+            "[{} bytes of binary data]",
+            message.body_size_bytes.unwrap_or(body.len())
+        );
+    }
+
+    let mut preview: String = body.chars().take(max_chars).collect();
+    if preview.len() < body.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Escape the characters Slack treats as markup in message text
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Fans out stored webhook requests to live subscribers, keyed by token
+#[derive(Clone, Default)]
+pub struct WebhookBroadcaster {
+    channels: Arc<RwLock<HashMap<String, broadcast::Sender<WebhookRequest>>>>,
+}
+
+impl WebhookBroadcaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, token: &str) -> broadcast::Receiver<WebhookRequest> {
+        let mut channels = self.channels.write().unwrap_or_else(|e| e.into_inner());
+        channels
+            .entry(token.to_string())
+            .or_insert_with(|| broadcast::channel(BROADCAST_CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, request: &WebhookRequest) {
+        let delivered = {
+            let channels = self.channels.read().unwrap_or_else(|e| e.into_inner());
+            match channels.get(&request.token_id) {
+                Some(sender) => sender.send(request.clone()).is_ok(),
+                None => return,
+            }
+        };
+
+        // Drop the channel once every subscriber has gone away, unless one subscribed since
+        if !delivered {
+            let mut channels = self.channels.write().unwrap_or_else(|e| e.into_inner());
+            if channels
+                .get(&request.token_id)
+                .is_some_and(|sender| sender.receiver_count() == 0)
+            {
+                channels.remove(&request.token_id);
+            }
+        }
+    }
+}
+
+/// How long a cached token is trusted. Changes made through this server invalidate the entry
+/// at once; the TTL bounds how long changes made elsewhere (another replica sharing the
+/// database) go unnoticed
+const TOKEN_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Recently looked-up tokens, including ones that do not exist, so that most webhooks can
+/// skip the database lookup
+#[derive(Clone)]
+pub struct TokenCache {
+    /// `None` when caching is disabled (`CACHE_MAX_TOKENS=0`)
+    tokens: Option<Arc<Mutex<LruCache<String, CachedToken>>>>,
+}
+
+struct CachedToken {
+    token_info: Option<TokenInfo>,
+    cached_at: Instant,
+}
+
+impl TokenCache {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            tokens: NonZeroUsize::new(config.cache_max_tokens)
+                .map(|capacity| Arc::new(Mutex::new(LruCache::new(capacity)))),
+        }
+    }
+
+    /// The cached token, `Some(None)` if it is known not to exist, or `None` if it is not
+    /// cached or the entry has expired
+    pub fn get(&self, token: &str) -> Option<Option<TokenInfo>> {
+        let mut tokens = self.lock()?;
+        let cached = tokens.get(token)?;
+        if cached.cached_at.elapsed() < TOKEN_CACHE_TTL {
+            return Some(cached.token_info.clone());
+        }
+        tokens.pop(token);
+        None
+    }
+
+    pub fn insert(&self, token: &str, token_info: Option<TokenInfo>) {
+        if let Some(mut tokens) = self.lock() {
+            tokens.put(
+                token.to_string(),
+                CachedToken {
+                    token_info,
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Forget the token, e.g. after its settings changed
+    pub fn invalidate(&self, token: &str) {
+        if let Some(mut tokens) = self.lock() {
+            tokens.pop(token);
+        }
+    }
+
+    /// Forget all tokens, e.g. after tokens were deleted in bulk
+    pub fn clear(&self) {
+        if let Some(mut tokens) = self.lock() {
+            tokens.clear();
+        }
+    }
+
+    fn lock(&self) -> Option<MutexGuard<'_, LruCache<String, CachedToken>>> {
+        let tokens = self.tokens.as_ref()?;
+        Some(
+            tokens
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+}
+
+/// Headers a sender can use to mark retries of the same webhook
+const IDEMPOTENCY_HEADERS: &[&str] = &["x-idempotency-key", "idempotency-key"];
+
+/// Recently seen idempotency keys and the request each one produced, backed by the database
+#[derive(Clone)]
+pub struct IdempotencyCache {
+    requests: Arc<DashMap<(String, String), (String, Instant)>>,
+    window: Duration,
+}
+
+impl IdempotencyCache {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            requests: Arc::new(DashMap::new()),
+            window: Duration::from_secs(config.idempotency_window_secs),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    pub fn get(&self, token: &str, key: &str) -> Option<String> {
+        self.requests
+            .get(&(token.to_string(), key.to_string()))
+            .filter(|entry| entry.1.elapsed() < self.window)
+            .map(|entry| entry.0.clone())
+    }
+
+    pub fn insert(&self, token: &str, key: &str, request_id: &str) {
+        self.requests.insert(
+            (token.to_string(), key.to_string()),
+            (request_id.to_string(), Instant::now()),
+        );
+    }
+
+    /// Drop keys that have left the window
+    pub fn cleanup(&self) {
+        self.requests
+            .retain(|_, (_, seen_at)| seen_at.elapsed() < self.window);
+    }
+}
+
+/// Per-token sliding window rate limiter
+#[derive(Clone)]
+pub struct RateLimiter {
+    requests: Arc<DashMap<String, VecDeque<Instant>>>,
+    max_requests: usize,
+    window: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            requests: Arc::new(DashMap::new()),
+            max_requests: config.rate_limit_requests as usize,
+            window: Duration::from_secs(config.rate_limit_window_secs),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_requests > 0
+    }
+
+    /// Count a request for the token, rejecting it once the window is full
+    pub fn check(&self, token: &str) -> Result<(), AppError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let mut timestamps = self.requests.entry(token.to_string()).or_default();
+        check_window(&mut timestamps, 1, self.max_requests, self.window)
+    }
+
+    /// Drop tokens with no requests inside the current window
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.requests.retain(|_, timestamps| {
+            timestamps
+                .back()
+                .is_some_and(|&t| now.duration_since(t) < self.window)
+        });
+    }
+}
+
+/// Count `count` requests in a sliding window of timestamps, rejecting all of them unless
+/// they fit within the `max_requests` allowed inside `window`
+pub fn check_window(
+    timestamps: &mut VecDeque<Instant>,
+    count: usize,
+    max_requests: usize,
+    window: Duration,
+) -> Result<(), AppError> {
+    let now = Instant::now();
+    while timestamps
+        .front()
+        .is_some_and(|&t| now.duration_since(t) >= window)
+    {
+        timestamps.pop_front();
+    }
+
+    let excess = (timestamps.len() + count).saturating_sub(max_requests);
+    if excess > 0 {
+        // The oldest requests leaving the window free the slots needed
+        let retry_after = timestamps
+            .get(excess - 1)
+            .map(|&oldest| window.saturating_sub(now.duration_since(oldest)))
+            .unwrap_or(window);
+        return Err(AppError::RateLimitExceeded {
+            retry_after_secs: retry_after.as_secs_f64().ceil() as u64,
+        });
+    }
+
+    timestamps.extend(std::iter::repeat_n(now, count));
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct WebhookService {
+    db: Arc<dyn DatabaseBackend>,
+    broadcaster: WebhookBroadcaster,
+    metrics: Arc<Metrics>,
+    forwarding: ForwardingService,
+    notifications: NotificationService,
+    rate_limiter: RateLimiter,
+    token_cache: TokenCache,
+    idempotency: IdempotencyCache,
+    /// Storage cap for tokens without their own `max_requests`
+    default_max_requests: Option<u32>,
+    /// Upper bound on the response delay applied to any token
+    max_response_delay_ms: u32,
+    /// Headers redacted for tokens without their own list
+    redact_headers: Vec<String>,
+    /// How far behind a sender's clock may be when checking a token's `max_age_seconds`
+    clock_skew_tolerance_secs: u64,
+    /// Body size limit for tokens without their own `max_payload_bytes`
+    max_payload_bytes: usize,
+    geo: GeoLookup,
+}
+
+impl WebhookService {
+    pub fn new(
+        db: Arc<dyn DatabaseBackend>,
+        metrics: Arc<Metrics>,
+        forwarding: ForwardingService,
+        notifications: NotificationService,
+        rate_limiter: RateLimiter,
+        token_cache: TokenCache,
+        config: &Config,
+    ) -> Self {
+        Self {
+            db,
+            broadcaster: WebhookBroadcaster::new(),
+            metrics,
+            forwarding,
+            notifications,
+            rate_limiter,
+            token_cache,
+            idempotency: IdempotencyCache::new(config),
+            default_max_requests: Some(config.default_max_requests_per_token)
+                .filter(|max| *max > 0),
+            max_response_delay_ms: config.max_response_delay_ms,
+            redact_headers: config.redact_headers.clone(),
+            clock_skew_tolerance_secs: config.clock_skew_tolerance_secs,
+            max_payload_bytes: config.max_payload_bytes,
+            geo: GeoLookup::new(config),
+        }
+    }
+
+    #[tracing::instrument(
+        name = "process_webhook",
+        skip_all,
+        fields(token_id = token, request_id = tracing::field::Empty)
+    )]
+    pub async fn process_webhook(
+        &self,
+        token: &str,
+        token_info: Option<&TokenInfo>,
+        message: MessageObject,
+        raw_body: &[u8],
+        client_ip: IpAddr,
+        peer_ip: IpAddr,
+    ) -> Result<String, AppError> {
+        telemetry::link_sender_trace(&message.headers);
+
+        let method = message.method.clone();
+        let result = self
+            .accept_webhook(token, token_info, message, raw_body, client_ip, peer_ip)
+            .await;
+
+        let status = match &result {
+            Ok(_) => 200,
+            Err(e) => e.status_code().as_u16(),
+        };
+        self.metrics.record_webhook(token, &method, status);
+
+        result
+    }
+
+    /// Reject a request whose `X-Timestamp` (or else `Date`) header is older than `max_age_secs`
+    fn check_request_age(
+        &self,
+        token: &str,
+        message: &MessageObject,
+        max_age_secs: u32,
+    ) -> Result<(), AppError> {
+        let (header, value) = TIMESTAMP_HEADERS
+            .iter()
+            .find_map(|&name| Some((name, message.headers.get(name)?.first()?)))
+            .ok_or_else(|| {
+                AppError::InvalidRequest(format!(
+                    "Missing X-Timestamp or Date header; this token rejects requests older than {}s",
+                    max_age_secs
+                ))
+            })?;
+        let sent_at = parse_request_timestamp(value).ok_or_else(|| {
+            AppError::InvalidRequest(format!("Invalid {} header: '{}'", header, value))
+        })?;
+
+        let age_secs = (chrono::Utc::now() - sent_at).num_seconds();
+        info!(
+            token_id = token,
+            age_secs, max_age_secs, "Checked webhook timestamp from {} header", header
+        );
+        let limit = i64::from(max_age_secs).saturating_add_unsigned(self.clock_skew_tolerance_secs);
+        if age_secs > limit {
+            return Err(AppError::RequestTooOld {
+                age_secs,
+                max_age_secs,
+            });
+        }
+        Ok(())
+    }
+
+    async fn consume_nonce(&self, token: &str, message: &MessageObject) -> Result<(), AppError> {
+        let nonce = message
+            .headers
+            .get(NONCE_HEADER)
+            .and_then(|values| values.first())
+            .ok_or_else(|| {
+                AppError::InvalidRequest(
+                    "Missing X-Nonce header; this token requires a nonce from POST /api/tokens/{token}/nonces".into(),
+                )
+            })?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let status = self
+            .db
+            .consume_nonce(token, nonce, &now)
+            .await
+            .map_err(|e| AppError::database(format!("checking nonce for token {}", token), e))?;
+        match status {
+            NonceStatus::Accepted => Ok(()),
+            NonceStatus::AlreadyUsed => {
+                warn!("Rejected reused nonce for token {}", token);
+                Err(AppError::NonceAlreadyUsed)
+            }
+            NonceStatus::Unknown => Err(AppError::InvalidRequest(
+                "Unknown or expired X-Nonce".into(),
+            )),
+        }
+    }
+
+    /// Delete nonces past their expiry
+    pub async fn prune_expired_nonces(&self) {
+        let now = chrono::Utc::now().to_rfc3339();
+        match self.db.prune_expired_nonces(&now).await {
+            Ok(pruned) if pruned > 0 => debug!("Pruned {} expired nonces", pruned),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to prune expired nonces: {}", e),
+        }
+    }
+
+    async fn accept_webhook(
+        &self,
+        token: &str,
+        token_info: Option<&TokenInfo>,
+        message: MessageObject,
+        raw_body: &[u8],
+        client_ip: IpAddr,
+        peer_ip: IpAddr,
+    ) -> Result<String, AppError> {
+        let method = message.method.clone();
+
+        // Validate token format (should be a UUID)
+        Uuid::parse_str(token).map_err(|e| {
+            warn!(
+                "Invalid UUID token received in webhook processing: '{}' - {}",
+                token, e
+            );
+            AppError::InvalidToken
+        })?;
+
+        let token_info = token_info.ok_or(AppError::TokenNotFound)?;
+
+        // Enforce the token's allowed HTTP methods
+        let allowed_methods = &token_info.allowed_methods;
+        if !allowed_methods.is_empty() && !allowed_methods.contains(&method) {
+            return Err(AppError::MethodNotAllowed {
+                allowed: allowed_methods.clone(),
+            });
+        }
+
+        // Enforce the token's allowed media types; only GET requests may omit Content-Type
+        if let Some(ref allowed) = token_info.allowed_content_types {
+            let permitted = match message.content_type.as_deref() {
+                Some(content_type) => allowed.contains(&media_type(content_type)),
+                None => method == "GET",
+            };
+            if !permitted {
+                return Err(AppError::UnsupportedMediaType {
+                    allowed: allowed.clone(),
+                });
+            }
+        }
+
+        // Reject webhooks for tokens that have been suspended
+        if token_info.disabled {
+            return Err(AppError::TokenDisabled);
+        }
+
+        // Read-only tokens keep their stored requests but capture no new ones
+        if token_info.readonly {
+            return Err(AppError::TokenReadOnly);
+        }
+
+        // Reject webhooks for tokens past their expiry time
+        if token_expired(token_info) {
+            return Err(AppError::TokenExpired);
+        }
+
+        // Enforce the token's IP allowlist/denylist
+        let ip_rules =
+            self.db.get_ip_rules(token).await.map_err(|e| {
+                AppError::database(format!("getting IP rules for token {}", token), e)
+            })?;
+        if !ip_permitted(&ip_rules, client_ip) {
+            warn!(
+                "Rejected {} request for token {} from {}",
+                method, token, client_ip
+            );
+            return Err(AppError::Forbidden);
+        }
+
+        // Verify the payload signature when the token has a secret configured
+        let secret = self.db.get_webhook_secret(token).await.map_err(|e| {
+            AppError::database(format!("getting webhook secret for token {}", token), e)
+        })?;
+        let signature = message
+            .headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|values| values.first())
+            .map(String::as_str);
+        let signature_valid = match secret {
+            Some(secret) if verify_signature(&secret, signature, raw_body) => {
+                debug!("Signature for token {} matched the current secret", token);
+                Some(true)
+            }
+            Some(_) => {
+                // Senders may still sign with the secret replaced by the last rotation
+                let previous = self
+                    .db
+                    .get_previous_webhook_secret(token)
+                    .await
+                    .map_err(|e| {
+                        AppError::database(
+                            format!("getting previous webhook secret for token {}", token),
+                            e,
+                        )
+                    })?;
+                let valid = previous
+                    .is_some_and(|previous| verify_signature(&previous, signature, raw_body));
+                if valid {
+                    info!("Signature for token {} matched the previous secret", token);
+                }
+                Some(valid)
+            }
+            None => None,
+        };
+
+        // A retried webhook gets the original request's ID instead of being stored again;
+        // requests with a bad signature neither match nor claim a key
+        let idempotency_key = IDEMPOTENCY_HEADERS
+            .iter()
+            .find_map(|name| message.headers.get(*name).and_then(|values| values.first()))
+            .filter(|_| self.idempotency.is_enabled() && signature_valid != Some(false))
+            .cloned();
+        if let Some(ref key) = idempotency_key
+            && let Some(request_id) = self.find_idempotent_request(token, key).await?
+        {
+            info!(
+                token_id = token,
+                request_id = %request_id,
+                method = %method,
+                "Duplicate webhook with idempotency key, returning the original request"
+            );
+            return Ok(request_id);
+        }
+
+        // Opt-in: refuse captured webhooks that are replayed long after they were sent
+        if let Some(max_age_secs) = token_info.max_age_seconds {
+            self.check_request_age(token, &message, max_age_secs)?;
+        }
+
+        // Opt-in: every webhook must use up one of the nonces issued for the token
+        if token_info.nonce_required {
+            self.consume_nonce(token, &message).await?;
+        }
+
+        // Opt-in: an identical body within the token's window is answered with the earlier request
+        if let Some(window_secs) = token_info.deduplicate_window_secs
+            && let Some(ref body_sha256) = message.body_sha256
+            && signature_valid != Some(false)
+        {
+            let since = (chrono::Utc::now() - chrono::Duration::seconds(i64::from(window_secs)))
+                .to_rfc3339();
+            let duplicate = self
+                .db
+                .find_duplicate_request(token, body_sha256, &since)
+                .await
+                .map_err(|e| {
+                    AppError::database(
+                        format!("looking up duplicate request for token {}", token),
+                        e,
+                    )
+                })?;
+            if let Some(request_id) = duplicate {
+                info!(
+                    token_id = token,
+                    request_id = %request_id,
+                    method = %method,
+                    "Duplicate webhook body within the deduplication window, returning the earlier request"
+                );
+                return Ok(request_id);
+            }
+        }
+
+        // Refuse to grow the token's history past its storage cap
+        self.check_storage_limit(token_info).await?;
+
+        // Create webhook request
+        let trace_id = sender_trace_id(&message.headers)
+            .unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()));
+        let geo = self.geo.lookup(client_ip);
+        let user_agent = message
+            .headers
+            .get(header::USER_AGENT.as_str())
+            .and_then(|values| values.first())
+            .map(|user_agent| user_agent::parse(user_agent))
+            .unwrap_or_default();
+        let mut webhook_request = WebhookRequest {
+            id: Uuid::new_v4().to_string(),
+            date: chrono::Utc::now().to_rfc3339(),
+            token_id: token.to_string(),
+            message_object: message,
+            message: None,
+            replayed_from: None,
+            forward_status: None,
+            signature_valid,
+            source_ip: Some(client_ip.to_string()),
+            peer_ip: Some(peer_ip.to_string()),
+            source_country: geo.country_code,
+            source_city: geo.city,
+            source_asn: geo.asn,
+            ua_browser: user_agent.browser,
+            ua_os: user_agent.os,
+            ua_device_type: user_agent.device_type,
+            ua_client: user_agent.client,
+            comment: None,
+            flagged: false,
+            redacted_headers: Vec::new(),
+            trace_id: Some(trace_id),
+        };
+        tracing::Span::current().record("request_id", webhook_request.id.as_str());
+
+        // Sensitive header values are withheld from storage but still forwarded
+        let redact = token_info
+            .redact_headers
+            .as_deref()
+            .unwrap_or(&self.redact_headers);
+        let original_headers = redact_headers(&mut webhook_request, redact);
+
+        // Only the parsed body is rewritten; the raw body is stored as received
+        if let Some(ref mut body_object) = webhook_request.message_object.body_object {
+            Transformer::apply(&token_info.transform_rules, body_object);
+        }
+        let message = &webhook_request.message_object;
+        webhook_request.message = match token_info.message_config {
+            Some(ref extractor) => extractor.extract(message),
+            None => default_message(message),
+        };
+
+        // Rejected requests are still stored so the failed signature can be inspected
+        self.store_request(&webhook_request).await?;
+        self.metrics
+            .record_payload(webhook_request.message_object.body_size_bytes.unwrap_or(0));
+
+        if let Some(ref key) = idempotency_key {
+            self.idempotency.insert(token, key, &webhook_request.id);
+            if let Err(e) = self
+                .db
+                .record_idempotency_key(token, key, &webhook_request.id)
+                .await
+            {
+                warn!("Failed to record idempotency key: {}", e);
+            }
+        }
+
+        if signature_valid == Some(false) {
+            warn!(
+                token_id = token,
+                request_id = %webhook_request.id,
+                method = %method,
+                "Rejected webhook with invalid signature"
+            );
+            return Err(AppError::SignatureVerificationFailed);
+        }
+
+        info!(
+            token_id = token,
+            request_id = %webhook_request.id,
+            method = %method,
+            body_size = webhook_request.message_object.body_size_bytes.unwrap_or(0),
+            "Received webhook"
+        );
+
+        if let Some(headers) = original_headers {
+            webhook_request.message_object.headers = headers;
+        }
+
+        // Notifications about notifications are not sent, in case the URL points back here
+        let is_notification = webhook_request
+            .message_object
+            .headers
+            .contains_key(NOTIFICATION_EVENT_HEADER);
+        if !is_notification && let Some(url) = notification_url(token_info, EVENT_WEBHOOK_RECEIVED)
+        {
+            self.spawn_notify(
+                url.to_string(),
+                EVENT_WEBHOOK_RECEIVED,
+                webhook_request.clone(),
+            );
+        }
+        self.spawn_slack(webhook_request.clone());
+        if !is_notification {
+            self.spawn_email(EVENT_WEBHOOK_RECEIVED, webhook_request.clone());
+        }
+
+        // Forward in the background so the sender gets its response right away
+        if let Some(ref forward_url) = token_info.forward_url {
+            self.spawn_forward(webhook_request.clone(), forward_url.clone(), token_info);
+        }
+        match self.db.list_forward_targets(token).await {
+            Ok(targets) => {
+                let targets = self.forwarding.select_targets(token, targets);
+                if !targets.is_empty() {
+                    self.spawn_mirror(webhook_request.clone(), targets);
+                }
+            }
+            Err(e) => warn!("Failed to get forward targets for token {}: {}", token, e),
+        }
+
+        Ok(webhook_request.id)
+    }
+
+    /// Look up an idempotency key in memory, then in the database (e.g. after a restart)
+    async fn find_idempotent_request(
+        &self,
+        token: &str,
+        key: &str,
+    ) -> Result<Option<String>, AppError> {
+        if let Some(request_id) = self.idempotency.get(token, key) {
+            return Ok(Some(request_id));
+        }
+
+        let since = (chrono::Utc::now() - self.idempotency.window).to_rfc3339();
+        let request_id = self
+            .db
+            .find_idempotent_request(token, key, &since)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("looking up idempotency key for token {}", token), e)
+            })?;
+        if let Some(ref request_id) = request_id {
+            self.idempotency.insert(token, key, request_id);
+        }
+        Ok(request_id)
+    }
+
+    /// Forget idempotency keys that have left the window, in memory and in the database
+    pub async fn prune_idempotency_keys(&self) {
+        self.idempotency.cleanup();
+
+        let before = (chrono::Utc::now() - self.idempotency.window).to_rfc3339();
+        if let Err(e) = self.db.prune_idempotency_keys(&before).await {
+            warn!("Failed to prune idempotency keys: {}", e);
+        }
+    }
+
+    async fn check_storage_limit(&self, token_info: &TokenInfo) -> Result<(), AppError> {
+        let token = token_info.token.as_str();
+        let Some(limit) = token_info.max_requests.or(self.default_max_requests) else {
+            return Ok(());
+        };
+
+        let count =
+            self.db.count_requests_by_token(token).await.map_err(|e| {
+                AppError::database(format!("counting requests of token {}", token), e)
+            })?;
+        if count >= i64::from(limit) {
+            warn!(
+                "Token {} has reached its limit of {} requests",
+                token, limit
+            );
+            return Err(AppError::StorageLimitExceeded { limit });
+        }
+        Ok(())
+    }
+
+    /// Deliver a stored request to the forward URL without blocking the caller, notifying
+    /// `failure_notification_url` if delivery fails
+    fn spawn_forward(
+        &self,
+        webhook_request: WebhookRequest,
+        forward_url: String,
+        token_info: &TokenInfo,
+    ) {
+        // Notifications about notifications are not sent, in case the URL points back here
+        let is_notification = webhook_request
+            .message_object
+            .headers
+            .contains_key(NOTIFICATION_EVENT_HEADER);
+        let subscribed_url = |event| {
+            notification_url(token_info, event)
+                .filter(|_| !is_notification)
+                .map(str::to_string)
+        };
+        let failure_notification_url = subscribed_url(EVENT_FORWARD_FAILED);
+        let dead_letter_notification_url = subscribed_url(EVENT_DELIVERY_FAILED);
+
+        let forwarding = self.forwarding.clone();
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move {
+            let result = forwarding.deliver(&webhook_request, &forward_url).await;
+            let failed = !matches!(result, Ok(status) if status < 400);
+            if failed && let Some(url) = failure_notification_url {
+                forwarding
+                    .notify(&url, EVENT_FORWARD_FAILED, &webhook_request)
+                    .await;
+            }
+            if result.is_err()
+                && let Some(url) = dead_letter_notification_url
+            {
+                forwarding
+                    .notify(&url, EVENT_DELIVERY_FAILED, &webhook_request)
+                    .await;
+            }
+            if !is_notification && failed {
+                notifications
+                    .notify_email(EVENT_FORWARD_FAILED, &webhook_request)
+                    .await;
+            }
+            if !is_notification && result.is_err() {
+                notifications
+                    .notify_email(EVENT_DELIVERY_FAILED, &webhook_request)
+                    .await;
+            }
+        });
+    }
+
+    fn spawn_slack(&self, webhook_request: WebhookRequest) {
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move {
+            notifications.notify_slack(&webhook_request).await;
+        });
+    }
+
+    /// Email the token about a stored request without blocking the caller
+    fn spawn_email(&self, event: &'static str, webhook_request: WebhookRequest) {
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move {
+            notifications.notify_email(event, &webhook_request).await;
+        });
+    }
+
+    /// Send a notification about a stored request without blocking the caller
+    fn spawn_notify(&self, url: String, event: &'static str, webhook_request: WebhookRequest) {
+        let forwarding = self.forwarding.clone();
+        tokio::spawn(async move {
+            forwarding.notify(&url, event, &webhook_request).await;
+        });
+    }
+
+    /// Deliver a stored request to the token's mirror targets without blocking the caller
+    fn spawn_mirror(&self, webhook_request: WebhookRequest, targets: Vec<ForwardTarget>) {
+        let forwarding = self.forwarding.clone();
+        tokio::spawn(async move {
+            forwarding.mirror(Arc::new(webhook_request), targets).await;
+        });
+    }
+
+    pub async fn get_request(
+        &self,
+        token: &str,
+        request_id: &str,
+    ) -> Result<WebhookRequest, AppError> {
+        self.db
+            .get_request_by_id(token, request_id)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("getting webhook request for token {}", token), e)
+            })?
+            .ok_or(AppError::RequestNotFound)
+    }
+
+    pub async fn token_stats(&self, token: &str) -> Result<TokenStats, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        self.db
+            .get_token_size_stats(token)
+            .await
+            .map_err(|e| AppError::database(format!("getting token stats for token {}", token), e))
+    }
+
+    /// Request counts per hour and method over the last `days` days
+    pub async fn hourly_volume(
+        &self,
+        token: &str,
+        days: u32,
+    ) -> Result<Vec<HourlyBucket>, AppError> {
+        if days == 0 || days > MAX_VOLUME_DAYS {
+            return Err(AppError::InvalidRequest(format!(
+                "days must be between 1 and {}",
+                MAX_VOLUME_DAYS
+            )));
+        }
+        self.ensure_token_exists(token).await?;
+
+        let since = (chrono::Utc::now() - chrono::TimeDelta::days(i64::from(days))).to_rfc3339();
+        self.db.get_hourly_stats(token, &since).await.map_err(|e| {
+            AppError::database(
+                format!("getting hourly request volume for token {}", token),
+                e,
+            )
+        })
+    }
+
+    /// Average request count for each hour of the week over the last `weeks` weeks
+    pub async fn heatmap(&self, token: &str, weeks: u32) -> Result<Heatmap, AppError> {
+        if weeks == 0 || weeks > MAX_HEATMAP_WEEKS {
+            return Err(AppError::InvalidRequest(format!(
+                "weeks must be between 1 and {}",
+                MAX_HEATMAP_WEEKS
+            )));
+        }
+        self.ensure_token_exists(token).await?;
+
+        let counted = self.db.get_heatmap_data(token, weeks).await.map_err(|e| {
+            AppError::database(format!("getting heatmap data for token {}", token), e)
+        })?;
+        let mut cells: Vec<HeatmapCell> = (0..7)
+            .flat_map(|dow| {
+                (0..24).map(move |hour| HeatmapCell {
+                    dow,
+                    hour,
+                    avg_count: 0.0,
+                })
+            })
+            .collect();
+        for cell in counted {
+            let index = usize::from(cell.dow) * 24 + usize::from(cell.hour);
+            if let Some(slot) = cells.get_mut(index) {
+                *slot = cell;
+            }
+        }
+        Ok(Heatmap { cells })
+    }
+
+    /// Bodies the token received more than once in the last `window_minutes`, most repeated
+    /// first; retries from senders without idempotency show up here
+    pub async fn duplicate_requests(
+        &self,
+        token: &str,
+        window_minutes: u32,
+    ) -> Result<Vec<DuplicateGroup>, AppError> {
+        if window_minutes == 0 || window_minutes > MAX_DUPLICATES_WINDOW_MINUTES {
+            return Err(AppError::InvalidRequest(format!(
+                "window_minutes must be between 1 and {}",
+                MAX_DUPLICATES_WINDOW_MINUTES
+            )));
+        }
+        self.ensure_token_exists(token).await?;
+
+        let window = Duration::from_secs(u64::from(window_minutes) * 60);
+        self.db
+            .find_duplicate_requests(token, window)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("finding duplicate requests for token {}", token), e)
+            })
+    }
+
+    /// Where the token's requests came from, as request counts per country
+    pub async fn geo_stats(&self, token: &str) -> Result<Vec<CountryCount>, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        self.db.get_country_counts(token).await.map_err(|e| {
+            AppError::database(format!("counting request countries for token {}", token), e)
+        })
+    }
+
+    /// Who sent the token's requests, as request counts per parsed user agent
+    pub async fn user_agent_stats(&self, token: &str) -> Result<Vec<UserAgentCount>, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        self.db.get_user_agent_counts(token).await.map_err(|e| {
+            AppError::database(
+                format!("counting request user agents for token {}", token),
+                e,
+            )
+        })
+    }
+
+    /// A JSON Schema describing the token's most recent request bodies
+    pub async fn infer_schema(&self, token: &str) -> Result<serde_json::Value, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        let samples = self
+            .db
+            .get_recent_body_objects(token, SCHEMA_SAMPLE_SIZE)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("getting request bodies for token {}", token), e)
+            })?;
+        let mut inferrer = SchemaInferrer::new();
+        for sample in &samples {
+            inferrer.add(sample);
+        }
+        Ok(inferrer.schema())
+    }
+
+    /// The `n` most frequently sent header names with the number of requests carrying them
+    pub async fn top_headers(&self, token: &str, n: usize) -> Result<Vec<HeaderCount>, AppError> {
+        if n == 0 || n > MAX_TOP_HEADERS {
+            return Err(AppError::InvalidRequest(format!(
+                "n must be between 1 and {}",
+                MAX_TOP_HEADERS
+            )));
+        }
+        self.ensure_token_exists(token).await?;
+
+        self.db
+            .get_top_headers(token, n as i64)
+            .await
+            .map_err(|e| AppError::database(format!("getting top headers for token {}", token), e))
+    }
+
+    pub async fn list_dead_letters(&self, token: &str) -> Result<Vec<DeadLetter>, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        self.db
+            .list_dead_letters(token)
+            .await
+            .map_err(|e| AppError::database(format!("listing dead letters for token {}", token), e))
+    }
+
+    /// Take a request out of the dead letter queue and deliver it again with a fresh set of retries
+    pub async fn requeue_dead_letter(&self, token: &str, id: &str) -> Result<DeadLetter, AppError> {
+        let dead_letter = self
+            .db
+            .get_dead_letter(token, id)
+            .await
+            .map_err(|e| AppError::database(format!("getting dead letter for token {}", token), e))?
+            .ok_or(AppError::DeadLetterNotFound)?;
+        let token_info = self
+            .db
+            .get_token(token)
+            .await
+            .map_err(|e| AppError::database(format!("getting token {}", token), e))?
+            .ok_or(AppError::TokenNotFound)?;
+        let mut webhook_request = self.get_request(token, &dead_letter.request_id).await?;
+
+        self.delete_dead_letter(token, id).await?;
+
+        // Redacted values are placeholders, so those headers are left out rather than sent
+        for name in &webhook_request.redacted_headers {
+            webhook_request.message_object.headers.remove(name);
+        }
+        self.spawn_forward(
+            webhook_request,
+            dead_letter.forward_url.clone(),
+            &token_info,
+        );
+
+        info!(
+            "Requeued request {} for token {} to {}",
+            dead_letter.request_id, token, dead_letter.forward_url
+        );
+        audit::record(
+            self.db.as_ref(),
+            "dead_letter.requeue",
+            Some(token),
+            Some(serde_json::json!({
+                "dead_letter_id": dead_letter.id,
+                "request_id": dead_letter.request_id,
+            })),
+        )
+        .await;
+        Ok(dead_letter)
+    }
+
+    /// Acknowledge a dead letter, giving up on its delivery
+    pub async fn discard_dead_letter(&self, token: &str, id: &str) -> Result<(), AppError> {
+        self.delete_dead_letter(token, id).await?;
+
+        info!("Discarded dead letter {} for token {}", id, token);
+        audit::record(
+            self.db.as_ref(),
+            "dead_letter.discard",
+            Some(token),
+            Some(serde_json::json!({ "dead_letter_id": id })),
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn delete_dead_letter(&self, token: &str, id: &str) -> Result<(), AppError> {
+        let deleted = self.db.delete_dead_letter(token, id).await.map_err(|e| {
+            AppError::database(format!("deleting dead letter for token {}", token), e)
+        })?;
+        if !deleted {
+            return Err(AppError::DeadLetterNotFound);
+        }
+        Ok(())
+    }
+
+    pub async fn list_forward_attempts(
+        &self,
+        token: &str,
+        request_id: &str,
+    ) -> Result<Vec<ForwardAttempt>, AppError> {
+        // Make sure the request belongs to this token before exposing its history
+        self.get_request(token, request_id).await?;
+
+        self.db.get_forward_attempts(request_id).await.map_err(|e| {
+            AppError::database(format!("getting forward attempts for token {}", token), e)
+        })
+    }
+
+    /// Count a webhook against the token's rate limit. Checked before the token is looked up,
+    /// so throttled senders cause no database work
+    pub fn check_rate_limit(&self, token: &str, method: &str) -> Result<(), AppError> {
+        self.rate_limiter.check(token).inspect_err(|e| {
+            self.metrics
+                .record_webhook(token, method, e.status_code().as_u16())
+        })
+    }
+
+    /// The token a webhook was sent to, or `None` if there is no such token. Read once per
+    /// request; every per-token setting the webhook needs comes from it
+    pub async fn webhook_token(&self, token: &str) -> Result<Option<TokenInfo>, AppError> {
+        if let Some(token_info) = self.token_cache.get(token) {
+            return Ok(token_info);
+        }
+
+        let token_info = self
+            .db
+            .get_token(token)
+            .await
+            .map_err(|e| AppError::database(format!("getting token {}", token), e))?;
+        self.token_cache.insert(token, token_info.clone());
+        Ok(token_info)
+    }
+
+    /// The `Access-Control-Allow-Origin` value for a browser request from `origin`, if the token allows it
+    pub fn cors_allow_origin(&self, token_info: &TokenInfo, origin: &str) -> Option<String> {
+        let origins = &token_info.cors_origins;
+        if origins.iter().any(|allowed| allowed == "*") {
+            Some("*".to_string())
+        } else {
+            origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+                .then(|| origin.to_string())
+        }
+    }
+
+    /// Largest webhook body the token accepts: its own `max_payload_bytes`, else the server limit
+    pub fn max_payload_bytes(&self, token_info: Option<&TokenInfo>) -> usize {
+        token_info
+            .and_then(|token_info| token_info.max_payload_bytes)
+            .map_or(self.max_payload_bytes, |limit| limit as usize)
+    }
+
+    /// The mock response configured for the token, if any. Only tokens known to have one
+    /// cost a database lookup
+    pub async fn custom_response(
+        &self,
+        token_info: Option<&TokenInfo>,
+    ) -> Result<Option<CustomResponse>, AppError> {
+        let Some(token_info) = token_info.filter(|token_info| token_info.has_custom_response)
+        else {
+            return Ok(None);
+        };
+        let token = token_info.token.as_str();
+        self.db.get_custom_response(token).await.map_err(|e| {
+            AppError::database(format!("getting custom response for token {}", token), e)
+        })
+    }
+
+    /// How long to hold back the response to the token's webhooks, capped by the server limit
+    pub fn response_delay(&self, token_info: Option<&TokenInfo>) -> Duration {
+        let delay_ms = token_info
+            .and_then(|token_info| token_info.response_delay_ms)
+            .unwrap_or(0)
+            .min(self.max_response_delay_ms);
+        Duration::from_millis(u64::from(delay_ms))
+    }
+
+    /// Persist a request and notify live subscribers
+    async fn store_request(&self, webhook_request: &WebhookRequest) -> Result<(), AppError> {
+        self.db
+            .store_webhook_request(webhook_request)
+            .await
+            .map_err(|e| {
+                AppError::database(
+                    format!(
+                        "storing webhook request for token {}",
+                        webhook_request.token_id
+                    ),
+                    e,
+                )
+            })?;
+
+        self.broadcaster.publish(webhook_request);
+        Ok(())
+    }
+
+    /// Re-send a stored request to the token's forward URL and store the replay as a new request
+    pub async fn replay_request(
+        &self,
+        token: &str,
+        request_id: &str,
+    ) -> Result<ReplayResult, AppError> {
+        let token_info = self
+            .db
+            .get_token(token)
+            .await
+            .map_err(|e| AppError::database(format!("getting token {}", token), e))?
+            .ok_or(AppError::TokenNotFound)?;
+        let forward_url = token_info
+            .forward_url
+            .ok_or(AppError::ForwardUrlNotConfigured)?;
+
+        let original = self
+            .db
+            .get_request_by_id(token, request_id)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("getting webhook request for token {}", token), e)
+            })?
+            .ok_or(AppError::RequestNotFound)?;
+
+        // Redacted values are placeholders, so those headers are left out rather than replayed
+        let mut message = Cow::Borrowed(&original.message_object);
+        if !original.redacted_headers.is_empty() {
+            let headers = &mut message.to_mut().headers;
+            for name in &original.redacted_headers {
+                headers.remove(name);
+            }
+        }
+        let response = self
+            .forwarding
+            .send(&forward_url, &original, &message)
+            .await
+            .inspect_err(|e| {
+                warn!(
+                    "Failed to replay request {} to {}: {}",
+                    request_id, forward_url, e
+                )
+            })?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(|e| {
+            warn!("Failed to read replay response from {}: {}", forward_url, e);
+            AppError::ForwardFailed(e.to_string())
+        })?;
+
+        let replayed = WebhookRequest {
+            id: Uuid::new_v4().to_string(),
+            date: chrono::Utc::now().to_rfc3339(),
+            token_id: token.to_string(),
+            message_object: original.message_object,
+            message: original.message,
+            replayed_from: Some(original.id),
+            forward_status: Some(status),
+            signature_valid: None,
+            source_ip: original.source_ip,
+            peer_ip: original.peer_ip,
+            source_country: original.source_country,
+            source_city: original.source_city,
+            source_asn: original.source_asn,
+            ua_browser: original.ua_browser,
+            ua_os: original.ua_os,
+            ua_device_type: original.ua_device_type,
+            ua_client: original.ua_client,
+            comment: None,
+            flagged: false,
+            redacted_headers: original.redacted_headers,
+            trace_id: original.trace_id,
+        };
+        self.store_request(&replayed).await?;
+
+        info!(
+            "Replayed request {} for token {} to {} (status {})",
+            request_id, token, forward_url, status
+        );
+        audit::record(
+            self.db.as_ref(),
+            "request.replay",
+            Some(token),
+            Some(serde_json::json!({ "request_id": request_id, "replay_id": replayed.id })),
+        )
+        .await;
+
+        Ok(ReplayResult {
+            request_id: replayed.id,
+            status,
+            body,
+        })
+    }
+
+    /// Subscribe to webhook requests stored for the given token from now on
+    pub async fn subscribe(
+        &self,
+        token: &str,
+    ) -> Result<broadcast::Receiver<WebhookRequest>, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        Ok(self.broadcaster.subscribe(token))
+    }
+
+    async fn ensure_token_exists(&self, token: &str) -> Result<(), AppError> {
+        Uuid::parse_str(token).map_err(|_| AppError::InvalidToken)?;
+
+        if !self
+            .db
+            .token_exists(token)
+            .await
+            .map_err(|e| AppError::database(format!("checking if token {} exists", token), e))?
+        {
+            return Err(AppError::TokenNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_webhook_logs(
+        &self,
+        token: &str,
+        count: u32,
+    ) -> Result<Vec<WebhookRequest>, AppError> {
+        let count = count.min(MAX_PAGE_SIZE);
+        let requests = self
+            .db
+            .get_webhook_requests(token, count)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("getting webhook requests for token {}", token), e)
+            })?;
+        Ok(requests)
+    }
+
+    /// Set (or remove, with `None` or a blank string) the comment of a stored request
+    pub async fn update_request_comment(
+        &self,
+        token: &str,
+        request_id: &str,
+        comment: Option<String>,
+    ) -> Result<WebhookRequest, AppError> {
+        let comment = comment
+            .map(|comment| comment.trim().to_string())
+            .filter(|comment| !comment.is_empty());
+        if comment
+            .as_ref()
+            .is_some_and(|comment| comment.chars().count() > MAX_COMMENT_LENGTH)
+        {
+            return Err(AppError::InvalidRequest(format!(
+                "comment must be at most {} characters",
+                MAX_COMMENT_LENGTH
+            )));
+        }
+
+        let request = self
+            .db
+            .update_request_comment(token, request_id, comment.as_deref())
+            .await
+            .map_err(|e| {
+                AppError::database(format!("updating request comment for token {}", token), e)
+            })?
+            .ok_or(AppError::RequestNotFound)?;
+
+        info!(
+            "Updated comment of request {} for token {}",
+            request_id, token
+        );
+        audit::record(
+            self.db.as_ref(),
+            "request.comment",
+            Some(token),
+            Some(serde_json::json!({ "request_id": request_id, "comment": request.comment })),
+        )
+        .await;
+        Ok(request)
+    }
+
+    /// Flag a stored request for follow-up, or clear its flag
+    pub async fn set_request_flag(
+        &self,
+        token: &str,
+        request_id: &str,
+        flagged: bool,
+    ) -> Result<WebhookRequest, AppError> {
+        let request = self
+            .db
+            .set_request_flag(token, request_id, flagged)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("updating request flag for token {}", token), e)
+            })?
+            .ok_or(AppError::RequestNotFound)?;
+
+        info!(
+            "{} request {} for token {}",
+            if flagged { "Flagged" } else { "Unflagged" },
+            request_id,
+            token
+        );
+        audit::record(
+            self.db.as_ref(),
+            if flagged {
+                "request.flag"
+            } else {
+                "request.unflag"
+            },
+            Some(token),
+            Some(serde_json::json!({ "request_id": request_id })),
+        )
+        .await;
+        Ok(request)
+    }
+
+    /// Unflag all of the token's requests at once, returning how many were flagged
+    pub async fn clear_request_flags(&self, token: &str) -> Result<u64, AppError> {
+        self.ensure_token_exists(token).await?;
+        let unflagged = self.db.clear_request_flags(token).await.map_err(|e| {
+            AppError::database(format!("clearing request flags for token {}", token), e)
+        })?;
+
+        info!("Unflagged {} requests for token {}", unflagged, token);
+        audit::record(
+            self.db.as_ref(),
+            "request.unflag_all",
+            Some(token),
+            Some(serde_json::json!({ "unflagged": unflagged })),
+        )
+        .await;
+        Ok(unflagged)
+    }
+
+    /// Delete a single stored request, scoped to the token it was received on
+    pub async fn delete_request(&self, token: &str, request_id: &str) -> Result<(), AppError> {
+        let deleted = self
+            .db
+            .delete_webhook_request(token, request_id)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("deleting webhook request for token {}", token), e)
+            })?;
+        if !deleted {
+            return Err(AppError::RequestNotFound);
+        }
+
+        info!("Deleted webhook request {} for token {}", request_id, token);
+        audit::record(
+            self.db.as_ref(),
+            "request.delete",
+            Some(token),
+            Some(serde_json::json!({ "request_id": request_id })),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Stream the token's requests as CSV, newest first
+    pub async fn export_csv(
+        &self,
+        token: &str,
+        include_body: bool,
+    ) -> Result<ReceiverStream<Result<Vec<u8>, std::io::Error>>, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        let header = export::csv_header(include_body).map_err(|e| {
+            warn!("Failed to write the CSV header: {}", e);
+            AppError::internal()
+        })?;
+        Ok(self.stream_export(
+            token,
+            WebhookRequestFilter::default(),
+            header,
+            b"",
+            b"",
+            move |request| Ok(export::csv_record(request, include_body)?),
+        ))
+    }
+
+    /// Stream the token's matching requests as newline-delimited JSON, most recent first
+    pub async fn export_ndjson(
+        &self,
+        token: &str,
+        filter: WebhookRequestFilter,
+    ) -> Result<ReceiverStream<Result<Vec<u8>, std::io::Error>>, AppError> {
+        check_query_filter(&filter)?;
+        self.ensure_token_exists(token).await?;
+
+        Ok(
+            self.stream_export(token, filter, Vec::new(), b"", b"", |request| {
+                let mut line = serde_json::to_vec(request)?;
+                line.push(b'\n');
+                Ok(line)
+            }),
+        )
+    }
+
+    /// Stream all of the token's requests as an HTTP Archive (HAR 1.2) document
+    pub async fn export_har(
+        &self,
+        token: &str,
+        base_url: Option<String>,
+    ) -> Result<ReceiverStream<Result<Vec<u8>, std::io::Error>>, AppError> {
+        self.ensure_token_exists(token).await?;
+
+        Ok(self.stream_export(
+            token,
+            WebhookRequestFilter::default(),
+            export::HAR_HEADER.as_bytes().to_vec(),
+            b",",
+            export::HAR_FOOTER.as_bytes(),
+            move |request| {
+                let url = original_url(&base_url, request);
+                Ok(serde_json::to_vec(&export::HarEntry::new(request, &url))?)
+            },
+        ))
+    }
+
+    /// Stream matching requests rendered one by one, wrapped in `prefix` and `suffix`
+    fn stream_export<F>(
+        &self,
+        token: &str,
+        filter: WebhookRequestFilter,
+        prefix: Vec<u8>,
+        separator: &'static [u8],
+        suffix: &'static [u8],
+        render: F,
+    ) -> ReceiverStream<Result<Vec<u8>, std::io::Error>>
+    where
+        F: Fn(&WebhookRequest) -> anyhow::Result<Vec<u8>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(2);
+        let db = self.db.clone();
+        let token = token.to_string();
+        let mut chunk = prefix;
+
+        tokio::spawn(async move {
+            let mut requests = db.stream_webhook_requests(&token, &filter);
+            let mut rendered = 0;
+
+            while let Some(request) = requests.next().await {
+                match request.and_then(|request| render(&request)) {
+                    Ok(record) => {
+                        if rendered > 0 {
+                            chunk.extend_from_slice(separator);
+                        }
+                        chunk.extend_from_slice(&record);
+                        rendered += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to export webhook requests: {}", e);
+                        let _ = sender.send(Err(std::io::Error::other(e.to_string()))).await;
+                        return;
+                    }
+                }
+
+                if rendered % EXPORT_BATCH_SIZE == 0
+                    && sender.send(Ok(std::mem::take(&mut chunk))).await.is_err()
+                {
+                    // The client has gone away
+                    return;
+                }
+            }
+
+            chunk.extend_from_slice(suffix);
+            if !chunk.is_empty() {
+                let _ = sender.send(Ok(chunk)).await;
+            }
+        });
+
+        ReceiverStream::new(receiver)
+    }
+
+    /// Search the token's request bodies, URLs and messages for `query`
+    pub async fn search_request_bodies(
+        &self,
+        token: &str,
+        query: &str,
+        limit: Option<u32>,
+    ) -> Result<RequestSearchResults, AppError> {
+        if query.trim().is_empty() {
+            return Err(AppError::InvalidRequest("q must not be empty".into()));
+        }
+        self.ensure_token_exists(token).await?;
+
+        let limit = limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_SEARCH_RESULTS);
+        let requests = self
+            .db
+            .search_requests(token, query, limit)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("searching webhook requests for token {}", token), e)
+            })?;
+
+        let requests = requests
+            .into_iter()
+            .map(|request| RequestSearchHit {
+                match_field: search_match_field(&request, query),
+                request,
+            })
+            .collect();
+        Ok(RequestSearchResults { requests })
+    }
+
+    /// Fetch a page of matching requests older than `before_id` (or the newest page if `None`)
+    pub async fn search_requests(
+        &self,
+        token: &str,
+        filter: &WebhookRequestFilter,
+        limit: Option<u32>,
+        before_id: Option<&str>,
+    ) -> Result<RequestPage, AppError> {
+        check_query_filter(filter)?;
+        self.ensure_token_exists(token).await?;
+
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let requests = self
+            .db
+            .get_requests_filtered(token, filter, limit, before_id)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("getting webhook requests for token {}", token), e)
+            })?;
+
+        // A short page means there is nothing older left to fetch
+        let next_cursor = if requests.len() == limit as usize {
+            requests.last().map(|r| r.id.clone())
+        } else {
+            None
+        };
+
+        Ok(RequestPage {
+            requests,
+            next_cursor,
+        })
+    }
+
+    /// Weak ETag of the token's request log as listed for `query`, `W/"<hash>"`. It changes
+    /// whenever one of the token's requests is stored, changed or deleted
+    pub async fn requests_etag(
+        &self,
+        token: &str,
+        query: Option<&str>,
+    ) -> Result<String, AppError> {
+        Uuid::parse_str(token).map_err(|_| AppError::InvalidToken)?;
+        let version = self
+            .db
+            .get_requests_version(token)
+            .await
+            .map_err(|e| {
+                AppError::database(format!("getting requests version of token {}", token), e)
+            })?
+            .ok_or(AppError::TokenNotFound)?;
+
+        // The same parameters in another order list the same requests
+        let mut params: Vec<_> =
+            url::form_urlencoded::parse(query.unwrap_or_default().as_bytes()).collect();
+        params.sort();
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+
+        let hash = Sha256::digest(format!("{}\n{}", version, query));
+        Ok(format!("W/\"{}\"", hex::encode(hash)))
+    }
+}
+
+#[derive(Clone)]
+pub struct TokenService {
+    db: Arc<dyn DatabaseBackend>,
+    base_url: Option<String>,
+    metrics: Arc<Metrics>,
+    token_cache: TokenCache,
+    circuit_breaker: CircuitBreaker,
+    secret_rotation_grace: Duration,
+    /// Recent token creations per client IP, shared by every handler creating tokens
+    token_creations: Arc<DashMap<IpAddr, VecDeque<Instant>>>,
+    token_create_limit: usize,
+    token_create_window: Duration,
+    /// Tokens the instance may hold in total; 0 means unlimited
+    max_tokens: u32,
+    service_region: Option<String>,
+    notifications: NotificationService,
+}
+
+impl TokenService {
+    pub fn new(
+        db: Arc<dyn DatabaseBackend>,
+        config: &Config,
+        metrics: Arc<Metrics>,
+        token_cache: TokenCache,
+        circuit_breaker: CircuitBreaker,
+        token_creations: Arc<DashMap<IpAddr, VecDeque<Instant>>>,
+        notifications: NotificationService,
+    ) -> Self {
+        Self {
+            db,
+            base_url: config.base_url.clone(),
+            metrics,
+            token_cache,
+            circuit_breaker,
+            secret_rotation_grace: Duration::from_secs(config.secret_rotation_grace_secs),
+            token_creations,
+            token_create_limit: config.ip_token_create_limit as usize,
+            token_create_window: Duration::from_secs(config.ip_token_create_window_secs),
+            max_tokens: config.max_tokens,
+            service_region: config.service_region.clone(),
+            notifications,
+        }
+    }
+
+    pub async fn create_token(
+        &self,
+        client_ip: IpAddr,
+        headers: &HeaderMap,
+        label: Option<String>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<TokenInfo, AppError> {
+        self.create_token_with_id(Uuid::new_v4(), client_ip, headers, label, ttl_seconds)
+            .await
+    }
+
+    /// Create a token with a given UUID. Callers supplying their own ID are responsible
+    /// for it being unique and hard to guess, as anyone who knows it can send webhooks
+    pub async fn create_token_with_id(
+        &self,
+        id: Uuid,
+        client_ip: IpAddr,
+        headers: &HeaderMap,
+        label: Option<String>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<TokenInfo, AppError> {
+        let created_at = chrono::Utc::now();
+        let expires_at = expiry_from_ttl(created_at, ttl_seconds)?;
+        let token_info =
+            self.new_token_info(id, headers, created_at, expires_at, normalize_label(label)?);
+        self.check_token_create_limit(client_ip, 1)?;
+        self.check_max_tokens(1).await?;
+
+        self.db.create_token(&token_info).await.map_err(|e| {
+            if is_unique_violation(&e) {
+                return AppError::TokenAlreadyExists;
+            }
+            warn!("Failed to create token: {}", e);
+            AppError::internal()
+        })?;
+
+        self.metrics.record_token_created();
+        self.token_cache
+            .insert(&token_info.token, Some(token_info.clone()));
+
+        info!("Created new token: {}", token_info.token);
+        audit::record(
+            self.db.as_ref(),
+            "token.create",
+            Some(&token_info.token),
+            Some(serde_json::json!({
+                "label": token_info.label,
+                "expires_at": token_info.expires_at,
+            })),
+        )
+        .await;
+        Ok(token_info)
+    }
+
+    /// Create a token with the configuration of `source_token` but none of its requests.
+    /// The webhook secret and read-only key are not copied and must be set up again.
+    pub async fn clone_token(
+        &self,
+        source_token: &str,
+        client_ip: IpAddr,
+        headers: &HeaderMap,
+    ) -> Result<TokenInfo, AppError> {
+        let token_info =
+            self.new_token_info(Uuid::new_v4(), headers, chrono::Utc::now(), None, None);
+        self.check_token_create_limit(client_ip, 1)?;
+        self.check_max_tokens(1).await?;
+
+        let cloned = self
+            .db
+            .clone_token(source_token, &token_info)
+            .await
+            .map_err(|e| {
+                warn!("Failed to clone token: {}", e);
+                AppError::internal()
+            })?;
+        if !cloned {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.metrics.record_token_created();
+        // The clone copies settings in the database, so it is looked up on first use
+        self.token_cache.invalidate(&token_info.token);
+
+        info!("Cloned token {} as {}", source_token, token_info.token);
+        audit::record(
+            self.db.as_ref(),
+            "token.clone",
+            Some(&token_info.token),
+            Some(serde_json::json!({ "source": source_token })),
+        )
+        .await;
+        self.get_token(&token_info.token).await
+    }
+
+    /// Count `count` token creations for the client IP, rejecting all of them unless they fit
+    /// in the window
+    fn check_token_create_limit(&self, client_ip: IpAddr, count: usize) -> Result<(), AppError> {
+        if self.token_create_limit == 0 {
+            return Ok(());
+        }
+        if count > self.token_create_limit {
+            return Err(AppError::InvalidRequest(format!(
+                "count must be at most {}, the number of tokens one IP may create per window",
+                self.token_create_limit
+            )));
+        }
+
+        // The entry stays locked until the creation is counted, so parallel requests
+        // from one IP cannot all pass the check
+        let mut timestamps = self.token_creations.entry(client_ip).or_default();
+        check_window(
+            &mut timestamps,
+            count,
+            self.token_create_limit,
+            self.token_create_window,
+        )
+        .inspect_err(|_| warn!("Token creation limit reached for {}", client_ip))
+    }
+
+    /// Reject creating `new_tokens` more tokens when they would exceed `MAX_TOKENS`
+    async fn check_max_tokens(&self, new_tokens: u32) -> Result<(), AppError> {
+        if self.max_tokens == 0 {
+            return Ok(());
+        }
+
+        let count = self.db.count_tokens().await.map_err(|e| {
+            warn!("Failed to count tokens: {}", e);
+            AppError::internal()
+        })?;
+        if count + i64::from(new_tokens) > i64::from(self.max_tokens) {
+            return Err(AppError::MaxTokensReached {
+                count,
+                limit: self.max_tokens,
+            });
+        }
+        Ok(())
+    }
+
+    /// Drop IPs with no token creations inside the current window
+    pub fn cleanup_token_creations(&self) {
+        let now = Instant::now();
+        self.token_creations.retain(|_, timestamps| {
+            timestamps
+                .back()
+                .is_some_and(|&t| now.duration_since(t) < self.token_create_window)
+        });
+    }
+
+    /// Create `count` tokens at once, labelled `<label_prefix>1` to `<label_prefix><count>`
+    pub async fn create_tokens_batch(
+        &self,
+        client_ip: IpAddr,
+        headers: &HeaderMap,
+        count: u32,
+        label_prefix: Option<&str>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<Vec<TokenInfo>, AppError> {
+        if count == 0 || count > MAX_TOKEN_BATCH_SIZE {
+            return Err(AppError::InvalidRequest(format!(
+                "count must be between 1 and {}",
+                MAX_TOKEN_BATCH_SIZE
+            )));
+        }
+
+        let created_at = chrono::Utc::now();
+        let expires_at = expiry_from_ttl(created_at, ttl_seconds)?;
+        let tokens = (1..=count)
+            .map(|index| {
+                let label = label_prefix
+                    .map(|prefix| normalize_label(Some(format!("{}{}", prefix, index))))
+                    .transpose()?
+                    .flatten();
+                Ok(self.new_token_info(
+                    Uuid::new_v4(),
+                    headers,
+                    created_at,
+                    expires_at.clone(),
+                    label,
+                ))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        self.check_token_create_limit(client_ip, tokens.len())?;
+        self.check_max_tokens(count).await?;
+
+        self.db.create_tokens(&tokens).await.map_err(|e| {
+            warn!("Failed to create tokens: {}", e);
+            AppError::internal()
+        })?;
+
+        for token_info in &tokens {
+            self.metrics.record_token_created();
+            self.token_cache
+                .insert(&token_info.token, Some(token_info.clone()));
+        }
+
+        info!("Created {} new tokens", tokens.len());
+        audit::record_many(
+            self.db.as_ref(),
+            "token.create",
+            tokens.iter().map(|token_info| {
+                (
+                    Some(token_info.token.as_str()),
+                    Some(serde_json::json!({
+                        "label": token_info.label,
+                        "expires_at": token_info.expires_at,
+                        "batch": true,
+                    })),
+                )
+            }),
+        )
+        .await;
+        Ok(tokens)
+    }
+
+    /// A fresh token with default settings, not yet stored
+    fn new_token_info(
+        &self,
+        id: Uuid,
+        headers: &HeaderMap,
+        created_at: chrono::DateTime<chrono::Utc>,
+        expires_at: Option<String>,
+        label: Option<String>,
+    ) -> TokenInfo {
+        let token = id.to_string();
+
+        // Generate webhook URL based on configuration or request
+        let webhook_url = generate_webhook_url(&self.base_url, headers, &token);
+
+        TokenInfo {
+            token,
+            created_at: created_at.to_rfc3339(),
+            webhook_url,
+            expires_at,
+            label,
+            disabled: false,
+            forward_url: None,
+            has_secret: false,
+            last_used_at: None,
+            max_requests: None,
+            cors_origins: Vec::new(),
+            deduplicate_window_secs: None,
+            response_delay_ms: None,
+            redact_headers: None,
+            allowed_methods: Vec::new(),
+            allowed_content_types: None,
+            max_age_seconds: None,
+            nonce_required: false,
+            notification_url: None,
+            notification_events: None,
+            readonly: false,
+            has_readonly_key: false,
+            has_custom_response: false,
+            max_payload_bytes: None,
+            transform_rules: Vec::new(),
+            message_config: None,
+            metadata: HashMap::new(),
+            request_count: 0,
+        }
+    }
+
+    pub async fn list_tokens(&self, query: &TokenListQuery) -> Result<Vec<TokenInfo>, AppError> {
+        let tokens = self.db.list_tokens(query).await.map_err(|e| {
+            warn!("Failed to list tokens: {}", e);
+            AppError::internal()
+        })?;
+        Ok(tokens)
+    }
+
+    /// Issue random single-use nonces for the token's webhooks
+    pub async fn create_nonces(
+        &self,
+        token: &str,
+        count: Option<u32>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<NonceBatch, AppError> {
+        let count = count.unwrap_or(DEFAULT_NONCE_BATCH_SIZE);
+        if count == 0 || count > MAX_NONCE_BATCH_SIZE {
+            return Err(AppError::InvalidRequest(format!(
+                "count must be between 1 and {}",
+                MAX_NONCE_BATCH_SIZE
+            )));
+        }
+        if ttl_seconds == Some(0) {
+            return Err(AppError::InvalidRequest(
+                "ttl_seconds must be at least 1".into(),
+            ));
+        }
+        let expires_at = expiry_from_ttl(
+            chrono::Utc::now(),
+            Some(ttl_seconds.unwrap_or(DEFAULT_NONCE_TTL_SECS)),
+        )?
+        .ok_or_else(AppError::internal)?;
+        self.get_token(token).await?;
+
+        let nonces: Vec<String> = (0..count)
+            .map(|_| hex::encode(rand::random::<[u8; NONCE_BYTES]>()))
+            .collect();
+        self.db
+            .create_nonces(token, &nonces, &expires_at)
+            .await
+            .map_err(|e| {
+                warn!("Failed to create nonces: {}", e);
+                AppError::internal()
+            })?;
+
+        info!("Issued {} nonces for token: {}", count, token);
+        audit::record(
+            self.db.as_ref(),
+            "nonce.create",
+            Some(token),
+            Some(serde_json::json!({ "count": count, "expires_at": expires_at })),
+        )
+        .await;
+        Ok(NonceBatch { nonces, expires_at })
+    }
+
+    pub async fn disable_token(&self, token: &str) -> Result<TokenInfo, AppError> {
+        self.set_token_disabled(token, true).await?;
+        self.token_cache.invalidate(token);
+        info!("Disabled token: {}", token);
+        audit::record(self.db.as_ref(), "token.disable", Some(token), None).await;
+        self.get_token(token).await
+    }
+
+    pub async fn enable_token(&self, token: &str) -> Result<TokenInfo, AppError> {
+        self.set_token_disabled(token, false).await?;
+        self.token_cache.invalidate(token);
+        info!("Enabled token: {}", token);
+        audit::record(self.db.as_ref(), "token.enable", Some(token), None).await;
+        self.get_token(token).await
+    }
+
+    async fn set_token_disabled(&self, token: &str, disabled: bool) -> Result<(), AppError> {
+        let updated = self
+            .db
+            .set_token_disabled(token, disabled)
+            .await
+            .map_err(|e| {
+                warn!("Failed to update token state: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+        Ok(())
+    }
+
+    /// Apply a partial update of the token's settings. Every field is checked before any is
+    /// stored, and all of them are stored in one statement
+    pub async fn update_token(
+        &self,
+        token: &str,
+        request: UpdateTokenRequest,
+    ) -> Result<TokenInfo, AppError> {
+        let update = UpdateTokenRequest {
+            label: request.label.map(normalize_label).transpose()?,
+            max_requests: request
+                .max_requests
+                .map(|max| {
+                    at_least_one(
+                        max,
+                        "max_requests must be at least 1; use null to remove the limit",
+                    )
+                })
+                .transpose()?,
+            deduplicate_window_secs: request
+                .deduplicate_window_secs
+                .map(|window_secs| {
+                    at_least_one(
+                        window_secs,
+                        "deduplicate_window_secs must be at least 1; use null to disable deduplication",
+                    )
+                })
+                .transpose()?,
+            response_delay_ms: request
+                .response_delay_ms
+                .map(|delay_ms| {
+                    if delay_ms.is_some_and(|delay| delay > MAX_TOKEN_RESPONSE_DELAY_MS) {
+                        return Err(AppError::InvalidRequest(format!(
+                            "response_delay_ms must be at most {}",
+                            MAX_TOKEN_RESPONSE_DELAY_MS
+                        )));
+                    }
+                    Ok(delay_ms)
+                })
+                .transpose()?,
+            redact_headers: request
+                .redact_headers
+                .map(|headers| headers.map(redact_header_names).transpose())
+                .transpose()?,
+            allowed_methods: request.allowed_methods.map(allowed_methods).transpose()?,
+            allowed_content_types: request
+                .allowed_content_types
+                .map(|content_types| content_types.map(allowed_media_types).transpose())
+                .transpose()?,
+            max_age_seconds: request
+                .max_age_seconds
+                .map(|max_age_secs| {
+                    at_least_one(
+                        max_age_secs,
+                        "max_age_seconds must be at least 1; use null to disable the check",
+                    )
+                })
+                .transpose()?,
+            nonce_required: request.nonce_required,
+            notification_url: request
+                .notification_url
+                .map(|url| url.map(valid_notification_url).transpose())
+                .transpose()?,
+            notification_events: request
+                .notification_events
+                .map(|events| events.map(notification_events).transpose())
+                .transpose()?,
+            readonly: request.readonly,
+            max_payload_bytes: request
+                .max_payload_bytes
+                .map(|max_bytes| {
+                    if max_bytes.is_some_and(|max| max == 0 || max > MAX_TOKEN_PAYLOAD_BYTES) {
+                        return Err(AppError::InvalidRequest(format!(
+                            "max_payload_bytes must be between 1 and {}",
+                            MAX_TOKEN_PAYLOAD_BYTES
+                        )));
+                    }
+                    Ok(max_bytes)
+                })
+                .transpose()?,
+            transform_rules: request.transform_rules.map(transform_rules).transpose()?,
+            message_config: request
+                .message_config
+                .map(|extractor| {
+                    extractor
+                        .map(MessageExtractor::validate)
+                        .transpose()
+                        .map_err(AppError::InvalidRequest)
+                })
+                .transpose()?,
+        };
+
+        let changes = update.changes();
+        if changes.is_empty() {
+            return self.get_token(token).await;
+        }
+        let updated = self
+            .db
+            .update_token_settings(token, &update)
+            .await
+            .map_err(|e| {
+                warn!("Failed to update token settings: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Updated settings for token: {}", token);
+        audit::record(
+            self.db.as_ref(),
+            "token.update",
+            Some(token),
+            Some(serde_json::Value::Object(changes)),
+        )
+        .await;
+        self.get_token(token).await
+    }
+
+    /// Set (or clear, with `None`) the URL incoming webhooks are forwarded to
+    pub async fn set_forward_url(
+        &self,
+        token: &str,
+        url: Option<String>,
+    ) -> Result<TokenInfo, AppError> {
+        if let Some(ref url) = url {
+            let parsed = url::Url::parse(url)
+                .map_err(|e| AppError::InvalidRequest(format!("invalid forward URL: {}", e)))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(AppError::InvalidRequest(
+                    "forward URL must use http or https".into(),
+                ));
+            }
+        }
+
+        let updated = self
+            .db
+            .set_forward_url(token, url.as_deref())
+            .await
+            .map_err(|e| {
+                warn!("Failed to update forward URL: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        match url {
+            Some(ref url) => info!("Forwarding token {} to {}", token, url),
+            None => info!("Disabled forwarding for token {}", token),
+        }
+        audit::record(
+            self.db.as_ref(),
+            "forward.set",
+            Some(token),
+            Some(serde_json::json!({ "url": url })),
+        )
+        .await;
+        self.get_token(token).await
+    }
+
+    pub async fn list_forward_targets(&self, token: &str) -> Result<Vec<ForwardTarget>, AppError> {
+        self.get_token(token).await?;
+        let mut targets = self.db.list_forward_targets(token).await.map_err(|e| {
+            warn!("Failed to list forward targets: {}", e);
+            AppError::internal()
+        })?;
+        for target in &mut targets {
+            target.circuit_state = self.circuit_breaker.state(&target.url).as_str();
+        }
+        Ok(targets)
+    }
+
+    /// The regions of the token's mirror targets and how many targets in each are healthy
+    pub async fn forward_regions(&self, token: &str) -> Result<Vec<ForwardRegion>, AppError> {
+        let targets = self.list_forward_targets(token).await?;
+
+        let mut regions: BTreeMap<Option<String>, Vec<ForwardTarget>> = BTreeMap::new();
+        for target in targets {
+            regions
+                .entry(target.forward_region.clone())
+                .or_default()
+                .push(target);
+        }
+
+        Ok(regions
+            .into_iter()
+            .map(|(region, targets)| {
+                let enabled = targets.iter().filter(|target| target.enabled).count();
+                let healthy = targets
+                    .iter()
+                    .filter(|target| target.enabled && target.circuit_state != "open")
+                    .count();
+                let status = match (enabled, healthy) {
+                    (0, _) => "disabled",
+                    (_, 0) => "down",
+                    (enabled, healthy) if healthy < enabled => "degraded",
+                    _ => "healthy",
+                };
+                ForwardRegion {
+                    local: region.is_some() && region == self.service_region,
+                    region,
+                    targets: targets.len(),
+                    enabled,
+                    healthy,
+                    status,
+                }
+            })
+            .collect())
+    }
+
+    /// Add a URL that incoming webhooks are mirrored to, in addition to the forward URL
+    pub async fn add_forward_target(
+        &self,
+        token: &str,
+        request: CreateForwardTargetRequest,
+    ) -> Result<ForwardTarget, AppError> {
+        let url = request.url.trim().to_string();
+        let parsed = url::Url::parse(&url)
+            .map_err(|e| AppError::InvalidRequest(format!("invalid forward URL: {}", e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(AppError::InvalidRequest(
+                "forward URL must use http or https".into(),
+            ));
+        }
+        for (name, value) in &request.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err()
+                || HeaderValue::from_str(value).is_err()
+            {
+                return Err(AppError::InvalidRequest(format!(
+                    "invalid header '{}'",
+                    name
+                )));
+            }
+        }
+
+        let forward_region = request
+            .forward_region
+            .map(|region| region.trim().to_string())
+            .filter(|region| !region.is_empty());
+        if forward_region
+            .as_ref()
+            .is_some_and(|region| region.chars().count() > MAX_REGION_LENGTH)
+        {
+            return Err(AppError::InvalidRequest(format!(
+                "forward_region must be at most {} characters",
+                MAX_REGION_LENGTH
+            )));
+        }
+
+        self.get_token(token).await?;
+        let target = ForwardTarget {
+            id: Uuid::new_v4().to_string(),
+            circuit_state: self.circuit_breaker.state(&url).as_str(),
+            url,
+            enabled: request.enabled,
+            headers: request.headers,
+            forward_region,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            last_result: None,
+        };
+        self.db
+            .create_forward_target(token, &target)
+            .await
+            .map_err(|e| {
+                warn!("Failed to create forward target: {}", e);
+                AppError::internal()
+            })?;
+
+        self.token_cache.invalidate(token);
+        info!("Mirroring token {} to {}", token, target.url);
+        audit::record(
+            self.db.as_ref(),
+            "forward.add",
+            Some(token),
+            Some(serde_json::json!({
+                "forward_id": target.id,
+                "url": target.url,
+                "forward_region": target.forward_region,
+            })),
+        )
+        .await;
+        Ok(target)
+    }
+
+    pub async fn delete_forward_target(
+        &self,
+        token: &str,
+        forward_id: &str,
+    ) -> Result<(), AppError> {
+        let deleted = self
+            .db
+            .delete_forward_target(token, forward_id)
+            .await
+            .map_err(|e| {
+                warn!("Failed to delete forward target: {}", e);
+                AppError::internal()
+            })?;
+        if !deleted {
+            return Err(AppError::ForwardTargetNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Deleted forward target {} of token {}", forward_id, token);
+        audit::record(
+            self.db.as_ref(),
+            "forward.delete",
+            Some(token),
+            Some(serde_json::json!({ "forward_id": forward_id })),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Replace the browser origins allowed to call the token's webhook URL
+    pub async fn set_cors_origins(
+        &self,
+        token: &str,
+        origins: Vec<String>,
+    ) -> Result<TokenInfo, AppError> {
+        let origins = origins
+            .iter()
+            .map(|origin| normalize_origin(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let updated = self
+            .db
+            .set_cors_origins(token, &origins)
+            .await
+            .map_err(|e| {
+                warn!("Failed to update CORS origins: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Updated CORS origins for token {}: {:?}", token, origins);
+        audit::record(
+            self.db.as_ref(),
+            "cors.set",
+            Some(token),
+            Some(serde_json::json!({ "origins": origins })),
+        )
+        .await;
+        self.get_token(token).await
+    }
+
+    /// Set the token's signing secret, generating a random one if none is given
+    pub async fn set_webhook_secret(
+        &self,
+        token: &str,
+        secret: Option<String>,
+    ) -> Result<WebhookSecret, AppError> {
+        let secret = match secret {
+            Some(secret) => {
+                let secret = secret.trim().to_string();
+                if secret.is_empty() {
+                    return Err(AppError::InvalidRequest("secret must not be empty".into()));
+                }
+                secret
+            }
+            None => hex::encode(rand::random::<[u8; WEBHOOK_SECRET_BYTES]>()),
+        };
+
+        let updated = self
+            .db
+            .set_webhook_secret(token, &secret)
+            .await
+            .map_err(|e| {
+                warn!("Failed to update webhook secret: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Updated webhook secret for token {}", token);
+        audit::record(self.db.as_ref(), "secret.set", Some(token), None).await;
+        Ok(WebhookSecret {
+            secret,
+            previous_expires_at: None,
+        })
+    }
+
+    /// Replace the token's secret with a random one while the current secret keeps
+    /// validating for the grace period, so senders can switch over without rejections
+    pub async fn rotate_webhook_secret(&self, token: &str) -> Result<WebhookSecret, AppError> {
+        let secret = hex::encode(rand::random::<[u8; WEBHOOK_SECRET_BYTES]>());
+        let previous_expires_at = chrono::TimeDelta::from_std(self.secret_rotation_grace)
+            .ok()
+            .and_then(|grace| chrono::Utc::now().checked_add_signed(grace))
+            .ok_or_else(AppError::internal)?;
+
+        let current = self.db.get_webhook_secret(token).await.map_err(|e| {
+            warn!("Failed to get webhook secret: {}", e);
+            AppError::internal()
+        })?;
+        let rotated = self
+            .db
+            .rotate_webhook_secret(token, &secret, &previous_expires_at.to_rfc3339())
+            .await
+            .map_err(|e| {
+                warn!("Failed to rotate webhook secret: {}", e);
+                AppError::internal()
+            })?;
+        if !rotated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Rotated webhook secret for token {}", token);
+        audit::record(
+            self.db.as_ref(),
+            "secret.rotate",
+            Some(token),
+            Some(serde_json::json!({ "previous_expires_at": previous_expires_at.to_rfc3339() })),
+        )
+        .await;
+        Ok(WebhookSecret {
+            secret,
+            previous_expires_at: current.map(|_| previous_expires_at.to_rfc3339()),
+        })
+    }
+
+    /// Issue a key that can read the token's requests but change nothing, replacing any
+    /// previous one
+    pub async fn create_readonly_key(&self, token: &str) -> Result<ReadonlyKey, AppError> {
+        let key = hex::encode(rand::random::<[u8; WEBHOOK_SECRET_BYTES]>());
+        let updated = self
+            .db
+            .set_readonly_key_hash(token, Some(&readonly_key_hash(&key)))
+            .await
+            .map_err(|e| {
+                warn!("Failed to store read-only key: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Issued read-only key for token {}", token);
+        audit::record(self.db.as_ref(), "readonly_key.create", Some(token), None).await;
+        Ok(ReadonlyKey { readonly_key: key })
+    }
+
+    pub async fn revoke_readonly_key(&self, token: &str) -> Result<TokenInfo, AppError> {
+        let updated = self
+            .db
+            .set_readonly_key_hash(token, None)
+            .await
+            .map_err(|e| {
+                warn!("Failed to revoke read-only key: {}", e);
+                AppError::internal()
+            })?;
+        if !updated {
+            return Err(AppError::TokenNotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Revoked read-only key for token {}", token);
+        audit::record(self.db.as_ref(), "readonly_key.revoke", Some(token), None).await;
+        self.get_token(token).await
+    }
+
+    pub async fn get_metadata(&self, token: &str) -> Result<HashMap<String, String>, AppError> {
+        self.get_token(token).await?;
+        self.db.get_token_metadata(token).await.map_err(|e| {
+            warn!("Failed to load token metadata: {}", e);
+            AppError::internal()
+        })
+    }
+
+    /// Replace the token's metadata; an empty object removes all entries
+    pub async fn set_metadata(
+        &self,
+        token: &str,
+        metadata: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, AppError> {
+        if metadata.len() > MAX_METADATA_KEYS {
+            return Err(AppError::InvalidRequest(format!(
+                "at most {} metadata keys are allowed",
+                MAX_METADATA_KEYS
+            )));
+        }
+        for (key, value) in &metadata {
+            if key.is_empty() || key.chars().count() > MAX_METADATA_KEY_LENGTH {
+                return Err(AppError::InvalidRequest(format!(
+                    "metadata keys must be 1 to {} characters",
+                    MAX_METADATA_KEY_LENGTH
+                )));
+            }
+            if value.chars().count() > MAX_METADATA_VALUE_LENGTH {
+                return Err(AppError::InvalidRequest(format!(
+                    "metadata value of '{}' exceeds {} characters",
+                    key, MAX_METADATA_VALUE_LENGTH
+                )));
+            }
+        }
+
+        self.get_token(token).await?;
+        self.db
+            .set_token_metadata(token, &metadata)
+            .await
+            .map_err(|e| {
+                warn!("Failed to update token metadata: {}", e);
+                AppError::internal()
+            })?;
+
+        self.token_cache.invalidate(token);
+        info!("Set {} metadata key(s) for token {}", metadata.len(), token);
+        audit::record(
+            self.db.as_ref(),
+            "metadata.set",
+            Some(token),
+            Some(serde_json::json!({ "metadata": metadata })),
+        )
+        .await;
+        Ok(metadata)
+    }
+
+    pub async fn delete_metadata_key(&self, token: &str, key: &str) -> Result<(), AppError> {
+        self.get_token(token).await?;
+        let deleted = self
+            .db
+            .delete_token_metadata(token, key)
+            .await
+            .map_err(|e| {
+                warn!("Failed to delete token metadata: {}", e);
+                AppError::internal()
+            })?;
+        if !deleted {
+            return Err(AppError::NotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        audit::record(
+            self.db.as_ref(),
+            "metadata.delete",
+            Some(token),
+            Some(serde_json::json!({ "key": key })),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Replace the token's IP rules; an empty list removes all restrictions
+    pub async fn set_ip_rules(
+        &self,
+        token: &str,
+        rules: Vec<IpRule>,
+    ) -> Result<Vec<IpRule>, AppError> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let network: IpNetwork = rule.cidr.trim().parse().map_err(|e| {
+                    AppError::InvalidRequest(format!("invalid CIDR '{}': {}", rule.cidr, e))
+                })?;
+                Ok(IpRule {
+                    cidr: network.to_string(),
+                    action: rule.action,
+                })
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        self.get_token(token).await?;
+        self.db.set_ip_rules(token, &rules).await.map_err(|e| {
+            warn!("Failed to update IP rules: {}", e);
+            AppError::internal()
+        })?;
+
+        self.token_cache.invalidate(token);
+        info!("Set {} IP rule(s) for token {}", rules.len(), token);
+        audit::record(
+            self.db.as_ref(),
+            "ip_rules.set",
+            Some(token),
+            Some(serde_json::json!({ "rules": rules })),
+        )
+        .await;
+        Ok(rules)
+    }
+
+    pub async fn set_custom_response(
+        &self,
+        token: &str,
+        request: SetCustomResponseRequest,
+    ) -> Result<(), AppError> {
+        if StatusCode::from_u16(request.status_code).is_err() {
+            return Err(AppError::InvalidRequest(format!(
+                "invalid status code: {}",
+                request.status_code
+            )));
+        }
+        for (name, value) in &request.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err()
+                || HeaderValue::from_str(value).is_err()
+            {
+                return Err(AppError::InvalidRequest(format!(
+                    "invalid response header: {}",
+                    name
+                )));
+            }
+        }
+
+        let body = match (request.body_base64, request.body) {
+            (Some(encoded), _) => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| AppError::InvalidRequest(format!("invalid body_base64: {}", e)))?,
+            (None, Some(body)) => body.into_bytes(),
+            (None, None) => Vec::new(),
+        };
+
+        // Make sure the token exists so we return 404 rather than a constraint error
+        self.get_token(token).await?;
+
+        let response = CustomResponse {
+            status_code: request.status_code,
+            headers: request.headers,
+            body,
+        };
+        self.db
+            .set_custom_response(token, &response)
+            .await
+            .map_err(|e| {
+                warn!("Failed to set custom response: {}", e);
+                AppError::internal()
+            })?;
+
+        self.token_cache.invalidate(token);
+        info!(
+            "Configured custom {} response for token: {}",
+            response.status_code, token
+        );
+        audit::record(
+            self.db.as_ref(),
+            "response.set",
+            Some(token),
+            Some(serde_json::json!({ "status_code": response.status_code })),
+        )
+        .await;
+        Ok(())
+    }
+
+    pub async fn get_slack_notification(&self, token: &str) -> Result<SlackNotification, AppError> {
+        self.get_token(token).await?;
+        self.db
+            .get_slack_notification(token)
+            .await
+            .map_err(|e| {
+                warn!("Failed to get Slack notification: {}", e);
+                AppError::internal()
+            })?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Post a message to a Slack incoming webhook for every webhook the token receives
+    pub async fn set_slack_notification(
+        &self,
+        token: &str,
+        request: SetSlackNotificationRequest,
+    ) -> Result<SlackNotification, AppError> {
+        let webhook_url = request.webhook_url.trim().to_string();
+        let parsed = url::Url::parse(&webhook_url)
+            .map_err(|e| AppError::InvalidRequest(format!("invalid Slack webhook URL: {}", e)))?;
+        if parsed.scheme() != "https" {
+            return Err(AppError::InvalidRequest(
+                "Slack webhook URL must use https".into(),
+            ));
+        }
+        let template = request
+            .template
+            .filter(|template| !template.trim().is_empty());
+        if template
+            .as_ref()
+            .is_some_and(|template| template.chars().count() > MAX_SLACK_TEMPLATE_LENGTH)
+        {
+            return Err(AppError::InvalidRequest(format!(
+                "template must be at most {} characters",
+                MAX_SLACK_TEMPLATE_LENGTH
+            )));
+        }
+
+        self.get_token(token).await?;
+        let notification = SlackNotification {
+            webhook_url,
+            template,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.db
+            .set_slack_notification(token, &notification)
+            .await
+            .map_err(|e| {
+                warn!("Failed to set Slack notification: {}", e);
+                AppError::internal()
+            })?;
+
+        self.token_cache.invalidate(token);
+        info!("Configured Slack notifications for token: {}", token);
+        audit::record(
+            self.db.as_ref(),
+            "slack.set",
+            Some(token),
+            Some(serde_json::json!({ "template": notification.template })),
+        )
+        .await;
+        Ok(notification)
+    }
+
+    pub async fn clear_slack_notification(&self, token: &str) -> Result<(), AppError> {
+        let deleted = self
+            .db
+            .delete_slack_notification(token)
+            .await
+            .map_err(|e| {
+                warn!("Failed to delete Slack notification: {}", e);
+                AppError::internal()
+            })?;
+        if !deleted {
+            return Err(AppError::NotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Removed Slack notifications for token: {}", token);
+        audit::record(self.db.as_ref(), "slack.clear", Some(token), None).await;
+        Ok(())
+    }
+
+    pub async fn get_email_notification(&self, token: &str) -> Result<EmailNotification, AppError> {
+        self.get_token(token).await?;
+        self.db
+            .get_email_notification(token)
+            .await
+            .map_err(|e| {
+                warn!("Failed to get email notification: {}", e);
+                AppError::internal()
+            })?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Email a summary of the token's webhooks to an address when the given events occur
+    pub async fn set_email_notification(
+        &self,
+        token: &str,
+        request: SetEmailNotificationRequest,
+    ) -> Result<EmailNotification, AppError> {
+        if !self.notifications.email_enabled() {
+            return Err(AppError::EmailNotConfigured);
+        }
+        let to = request.to.trim().to_string();
+        if let Err(e) = to.parse::<Mailbox>() {
+            return Err(AppError::InvalidRequest(format!(
+                "invalid email address: {}",
+                e
+            )));
+        }
+        let events = notification_events(
+            request
+                .events
+                .unwrap_or_else(|| vec![EVENT_WEBHOOK_RECEIVED.to_string()]),
+        )?;
+        if events.is_empty() {
+            return Err(AppError::InvalidRequest(
+                "events must name at least one event".into(),
+            ));
+        }
+
+        self.get_token(token).await?;
+        let notification = EmailNotification {
+            to,
+            events,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.db
+            .set_email_notification(token, &notification)
+            .await
+            .map_err(|e| {
+                warn!("Failed to set email notification: {}", e);
+                AppError::internal()
+            })?;
+
+        self.token_cache.invalidate(token);
+        info!("Configured email notifications for token: {}", token);
+        audit::record(
+            self.db.as_ref(),
+            "email.set",
+            Some(token),
+            Some(serde_json::json!({ "to": notification.to, "events": notification.events })),
+        )
+        .await;
+        Ok(notification)
+    }
+
+    pub async fn clear_email_notification(&self, token: &str) -> Result<(), AppError> {
+        let deleted = self
+            .db
+            .delete_email_notification(token)
+            .await
+            .map_err(|e| {
+                warn!("Failed to delete email notification: {}", e);
+                AppError::internal()
+            })?;
+        if !deleted {
+            return Err(AppError::NotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Removed email notifications for token: {}", token);
+        audit::record(self.db.as_ref(), "email.clear", Some(token), None).await;
+        Ok(())
+    }
+
+    /// Send a test email to the token's configured address, waiting for the SMTP server
+    pub async fn send_test_email(&self, token: &str) -> Result<EmailNotification, AppError> {
+        let email = self.get_email_notification(token).await?;
+        let body = format!(
+            "This is a test email from the webhook service.\n\nToken: {}\nEvents: {}\n",
+            token,
+            email.events.join(", ")
+        );
+        self.notifications
+            .send_email(&email.to, "Test notification", &body)
+            .await
+            .inspect_err(|e| warn!("Failed to send test email for token {}: {}", token, e))?;
+
+        info!("Sent test email for token: {}", token);
+        Ok(email)
+    }
+
+    pub async fn clear_custom_response(&self, token: &str) -> Result<(), AppError> {
+        let deleted = self.db.delete_custom_response(token).await.map_err(|e| {
+            warn!("Failed to delete custom response: {}", e);
+            AppError::internal()
+        })?;
+        if !deleted {
+            return Err(AppError::NotFound);
+        }
+
+        self.token_cache.invalidate(token);
+        info!("Cleared custom response for token: {}", token);
+        audit::record(self.db.as_ref(), "response.clear", Some(token), None).await;
+        Ok(())
+    }
+
+    /// Audit entries, most recent first
+    pub async fn list_audit(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AppError> {
+        // Dates are stored as UTC RFC 3339, so bounds must be in the same form to compare
+        let normalize = |date: &Option<String>, name: &str| {
+            date.as_deref()
+                .map(|date| {
+                    chrono::DateTime::parse_from_rfc3339(date)
+                        .map(|date| date.with_timezone(&chrono::Utc).to_rfc3339())
+                        .map_err(|_| {
+                            AppError::InvalidRequest(format!("{} must be an RFC 3339 date", name))
+                        })
+                })
+                .transpose()
+        };
+        let query = AuditQuery {
+            from: normalize(&query.from, "from")?,
+            to: normalize(&query.to, "to")?,
+            ..query.clone()
+        };
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        self.db.list_audit(&query, limit).await.map_err(|e| {
+            warn!("Failed to list audit entries: {}", e);
+            AppError::internal()
+        })
+    }
+
+    pub async fn get_token(&self, token: &str) -> Result<TokenInfo, AppError> {
+        self.db
+            .get_token(token)
+            .await
+            .map_err(|e| {
+                warn!("Failed to get token: {}", e);
+                AppError::internal()
+            })?
+            .ok_or(AppError::TokenNotFound)
+    }
+
+    /// One page of tokens, oldest first, together with all their stored requests
+    pub async fn export_page(&self, page: u32) -> Result<DataExport, AppError> {
+        let offset = page
+            .checked_mul(EXPORT_TOKENS_PER_PAGE)
+            .ok_or_else(|| AppError::InvalidRequest("page is out of range".into()))?;
+        // One extra token tells whether another page follows
+        let mut tokens = self
+            .db
+            .list_tokens_page(EXPORT_TOKENS_PER_PAGE + 1, offset)
+            .await
+            .map_err(|e| {
+                warn!("Failed to export tokens: {}", e);
+                AppError::internal()
+            })?;
+        let next_page = (tokens.len() > EXPORT_TOKENS_PER_PAGE as usize).then_some(page + 1);
+        tokens.truncate(EXPORT_TOKENS_PER_PAGE as usize);
+
+        let filter = WebhookRequestFilter::default();
+        let mut requests = Vec::new();
+        for token_info in &tokens {
+            let mut stream = self.db.stream_webhook_requests(&token_info.token, &filter);
+            while let Some(request) = stream.next().await {
+                requests.push(request.map_err(|e| {
+                    warn!("Failed to export webhook requests: {}", e);
+                    AppError::internal()
+                })?);
+            }
+        }
+
+        Ok(DataExport {
+            tokens,
+            requests,
+            next_page,
+        })
+    }
+
+    /// Import an export page, skipping tokens and requests that already exist. Webhook URLs
+    /// are regenerated for this instance
+    pub async fn import_data(
+        &self,
+        headers: &HeaderMap,
+        data: DataExport,
+        dry_run: bool,
+    ) -> Result<ImportSummary, AppError> {
+        let mut tokens = data.tokens;
+        for token_info in &mut tokens {
+            Uuid::parse_str(&token_info.token).map_err(|_| {
+                AppError::InvalidRequest(format!("invalid token '{}'", token_info.token))
+            })?;
+            token_info.webhook_url =
+                generate_webhook_url(&self.base_url, headers, &token_info.token);
+        }
+
+        // Requests may only belong to imported tokens or ones this instance already has
+        let mut known: HashMap<&str, bool> = tokens
+            .iter()
+            .map(|token_info| (token_info.token.as_str(), true))
+            .collect();
+        for request in &data.requests {
+            let exists = match known.get(request.token_id.as_str()) {
+                Some(&exists) => exists,
+                None => {
+                    let exists = self.db.token_exists(&request.token_id).await.map_err(|e| {
+                        warn!("Failed to check token existence: {}", e);
+                        AppError::internal()
+                    })?;
+                    known.insert(&request.token_id, exists);
+                    exists
+                }
+            };
+            if !exists {
+                return Err(AppError::InvalidRequest(format!(
+                    "request {} belongs to unknown token {}",
+                    request.id, request.token_id
+                )));
+            }
+        }
+
+        // Only tokens this instance does not have yet count against MAX_TOKENS; a dry run
+        // reports the overflow the same way the import would
+        if self.max_tokens > 0 {
+            let mut new_tokens = 0u32;
+            let imported: HashSet<&str> = tokens
+                .iter()
+                .map(|token_info| token_info.token.as_str())
+                .collect();
+            for token in imported {
+                let exists = self.db.token_exists(token).await.map_err(|e| {
+                    warn!("Failed to check token existence: {}", e);
+                    AppError::internal()
+                })?;
+                if !exists {
+                    new_tokens = new_tokens.saturating_add(1);
+                }
+            }
+            self.check_max_tokens(new_tokens).await?;
+        }
+
+        let summary = self
+            .db
+            .import_data(&tokens, &data.requests, dry_run)
+            .await
+            .map_err(|e| {
+                warn!("Failed to import data: {}", e);
+                AppError::internal()
+            })?;
+
+        if !dry_run {
+            // Tokens that already existed keep their stored settings
+            for token_info in &tokens {
+                self.token_cache.invalidate(&token_info.token);
+            }
+            for _ in 0..summary.tokens_imported {
+                self.metrics.record_token_created();
+            }
+        }
+
+        info!(
+            "Imported {} tokens and {} requests ({} tokens and {} requests already existed){}",
+            summary.tokens_imported,
+            summary.requests_imported,
+            summary.tokens_skipped,
+            summary.requests_skipped,
+            if dry_run { " [dry run]" } else { "" }
+        );
+        if !dry_run {
+            audit::record(
+                self.db.as_ref(),
+                "data.import",
+                None,
+                Some(serde_json::to_value(&summary).unwrap_or_default()),
+            )
+            .await;
+        }
+        Ok(summary)
+    }
+
+    /// Compact the database after bulk deletes, reporting page usage before and after
+    pub async fn vacuum_database(&self) -> Result<VacuumReport, AppError> {
+        let db = self.db.clone();
+        let runtime = tokio::runtime::Handle::current();
+        // VACUUM rewrites the whole database; keep it off the async workers, and let it
+        // finish even if the client disconnects
+        let report = tokio::task::spawn_blocking(move || {
+            runtime.block_on(async {
+                let started = Instant::now();
+                let before = db.page_stats().await?;
+                db.vacuum().await?;
+                let after = db.page_stats().await?;
+                anyhow::Ok(VacuumReport {
+                    before,
+                    after,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                })
+            })
+        })
+        .await
+        .map_err(|e| {
+            warn!("Vacuum task failed: {}", e);
+            AppError::internal()
+        })?
+        .map_err(|e| {
+            warn!("Failed to vacuum database: {}", e);
+            AppError::internal()
+        })?;
+
+        info!(
+            "Vacuumed database in {}ms ({} pages before, {} after)",
+            report.duration_ms, report.before.page_count, report.after.page_count
+        );
+        audit::record(
+            self.db.as_ref(),
+            "database.vacuum",
+            None,
+            Some(serde_json::to_value(&report).unwrap_or_default()),
+        )
+        .await;
+        Ok(report)
+    }
+
+    /// Refresh the query planner's statistics, e.g. after the data has changed a lot
+    pub async fn analyze_database(&self) -> Result<AnalyzeReport, AppError> {
+        let db = self.db.clone();
+        let runtime = tokio::runtime::Handle::current();
+        let started = Instant::now();
+        tokio::task::spawn_blocking(move || runtime.block_on(db.analyze()))
+            .await
+            .map_err(|e| {
+                warn!("Analyze task failed: {}", e);
+                AppError::internal()
+            })?
+            .map_err(|e| {
+                warn!("Failed to analyze database: {}", e);
+                AppError::internal()
+            })?;
+        let report = AnalyzeReport {
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+
+        info!("Analyzed database in {}ms", report.duration_ms);
+        audit::record(self.db.as_ref(), "database.analyze", None, None).await;
+        Ok(report)
+    }
+
+    /// Run the database's integrity checks and store the result for `GET /health`
+    pub async fn check_integrity(&self) -> Result<IntegrityReport, AppError> {
+        let db = self.db.clone();
+        let runtime = tokio::runtime::Handle::current();
+        // The checks read every page of the database; keep them off the async workers
+        let errors = tokio::task::spawn_blocking(move || runtime.block_on(db.integrity_check()))
+            .await
+            .map_err(|e| {
+                warn!("Integrity check task failed: {}", e);
+                AppError::internal()
+            })?
+            .map_err(|e| {
+                warn!("Failed to check database integrity: {}", e);
+                AppError::internal()
+            })?;
+        let report = IntegrityReport::new(errors, chrono::Utc::now().to_rfc3339());
+
+        if report.errors.is_empty() {
+            info!("Database integrity check passed");
+        } else {
+            warn!(
+                "Database integrity check found {} problems: {}",
+                report.errors.len(),
+                report.errors.join("; ")
+            );
+        }
+        if let Err(e) = self.db.record_health_check(&report.to_health_check()).await {
+            warn!("Failed to record integrity check result: {}", e);
+        }
+        Ok(report)
+    }
+
+    /// Time until the next scheduled integrity check, a week after the last one of any kind
+    pub async fn integrity_check_due_in(&self) -> Duration {
+        let last = match self.db.get_last_health_check(HEALTH_CHECK_INTEGRITY).await {
+            Ok(last) => last,
+            Err(e) => {
+                warn!("Failed to get the last integrity check: {}", e);
+                None
+            }
+        };
+        let Some(checked_at) =
+            last.and_then(|check| chrono::DateTime::parse_from_rfc3339(&check.checked_at).ok())
+        else {
+            return Duration::ZERO;
+        };
+
+        let elapsed = (chrono::Utc::now() - checked_at.to_utc())
+            .to_std()
+            .unwrap_or_default();
+        INTEGRITY_CHECK_INTERVAL.saturating_sub(elapsed)
     }
 
     pub async fn delete_token(&self, token: &str) -> Result<(), AppError> {
         self.db.delete_token(token).await.map_err(|e| {
             warn!("Failed to delete token: {}", e);
-            AppError::InternalServerError
+            AppError::internal()
         })?;
+        self.token_cache.invalidate(token);
 
         info!("Deleted token: {}", token);
+        audit::record(self.db.as_ref(), "token.delete", Some(token), None).await;
         Ok(())
     }
 }
@@ -0,0 +1,111 @@
+//! OpenTelemetry trace export, compiled in with the `opentelemetry` feature.
+//! Without the feature these functions are no-ops.
+
+use std::collections::HashMap;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+
+#[cfg(feature = "opentelemetry")]
+mod otel {
+    use opentelemetry::propagation::{Extractor, TextMapPropagator};
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
+    use tracing::Subscriber;
+    use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+    pub fn layer<S>() -> anyhow::Result<Option<OpenTelemetryLayer<S, SdkTracer>>>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        // The exporter reads the endpoint (and OTEL_EXPORTER_OTLP_HEADERS etc.) itself
+        if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+            return Ok(None);
+        }
+
+        let exporter = SpanExporter::builder().with_http().build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(env!("CARGO_PKG_NAME"))
+                    .build(),
+            )
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        let _ = PROVIDER.set(provider);
+
+        Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+    }
+
+    pub fn shutdown() {
+        if let Some(provider) = PROVIDER.get()
+            && let Err(e) = provider.shutdown()
+        {
+            tracing::warn!("Failed to flush OpenTelemetry spans: {}", e);
+        }
+    }
+
+    /// Reads trace context from stored webhook headers (names are lowercase)
+    struct HeaderExtractor<'a>(&'a HashMap<String, Vec<String>>);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0
+                .get(key)
+                .and_then(|values| values.first())
+                .map(String::as_str)
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(String::as_str).collect()
+        }
+    }
+
+    pub fn link_sender_trace(headers: &HashMap<String, Vec<String>>) {
+        let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+        let span_context = context.span().span_context().clone();
+        if span_context.is_valid() {
+            tracing::Span::current().add_link(span_context);
+        }
+    }
+}
+
+/// Layer exporting spans over OTLP, or `None` when `OTEL_EXPORTER_OTLP_ENDPOINT` is not set
+#[cfg(feature = "opentelemetry")]
+pub fn layer<S>() -> anyhow::Result<Option<impl tracing_subscriber::Layer<S>>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    otel::layer()
+}
+
+#[cfg(not(feature = "opentelemetry"))]
+pub fn layer<S>() -> anyhow::Result<Option<impl tracing_subscriber::Layer<S>>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    Ok(None::<tracing_subscriber::layer::Identity>)
+}
+
+/// Flush spans that have not been exported yet
+pub fn shutdown() {
+    #[cfg(feature = "opentelemetry")]
+    otel::shutdown();
+}
+
+/// Link the current span to the sender's trace from W3C `traceparent`/`tracestate` headers
+pub fn link_sender_trace(headers: &HashMap<String, Vec<String>>) {
+    #[cfg(feature = "opentelemetry")]
+    otel::link_sender_trace(headers);
+
+    #[cfg(not(feature = "opentelemetry"))]
+    let _ = headers;
+}
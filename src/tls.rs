@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use axum::Router;
+use axum_server::Handle;
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::ServerConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+/// Build a rustls server config from a PEM certificate chain and private key
+pub fn load_server_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read TLS certificate from {}", cert_path))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("Failed to read TLS private key from {}", key_path))?;
+
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Invalid TLS certificate or private key")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Serve HTTPS on `listener` until `shutdown` resolves, then drain open connections
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls_config: RustlsConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown.await;
+            handle.graceful_shutdown(None);
+        }
+    });
+
+    axum_server::from_tcp_rustls(listener.into_std()?, tls_config)?
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await?;
+    Ok(())
+}
+
+/// Reload the certificate and key on SIGHUP, e.g. after certbot renewed them.
+/// Connections keep the config they were accepted with; new ones use the reloaded one.
+pub fn spawn_reload_on_sighup(tls_config: RustlsConfig, cert_path: String, key_path: String) {
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                warn!(
+                    "Failed to listen for SIGHUP, TLS certificates will not be reloaded: {}",
+                    e
+                );
+                return;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            match load_server_config(&cert_path, &key_path) {
+                Ok(config) => {
+                    tls_config.reload_from_config(config);
+                    info!("Reloaded TLS certificate from {}", cert_path);
+                }
+                // Keep serving the previous certificate rather than failing every handshake
+                Err(e) => warn!("Failed to reload TLS certificate: {:#}", e),
+            }
+        }
+    });
+
+    #[cfg(not(unix))]
+    let _ = (tls_config, cert_path, key_path);
+}
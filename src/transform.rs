@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// One change made to a token's parsed webhook bodies before they are stored. Paths select a
+/// field from the document root, e.g. `$.customer.email`; numeric segments index into arrays
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Rule {
+    /// Delete the field
+    RemoveField { path: String },
+    /// Move the field to the key `to` of the same object
+    RenameField { path: String, to: String },
+    /// Set the field to `value`, creating missing parent objects
+    SetField { path: String, value: Value },
+    /// Cut a string field down to its first `max_length` characters
+    TruncateField { path: String, max_length: usize },
+}
+
+impl Rule {
+    fn path(&self) -> &str {
+        match self {
+            Rule::RemoveField { path }
+            | Rule::RenameField { path, .. }
+            | Rule::SetField { path, .. }
+            | Rule::TruncateField { path, .. } => path,
+        }
+    }
+
+    /// Reject paths and renames that could never apply
+    pub fn validate(&self) -> Result<(), String> {
+        parse_path(self.path())?;
+        if let Rule::RenameField { to, .. } = self
+            && to.is_empty()
+        {
+            return Err("rename_field needs a non-empty 'to'".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Applies a token's rules to parsed webhook bodies
+pub struct Transformer;
+
+impl Transformer {
+    /// Apply `rules` in order; rules whose field is missing leave the body unchanged
+    pub fn apply(rules: &[Rule], body_object: &mut Value) {
+        for rule in rules {
+            let Ok(segments) = parse_path(rule.path()) else {
+                continue;
+            };
+            let Some((key, parents)) = segments.split_last() else {
+                continue;
+            };
+            let create = matches!(rule, Rule::SetField { .. });
+            let Some(parent) = descend(body_object, parents, create) else {
+                continue;
+            };
+
+            match rule {
+                Rule::RemoveField { .. } => match parent {
+                    Value::Object(fields) => {
+                        fields.remove(*key);
+                    }
+                    Value::Array(elements) => {
+                        if let Some(index) = array_index(elements, key) {
+                            elements.remove(index);
+                        }
+                    }
+                    _ => {}
+                },
+                Rule::RenameField { to, .. } => {
+                    if let Value::Object(fields) = parent
+                        && let Some(value) = fields.remove(*key)
+                    {
+                        fields.insert(to.clone(), value);
+                    }
+                }
+                Rule::SetField { value, .. } => match parent {
+                    Value::Object(fields) => {
+                        fields.insert(key.to_string(), value.clone());
+                    }
+                    Value::Array(elements) => {
+                        if let Some(index) = array_index(elements, key) {
+                            elements[index] = value.clone();
+                        }
+                    }
+                    _ => {}
+                },
+                Rule::TruncateField { max_length, .. } => {
+                    if let Some(Value::String(text)) = child(parent, key)
+                        && let Some((end, _)) = text.char_indices().nth(*max_length)
+                    {
+                        text.truncate(end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The JSON Pointer (RFC 6901) selecting the same field as `path`, e.g. `/event/message`
+/// for `$.event.message`
+pub fn json_pointer(path: &str) -> Result<String, String> {
+    Ok(parse_path(path)?
+        .iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect())
+}
+
+/// Split `$.a.b` into `["a", "b"]`
+fn parse_path(path: &str) -> Result<Vec<&str>, String> {
+    let segments: Vec<&str> = path
+        .strip_prefix("$.")
+        .ok_or_else(|| format!("path '{}' must start with '$.'", path))?
+        .split('.')
+        .collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(format!("path '{}' has an empty segment", path));
+    }
+    Ok(segments)
+}
+
+/// The value at `segments` below `value`; with `create`, missing fields of objects are
+/// added as empty objects on the way
+fn descend<'a>(value: &'a mut Value, segments: &[&str], create: bool) -> Option<&'a mut Value> {
+    let Some((first, rest)) = segments.split_first() else {
+        return Some(value);
+    };
+    let next = if create && let Value::Object(fields) = value {
+        fields
+            .entry(first.to_string())
+            .or_insert_with(|| Value::Object(Map::new()))
+    } else {
+        child(value, first)?
+    };
+    descend(next, rest, create)
+}
+
+fn child<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Object(fields) => fields.get_mut(key),
+        Value::Array(elements) => {
+            let index = array_index(elements, key)?;
+            elements.get_mut(index)
+        }
+        _ => None,
+    }
+}
+
+fn array_index(elements: &[Value], key: &str) -> Option<usize> {
+    key.parse().ok().filter(|&index| index < elements.len())
+}
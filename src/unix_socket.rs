@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use axum::extract::ConnectInfo;
+use axum::{Extension, Router};
+use std::fs::Permissions;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::net::UnixListener;
+use tracing::warn;
+
+/// Removes the socket file when dropped, i.e. when the server exits
+pub struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            warn!("Failed to remove socket {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Bind a Unix domain socket that the owner and group can connect to
+pub fn bind(path: &Path) -> Result<(UnixListener, SocketFile)> {
+    // A socket left behind by a process that did not exit cleanly would make bind fail
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind unix socket {}", path.display()))?;
+    let socket_file = SocketFile(path.to_path_buf());
+    std::fs::set_permissions(path, Permissions::from_mode(0o660))
+        .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+
+    Ok((listener, socket_file))
+}
+
+/// Serve `app` on the socket until `shutdown` resolves, then drain open connections
+pub async fn serve(
+    listener: UnixListener,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    // Socket peers have no IP address; report them as local so handlers can still extract
+    // one. `BIND_UNIX` requires `TRUST_PROXY`, so the client address comes from the proxy
+    let app = app.layer(Extension(ConnectInfo(SocketAddr::from((
+        Ipv4Addr::LOCALHOST,
+        0,
+    )))));
+    axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
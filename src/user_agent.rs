@@ -0,0 +1,93 @@
+use woothee::parser::Parser;
+use woothee::woothee::VALUE_UNKNOWN;
+
+use crate::models::ClientKind;
+
+/// Product names of HTTP client libraries and command line tools, lowercase. Matched
+/// against the name before the `/` of each product in the header, e.g. `curl/8.5.0`
+const LIBRARY_PRODUCTS: &[&str] = &[
+    "aiohttp",
+    "apache-httpclient",
+    "axios",
+    "curl",
+    "dart",
+    "faraday",
+    "go-http-client",
+    "got",
+    "guzzlehttp",
+    "httpie",
+    "java",
+    "libwww-perl",
+    "node",
+    "node-fetch",
+    "okhttp",
+    "postmanruntime",
+    "python-httpx",
+    "python-requests",
+    "python-urllib",
+    "reqwest",
+    "rest-client",
+    "ruby",
+    "undici",
+    "wget",
+];
+
+/// What a `User-Agent` header says about the sender
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserAgentInfo {
+    /// Browser name, e.g. `Chrome`, or the library product, e.g. `python-requests`; for other
+    /// senders the first product of the header, e.g. `Stripe`
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    /// `pc`, `smartphone`, `mobilephone`, `appliance`, `crawler` or `misc`
+    pub device_type: Option<String>,
+    pub client: Option<ClientKind>,
+}
+
+/// Parse a `User-Agent` header; parts that are not recognized are left empty
+pub fn parse(user_agent: &str) -> UserAgentInfo {
+    let parsed = Parser::new().parse(user_agent).unwrap_or_default();
+    let library = library_product(user_agent);
+
+    let client = if library.is_some() {
+        Some(ClientKind::Library)
+    } else if parsed.browser_type == "browser" {
+        Some(ClientKind::Browser)
+    } else {
+        None
+    };
+    UserAgentInfo {
+        browser: library
+            .or_else(|| known(parsed.name))
+            .or_else(|| product_names(user_agent).next())
+            .map(str::to_string),
+        os: known(parsed.os).map(str::to_string),
+        device_type: known(parsed.category).map(str::to_string),
+        client,
+    }
+}
+
+/// The first product of the header that is an HTTP library, as sent
+fn library_product(user_agent: &str) -> Option<&str> {
+    product_names(user_agent)
+        .find(|name| LIBRARY_PRODUCTS.contains(&name.to_ascii_lowercase().as_str()))
+}
+
+/// Names of the `name/version` products of the header, skipping comments in parentheses
+fn product_names(user_agent: &str) -> impl Iterator<Item = &str> {
+    user_agent
+        .split_whitespace()
+        .scan(0usize, |depth, word| {
+            let in_comment = *depth > 0 || word.starts_with('(');
+            *depth = (*depth + word.matches('(').count()).saturating_sub(word.matches(')').count());
+            Some((!in_comment).then_some(word))
+        })
+        .flatten()
+        .filter_map(|product| product.split('/').next())
+        .filter(|name| !name.is_empty())
+}
+
+/// `value` unless woothee could not tell it
+fn known(value: &str) -> Option<&str> {
+    (!value.is_empty() && value != VALUE_UNKNOWN).then_some(value)
+}
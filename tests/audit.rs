@@ -0,0 +1,67 @@
+mod common;
+
+use common::{TestApp, json};
+
+/// The token's audit entries, newest first
+async fn audit_entries(app: &TestApp, token: &str) -> Vec<serde_json::Value> {
+    let response = app
+        .client
+        .get(app.url(&format!("/api/audit?token_id={}", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    json(response).await.as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn deleting_a_token_is_audited() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+
+    let response = app
+        .client
+        .delete(app.url(&format!("/api/tokens/{}", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let entries = audit_entries(&app, &token).await;
+    let actions: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, vec!["token.delete", "token.create"]);
+    let deleted = &entries[0];
+    assert_eq!(deleted["token_id"], token.as_str());
+    assert_eq!(deleted["actor_ip"], "127.0.0.1");
+    assert!(deleted["details"].is_null());
+}
+
+#[tokio::test]
+async fn batch_creation_audits_every_token() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .client
+        .post(app.url("/api/tokens/batch"))
+        .header("content-type", "application/json")
+        .body(r#"{"count":3,"label_prefix":"batch-"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let created = json(response).await;
+
+    for (index, token_info) in created.as_array().unwrap().iter().enumerate() {
+        let entries = audit_entries(&app, token_info["token"].as_str().unwrap()).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["action"], "token.create");
+        assert_eq!(entries[0]["details"]["batch"], true);
+        assert_eq!(
+            entries[0]["details"]["label"],
+            format!("batch-{}", index + 1)
+        );
+    }
+}
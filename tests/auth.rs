@@ -0,0 +1,134 @@
+mod common;
+
+use common::{TestApp, json};
+
+const ADMIN_KEY: &str = "test-admin-key";
+
+async fn spawn_with_admin_key() -> TestApp {
+    TestApp::spawn_with(|config| config.admin_api_key = Some(ADMIN_KEY.to_string())).await
+}
+
+#[tokio::test]
+async fn api_is_open_without_an_admin_key() {
+    let app = TestApp::spawn().await;
+
+    let response = app.client.get(app.url("/api/tokens")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn api_rejects_a_missing_bearer() {
+    let app = spawn_with_admin_key().await;
+
+    let response = app.client.get(app.url("/api/tokens")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+}
+
+#[tokio::test]
+async fn api_rejects_a_wrong_bearer() {
+    let app = spawn_with_admin_key().await;
+
+    let response = app
+        .client
+        .get(app.url("/api/tokens"))
+        .bearer_auth("not-the-admin-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+}
+
+#[tokio::test]
+async fn api_accepts_the_admin_key() {
+    let app = spawn_with_admin_key().await;
+
+    let response = app
+        .client
+        .get(app.url("/api/tokens"))
+        .bearer_auth(ADMIN_KEY)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn webhooks_need_no_admin_key() {
+    let app = spawn_with_admin_key().await;
+    let response = app
+        .client
+        .post(app.url("/api/tokens"))
+        .bearer_auth(ADMIN_KEY)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let token = json(response).await["token"].as_str().unwrap().to_string();
+
+    app.post_webhook(&token, r#"{"event":"ping"}"#).await;
+
+    let response = app
+        .client
+        .get(app.url(&format!("/api/tokens/{}/requests", token)))
+        .bearer_auth(ADMIN_KEY)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        json(response).await["requests"].as_array().unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn readonly_key_only_reads_its_own_token() {
+    let app = spawn_with_admin_key().await;
+    let create_token = || async {
+        let response = app
+            .client
+            .post(app.url("/api/tokens"))
+            .bearer_auth(ADMIN_KEY)
+            .send()
+            .await
+            .unwrap();
+        json(response).await["token"].as_str().unwrap().to_string()
+    };
+    let (token, other) = (create_token().await, create_token().await);
+
+    let response = app
+        .client
+        .post(app.url(&format!("/api/tokens/{}/readonly-key", token)))
+        .bearer_auth(ADMIN_KEY)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let key = json(response).await["readonly_key"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = app
+        .client
+        .get(app.url(&format!("/api/tokens/{}/requests", token)))
+        .bearer_auth(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Other tokens and other routes of the token treat it as a wrong key
+    for request in [
+        app.client
+            .get(app.url(&format!("/api/tokens/{}/requests", other))),
+        app.client.get(app.url(&format!("/api/tokens/{}", token))),
+        app.client
+            .delete(app.url(&format!("/api/tokens/{}/requests", token))),
+    ] {
+        let response = request.bearer_auth(&key).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+    }
+}
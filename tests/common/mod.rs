@@ -0,0 +1,269 @@
+//! Shared setup for the integration tests: a fully wired service on a temporary SQLite
+//! database, served on an ephemeral port. With `TEST_POSTGRES_URL` set, each test gets its
+//! own database on that PostgreSQL server instead
+#![allow(dead_code)]
+
+use dashmap::DashMap;
+use sqlx::{Connection, Executor, PgConnection};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tempfile::TempDir;
+use tokio::sync::{Notify, RwLock};
+use tokio::task::JoinHandle;
+
+use webhook_service::circuit_breaker::CircuitBreaker;
+use webhook_service::config::Config;
+use webhook_service::database;
+use webhook_service::handlers::{AppState, create_router};
+use webhook_service::metrics::Metrics;
+use webhook_service::services::{
+    ForwardingService, NotificationService, RateLimiter, TokenCache, TokenService, WebhookService,
+};
+
+pub struct TestApp {
+    pub addr: SocketAddr,
+    pub state: AppState,
+    pub client: reqwest::Client,
+    shutdown: Arc<Notify>,
+    server: JoinHandle<()>,
+    // Removes the database when the test ends
+    _database: TestDatabase,
+}
+
+impl TestApp {
+    pub async fn spawn() -> Self {
+        Self::spawn_with(|_| {}).await
+    }
+
+    /// Serve the app with `configure` applied to the test configuration
+    pub async fn spawn_with(configure: impl FnOnce(&mut Config)) -> Self {
+        let database = TestDatabase::create().await;
+        let mut config = database.config();
+        configure(&mut config);
+        let state = app_state(&config).await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = create_router(state.clone(), &config);
+        let shutdown = Arc::new(Notify::new());
+        let server = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(async move { shutdown.notified().await })
+                .await
+                .unwrap();
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            client: reqwest::Client::builder().no_proxy().build().unwrap(),
+            shutdown,
+            server,
+            _database: database,
+        }
+    }
+
+    /// Start a graceful shutdown: no new connections, open ones finish their requests
+    pub fn shut_down(&self) {
+        self.shutdown.notify_one();
+    }
+
+    /// Wait for the server to stop after `shut_down`
+    pub async fn stopped(&mut self) {
+        (&mut self.server).await.unwrap();
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Create a token through the API and return its ID
+    pub async fn create_token(&self) -> String {
+        let response = self
+            .client
+            .post(self.url("/api/tokens"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let token = json(response).await;
+        token["token"].as_str().unwrap().to_string()
+    }
+
+    /// Send a webhook to the token and return the stored request's ID
+    pub async fn post_webhook(&self, token: &str, body: &str) -> String {
+        let response = self
+            .client
+            .post(self.url(&format!("/{}", token)))
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let accepted = json(response).await;
+        accepted["id"].as_str().unwrap().to_string()
+    }
+
+    /// IDs of the token's stored requests, newest first
+    pub async fn request_ids(&self, token: &str) -> Vec<String> {
+        let response = self
+            .client
+            .get(self.url(&format!("/api/tokens/{}/requests", token)))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let page = json(response).await;
+        page["requests"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|request| request["Id"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
+
+/// The response body as JSON
+pub async fn json(response: reqwest::Response) -> serde_json::Value {
+    serde_json::from_slice(&response.bytes().await.unwrap()).unwrap()
+}
+
+/// A database for one test, removed when it is dropped, and a temporary directory for any
+/// other files the test needs
+pub struct TestDatabase {
+    dir: TempDir,
+    /// Server URL and name of the PostgreSQL database, when `TEST_POSTGRES_URL` is set
+    postgres: Option<(url::Url, String)>,
+}
+
+impl TestDatabase {
+    pub async fn create() -> Self {
+        let dir = TempDir::new().unwrap();
+        let Ok(server_url) = std::env::var("TEST_POSTGRES_URL") else {
+            return Self {
+                dir,
+                postgres: None,
+            };
+        };
+
+        let server_url = url::Url::parse(&server_url).unwrap();
+        let name = format!("webhook_test_{}", uuid::Uuid::new_v4().simple());
+        let mut connection = PgConnection::connect(server_url.as_str()).await.unwrap();
+        connection
+            .execute(format!("CREATE DATABASE {}", name).as_str())
+            .await
+            .unwrap();
+        Self {
+            dir,
+            postgres: Some((server_url, name)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Defaults as if no environment variables were set, on this database
+    pub fn config(&self) -> Config {
+        let mut config = Config::from_env().unwrap();
+        config.database_url = self.postgres.as_ref().map(|(server_url, name)| {
+            let mut url = server_url.clone();
+            url.set_path(name);
+            url.into()
+        });
+        config.db_path = self
+            .path()
+            .join("webhook_service.db")
+            .to_string_lossy()
+            .into_owned();
+        config.base_url = None;
+        config.admin_api_key = None;
+        config.jwt_secret = None;
+        config.trust_proxy = false;
+        config.max_tokens = 0;
+        config.rate_limit_requests = 0;
+        config.ip_token_create_limit = 0;
+        config.geo_db_path = None;
+        config.http_proxy = None;
+        config.https_proxy = None;
+        config.socks5_proxy = None;
+        config.no_proxy = None;
+        config.smtp_host = None;
+        config
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let Some((server_url, name)) = self.postgres.take() else {
+            return;
+        };
+        // The test's runtime may be shutting down, so the database is dropped from a thread
+        // with a runtime of its own; FORCE closes the connections the server still holds
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    let mut connection = PgConnection::connect(server_url.as_str()).await?;
+                    connection
+                        .execute(format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name).as_str())
+                        .await
+                })
+        })
+        .join()
+        .unwrap()
+        .unwrap();
+    }
+}
+
+/// Wire up the services the way `main` does, without the background tasks
+pub async fn app_state(config: &Config) -> AppState {
+    let db = database::connect(config).await.unwrap();
+    let metrics = Arc::new(Metrics::new().unwrap());
+    let token_cache = TokenCache::new(config);
+    let circuit_breaker = CircuitBreaker::new(config);
+
+    let forwarding =
+        ForwardingService::new(db.clone(), circuit_breaker.clone(), metrics.clone(), config)
+            .unwrap();
+    let notifications = NotificationService::new(db.clone(), &forwarding, config).unwrap();
+    let webhook_service = WebhookService::new(
+        db.clone(),
+        metrics.clone(),
+        forwarding,
+        notifications.clone(),
+        RateLimiter::new(config),
+        token_cache.clone(),
+        config,
+    );
+    let token_service = TokenService::new(
+        db.clone(),
+        config,
+        metrics.clone(),
+        token_cache,
+        circuit_breaker,
+        Arc::new(DashMap::new()),
+        notifications,
+    );
+
+    AppState {
+        webhook_service,
+        token_service,
+        db,
+        metrics,
+        config: Arc::new(config.clone()),
+        started_at: Instant::now(),
+        stats_cache: Arc::new(RwLock::new(None)),
+    }
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::TestApp;
+
+/// Send a browser preflight for a POST from `origin` to the token's webhook URL
+async fn preflight(app: &TestApp, token: &str, origin: &str) -> reqwest::Response {
+    app.client
+        .request(reqwest::Method::OPTIONS, app.url(&format!("/{}", token)))
+        .header("origin", origin)
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "content-type")
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn preflight_allows_only_the_token_origins() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    let response = app
+        .client
+        .post(app.url(&format!("/api/tokens/{}/cors", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"origins":["https://app.example.com"]}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let response = preflight(&app, &token, "https://app.example.com").await;
+    assert_eq!(response.status(), 204);
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    assert_eq!(headers["access-control-allow-methods"], "POST");
+    assert_eq!(headers["access-control-allow-headers"], "content-type");
+
+    let response = preflight(&app, &token, "https://other.example.com").await;
+    assert_eq!(response.status(), 204);
+    assert!(
+        !response
+            .headers()
+            .contains_key("access-control-allow-origin")
+    );
+
+    // Preflights are not captured as webhooks
+    assert!(app.request_ids(&token).await.is_empty());
+}
+
+#[tokio::test]
+async fn preflight_without_origins_is_not_allowed() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+
+    let response = preflight(&app, &token, "https://app.example.com").await;
+    assert_eq!(response.status(), 204);
+    assert!(
+        !response
+            .headers()
+            .contains_key("access-control-allow-origin")
+    );
+}
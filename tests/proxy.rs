@@ -0,0 +1,101 @@
+mod common;
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use common::TestApp;
+
+/// A forward proxy that reports the request line of every request it receives. CONNECT
+/// tunnels are accepted and then closed, so TLS to the target fails after the handshake
+async fn spawn_proxy() -> (String, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (lines, received) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let lines = lines.clone();
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => head.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head);
+                let line = head.lines().next().unwrap_or_default().to_string();
+
+                let response: &[u8] = if line.starts_with("CONNECT ") {
+                    b"HTTP/1.1 200 Connection Established\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                };
+                let _ = stream.write_all(response).await;
+                let _ = lines.send(line);
+            });
+        }
+    });
+    (url, received)
+}
+
+/// Forward the token's webhooks to `url` and send it one
+async fn forward_webhook(app: &TestApp, url: &str) {
+    let token = app.create_token().await;
+    let response = app
+        .client
+        .put(app.url(&format!("/api/tokens/{}/forward", token)))
+        .header("content-type", "application/json")
+        .body(serde_json::json!({ "url": url }).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    app.post_webhook(&token, r#"{"event":"ping"}"#).await;
+}
+
+async fn next_line(received: &mut mpsc::UnboundedReceiver<String>) -> String {
+    tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("the proxy received no request")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn https_forwards_tunnel_through_the_proxy() {
+    let (proxy_url, mut received) = spawn_proxy().await;
+    let app = TestApp::spawn_with(|config| {
+        config.https_proxy = Some(proxy_url);
+        config.forward_max_retries = 0;
+    })
+    .await;
+
+    // The target does not resolve; only the proxy could have connected to it
+    forward_webhook(&app, "https://target.invalid:8443/hook").await;
+
+    assert_eq!(
+        next_line(&mut received).await,
+        "CONNECT target.invalid:8443 HTTP/1.1"
+    );
+}
+
+#[tokio::test]
+async fn http_forwards_go_to_the_proxy_in_absolute_form() {
+    let (proxy_url, mut received) = spawn_proxy().await;
+    let app = TestApp::spawn_with(|config| {
+        config.http_proxy = Some(proxy_url);
+        config.forward_max_retries = 0;
+    })
+    .await;
+
+    forward_webhook(&app, "http://target.invalid/hook").await;
+
+    assert_eq!(
+        next_line(&mut received).await,
+        "POST http://target.invalid/hook HTTP/1.1"
+    );
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use common::{TestApp, json};
+
+#[tokio::test]
+async fn deleting_a_request_keeps_the_others() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    let kept = app.post_webhook(&token, r#"{"n":1}"#).await;
+    let deleted = app.post_webhook(&token, r#"{"n":2}"#).await;
+
+    let response = app
+        .client
+        .delete(app.url(&format!("/api/tokens/{}/requests/{}", token, deleted)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    assert_eq!(app.request_ids(&token).await, vec![kept]);
+
+    // The request is gone, so deleting it again fails
+    let response = app
+        .client
+        .delete(app.url(&format!("/api/tokens/{}/requests/{}", token, deleted)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn custom_response_answers_webhooks_until_cleared() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    // Caches the token without a custom response
+    app.post_webhook(&token, r#"{"n":1}"#).await;
+
+    let response = app
+        .client
+        .post(app.url(&format!("/api/tokens/{}/response", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"status_code":202,"body":"queued"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = app
+        .client
+        .post(app.url(&format!("/{}", token)))
+        .body("{}")
+        .header("content-type", "application/json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    assert_eq!(response.text().await.unwrap(), "queued");
+
+    let response = app
+        .client
+        .delete(app.url(&format!("/api/tokens/{}/response", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    app.post_webhook(&token, r#"{"n":2}"#).await;
+}
+
+#[tokio::test]
+async fn tokens_can_accept_bodies_above_the_server_limit() {
+    let app = TestApp::spawn_with(|config| config.max_payload_bytes = 1024).await;
+    let token = app.create_token().await;
+    let body = format!(r#"{{"data":"{}"}}"#, "x".repeat(2048));
+    let send = || {
+        app.client
+            .post(app.url(&format!("/{}", token)))
+            .header("content-type", "application/json")
+            .body(body.clone())
+            .send()
+    };
+
+    assert_eq!(send().await.unwrap().status(), 413);
+
+    let response = app
+        .client
+        .patch(app.url(&format!("/api/tokens/{}", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"max_payload_bytes":4096}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(send().await.unwrap().status(), 200);
+}
+
+#[tokio::test]
+async fn request_log_etag_follows_changes_to_any_request() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    let oldest = app.post_webhook(&token, r#"{"n":1}"#).await;
+    app.post_webhook(&token, r#"{"n":2}"#).await;
+
+    let etag = |query: &'static str| {
+        let url = app.url(&format!("/api/tokens/{}/requests{}", token, query));
+        let client = app.client.clone();
+        async move {
+            let response = client.get(url).send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            response.headers()["etag"].to_str().unwrap().to_string()
+        }
+    };
+
+    let unfiltered = etag("").await;
+    assert_eq!(etag("").await, unfiltered);
+    assert_ne!(etag("?limit=1").await, unfiltered);
+    assert_eq!(
+        etag("?limit=1&method=POST").await,
+        etag("?method=POST&limit=1").await
+    );
+
+    let response = app
+        .client
+        .get(app.url(&format!("/api/tokens/{}/requests", token)))
+        .header("if-none-match", &unfiltered)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 304);
+
+    // Flagging the oldest request changes the log without adding to it
+    let response = app
+        .client
+        .post(app.url(&format!("/api/tokens/{}/requests/{}/flag", token, oldest)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_ne!(etag("").await, unfiltered);
+}
+
+#[tokio::test]
+async fn clearing_flags_unflags_every_request_of_the_token() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    let other = app.create_token().await;
+    let mut flagged = Vec::new();
+    for (token, body) in [(&token, "1"), (&token, "2"), (&other, "3")] {
+        flagged.push((token, app.post_webhook(token, body).await));
+    }
+    for (token, request_id) in &flagged {
+        let response = app
+            .client
+            .post(app.url(&format!(
+                "/api/tokens/{}/requests/{}/flag",
+                token, request_id
+            )))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    let response = app
+        .client
+        .delete(app.url(&format!("/api/tokens/{}/flags", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(json(response).await["unflagged"], 2);
+
+    let flagged_ids = |token: &str| {
+        let url = app.url(&format!("/api/tokens/{}/requests?flagged=true", token));
+        let client = app.client.clone();
+        async move {
+            let page = json(client.get(url).send().await.unwrap()).await;
+            page["requests"].as_array().unwrap().len()
+        }
+    };
+    assert_eq!(flagged_ids(&token).await, 0);
+    assert_eq!(flagged_ids(&other).await, 1);
+}
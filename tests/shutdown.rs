@@ -0,0 +1,39 @@
+mod common;
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn request_in_progress_at_shutdown_is_stored() {
+    let mut app = TestApp::spawn().await;
+    let token = app.create_token().await;
+
+    // The headers arrive before the shutdown, the body only after it
+    let body = r#"{"n":1}"#;
+    let mut stream = TcpStream::connect(app.addr).await.unwrap();
+    let head = format!(
+        "POST /{} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        token,
+        app.addr,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    app.shut_down();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    stream.write_all(body.as_bytes()).await.unwrap();
+
+    // The connection is closed once the response is sent
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    app.stopped().await;
+
+    let stored = app.state.db.get_webhook_requests(&token, 10).await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].message_object.body.as_deref(), Some(body));
+}
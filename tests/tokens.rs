@@ -0,0 +1,246 @@
+mod common;
+
+use std::collections::HashSet;
+
+use common::{TestApp, json};
+
+#[tokio::test]
+async fn update_with_an_invalid_field_changes_nothing() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+
+    let response = app
+        .client
+        .patch(app.url(&format!("/api/tokens/{}", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"label":"orders","max_requests":5,"response_delay_ms":999999}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = app
+        .client
+        .get(app.url(&format!("/api/tokens/{}", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let token_info = json(response).await;
+    assert!(token_info["label"].is_null());
+    assert!(token_info["max_requests"].is_null());
+    assert!(token_info["response_delay_ms"].is_null());
+}
+
+#[tokio::test]
+async fn update_stores_every_field() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+
+    let response = app
+        .client
+        .patch(app.url(&format!("/api/tokens/{}", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"label":"orders","max_requests":5,"allowed_methods":["PUT","POST"]}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let token_info = json(response).await;
+    assert_eq!(token_info["label"], "orders");
+    assert_eq!(token_info["max_requests"], 5);
+    assert_eq!(
+        token_info["allowed_methods"],
+        serde_json::json!(["POST", "PUT"])
+    );
+}
+
+#[tokio::test]
+async fn deleted_token_stops_accepting_webhooks() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    // Caches the token
+    app.post_webhook(&token, r#"{"n":1}"#).await;
+
+    let response = app
+        .client
+        .delete(app.url(&format!("/api/tokens/{}", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(send_webhook(&app, &token).await, 404);
+
+    // Re-creating it under the same ID replaces the cached miss
+    let response = app
+        .client
+        .post(app.url("/api/tokens"))
+        .header("content-type", "application/json")
+        .body(format!(r#"{{"id":"{}"}}"#, token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(send_webhook(&app, &token).await, 200);
+}
+
+#[tokio::test]
+async fn setting_changes_apply_to_the_next_webhook() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+    // Caches the token
+    app.post_webhook(&token, r#"{"n":1}"#).await;
+
+    let response = app
+        .client
+        .post(app.url(&format!("/api/tokens/{}/disable", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(send_webhook(&app, &token).await, 403);
+
+    let response = app
+        .client
+        .patch(app.url(&format!("/api/tokens/{}", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"allowed_methods":["PUT"]}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = app
+        .client
+        .post(app.url(&format!("/api/tokens/{}/enable", token)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(send_webhook(&app, &token).await, 405);
+}
+
+#[tokio::test]
+async fn webhooks_are_rate_limited_before_the_token_lookup() {
+    let app = TestApp::spawn_with(|config| config.rate_limit_requests = 1).await;
+    let token = uuid::Uuid::new_v4().to_string();
+
+    assert_eq!(send_webhook(&app, &token).await, 404);
+    assert_eq!(send_webhook(&app, &token).await, 429);
+}
+
+async fn send_webhook(app: &TestApp, token: &str) -> u16 {
+    app.client
+        .post(app.url(&format!("/{}", token)))
+        .header("content-type", "application/json")
+        .body(r#"{"n":2}"#)
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn batch_created_tokens_are_all_retrievable() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .client
+        .post(app.url("/api/tokens/batch"))
+        .header("content-type", "application/json")
+        .body(r#"{"count":50,"label_prefix":"load-"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let created = json(response).await;
+    let created = created.as_array().unwrap();
+    assert_eq!(created.len(), 50);
+
+    let mut tokens = HashSet::new();
+    for (index, token_info) in created.iter().enumerate() {
+        let token = token_info["token"].as_str().unwrap();
+        assert!(
+            tokens.insert(token.to_string()),
+            "duplicate token {}",
+            token
+        );
+
+        let response = app
+            .client
+            .get(app.url(&format!("/api/tokens/{}", token)))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let stored = json(response).await;
+        assert_eq!(stored["label"], format!("load-{}", index + 1));
+    }
+}
+
+#[tokio::test]
+async fn batch_counts_against_the_creation_limit() {
+    let app = TestApp::spawn_with(|config| config.ip_token_create_limit = 5).await;
+    let create_batch = |count: u32| {
+        app.client
+            .post(app.url("/api/tokens/batch"))
+            .header("content-type", "application/json")
+            .body(format!(r#"{{"count":{}}}"#, count))
+            .send()
+    };
+
+    assert_eq!(create_batch(3).await.unwrap().status(), 200);
+    // Only two more fit in the window, so none of the three are created
+    assert_eq!(create_batch(3).await.unwrap().status(), 429);
+    assert_eq!(app.state.db.count_tokens().await.unwrap(), 3);
+    // More than the limit can never fit
+    assert_eq!(create_batch(6).await.unwrap().status(), 400);
+
+    app.create_token().await;
+    app.create_token().await;
+    let response = app
+        .client
+        .post(app.url("/api/tokens"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 429);
+    assert_eq!(app.state.db.count_tokens().await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn import_counts_only_new_tokens_against_max_tokens() {
+    let app = TestApp::spawn_with(|config| config.max_tokens = 3).await;
+    let existing = [app.create_token().await, app.create_token().await];
+
+    let import = |new_tokens: usize, dry_run: bool| {
+        let tokens: Vec<_> = existing
+            .iter()
+            .cloned()
+            .chain((0..new_tokens).map(|_| uuid::Uuid::new_v4().to_string()))
+            .map(|token| {
+                serde_json::json!({
+                    "token": token,
+                    "created_at": "2024-01-01T00:00:00+00:00",
+                    "webhook_url": "",
+                })
+            })
+            .collect();
+        app.client
+            .post(app.url(&format!("/api/import?dry_run={}", dry_run)))
+            .header("content-type", "application/json")
+            .body(serde_json::json!({ "tokens": tokens }).to_string())
+            .send()
+    };
+
+    // Two new tokens would make four
+    assert_eq!(import(2, true).await.unwrap().status(), 507);
+    assert_eq!(import(2, false).await.unwrap().status(), 507);
+    assert_eq!(app.state.db.count_tokens().await.unwrap(), 2);
+
+    let response = import(1, false).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let summary = json(response).await;
+    assert_eq!(summary["tokens_imported"], 1);
+    assert_eq!(summary["tokens_skipped"], 2);
+}
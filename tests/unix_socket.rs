@@ -0,0 +1,55 @@
+#![cfg(unix)]
+
+mod common;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{HeaderMap, Request};
+use hyper_util::rt::TokioIo;
+use std::net::Ipv4Addr;
+use tokio::net::UnixStream;
+
+use webhook_service::handlers::create_router;
+use webhook_service::unix_socket;
+
+#[tokio::test]
+async fn webhook_over_unix_socket_records_the_forwarded_client() {
+    let database = common::TestDatabase::create().await;
+    let mut config = database.config();
+    config.trust_proxy = true;
+    let state = common::app_state(&config).await;
+    let token = state
+        .token_service
+        .create_token(Ipv4Addr::LOCALHOST.into(), &HeaderMap::new(), None, None)
+        .await
+        .unwrap()
+        .token;
+
+    let socket_path = database.path().join("webhook.sock");
+    let (listener, _socket_file) = unix_socket::bind(&socket_path).unwrap();
+    let app = create_router(state.clone(), &config);
+    tokio::spawn(unix_socket::serve(listener, app, std::future::pending()));
+
+    let stream = UnixStream::connect(&socket_path).await.unwrap();
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+
+    let request = Request::post(format!("/{}", token))
+        .header("host", "localhost")
+        .header("content-type", "application/json")
+        .header("x-forwarded-for", "203.0.113.7")
+        .body(Full::new(Bytes::from_static(br#"{"n":1}"#)))
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let accepted: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let stored = state.db.get_webhook_requests(&token, 10).await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id, accepted["id"]);
+    assert_eq!(stored[0].source_ip.as_deref(), Some("203.0.113.7"));
+    assert_eq!(stored[0].peer_ip.as_deref(), Some("127.0.0.1"));
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn every_websocket_client_receives_the_webhook() {
+    let app = TestApp::spawn().await;
+    let token = app.create_token().await;
+
+    let ws_url = format!("ws://{}/{}/ws", app.addr, token);
+    let (mut first, _) = tokio_tungstenite::connect_async(&ws_url).await.unwrap();
+    let (mut second, _) = tokio_tungstenite::connect_async(&ws_url).await.unwrap();
+
+    // The server only reads from a socket once it has subscribed it to the token, so a pong
+    // means the client will see every webhook from now on
+    for client in [&mut first, &mut second] {
+        client.send(Message::Ping(Vec::new().into())).await.unwrap();
+        let pong = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("no pong received")
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(pong, Message::Pong(_)),
+            "expected a pong, got {:?}",
+            pong
+        );
+    }
+
+    let id = app.post_webhook(&token, r#"{"event":"ping"}"#).await;
+
+    for client in [&mut first, &mut second] {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("no webhook received")
+            .unwrap()
+            .unwrap();
+        let Message::Text(text) = message else {
+            panic!("expected a text message, got {:?}", message);
+        };
+        let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(request["Id"], id);
+        assert_eq!(request["TokenId"], token);
+    }
+}